@@ -0,0 +1,288 @@
+// Turns instructions.csv, the one table of every opcode on every variant, into the Rust the decoder is built on.
+// Mistakes in the table fail the build here rather than showing up as a wrong instruction at runtime.
+use std::{collections::HashMap, env, fmt::Write, fs, path::Path};
+
+const COLUMNS: usize = 11;
+
+// Every CpuVariant, the generated variant_index gives each its row of DECODE_INDEX by its position in here
+const VARIANTS: [&str; 5] = [
+    "Nmos6502",
+    "Wdc65C02",
+    "Rockwell65C02",
+    "Ricoh2A03",
+    "Wdc65C816",
+];
+
+// Status register bits, M and X are the 65C816's width flags
+const FLAGS: [(char, u8); 8] = [
+    ('N', 1 << 7),
+    ('V', 1 << 6),
+    ('M', 1 << 5),
+    ('X', 1 << 4),
+    ('D', 1 << 3),
+    ('I', 1 << 2),
+    ('Z', 1 << 1),
+    ('C', 1),
+];
+
+struct Row {
+    line: usize,
+    opcode: u8,
+    mnemonic: String,
+    addressing_mode: String,
+    bytes: u8,
+    cycles: u8,
+    page_penalty: bool,
+    flags_read: u8,
+    flags_written: u8,
+    undocumented: bool,
+    variants: Vec<usize>,
+    description: String,
+}
+
+fn parse_flags(field: &str, line: usize) -> u8 {
+    if field == "-" {
+        return 0;
+    }
+    return field.chars().fold(0, |mask, letter| {
+        match FLAGS.iter().find(|(flag, _)| *flag == letter) {
+            Some((_, bit)) => mask | bit,
+            None => panic!("instructions.csv:{}: unknown flag {}", line, letter),
+        }
+    });
+}
+
+fn parse_bool(field: &str, line: usize) -> bool {
+    return match field {
+        "true" => true,
+        "false" => false,
+        _ => panic!(
+            "instructions.csv:{}: expected true or false, got {}",
+            line, field
+        ),
+    };
+}
+
+fn parse_row(text: &str, line: usize) -> Row {
+    let fields: Vec<&str> = text.split(',').map(|field| field.trim()).collect();
+    if fields.len() != COLUMNS {
+        panic!(
+            "instructions.csv:{}: expected {} columns, got {}",
+            line,
+            COLUMNS,
+            fields.len()
+        );
+    }
+    let number = |field: &str, radix: u32| -> u8 {
+        return u8::from_str_radix(field, radix)
+            .unwrap_or_else(|_| panic!("instructions.csv:{}: bad number {}", line, field));
+    };
+    let variants = fields[9]
+        .split_whitespace()
+        .map(
+            |name| match VARIANTS.iter().position(|variant| *variant == name) {
+                Some(index) => index,
+                None => panic!("instructions.csv:{}: unknown variant {}", line, name),
+            },
+        )
+        .collect::<Vec<usize>>();
+    if variants.is_empty() {
+        panic!("instructions.csv:{}: no variants", line);
+    }
+    return Row {
+        line,
+        opcode: number(fields[0], 16),
+        mnemonic: fields[1].to_string(),
+        addressing_mode: fields[2].to_string(),
+        bytes: number(fields[3], 10),
+        cycles: number(fields[4], 10),
+        page_penalty: parse_bool(fields[5], line),
+        flags_read: parse_flags(fields[6], line),
+        flags_written: parse_flags(fields[7], line),
+        undocumented: parse_bool(fields[8], line),
+        variants,
+        description: fields[10].to_string(),
+    };
+}
+
+// An opcode means one thing per variant, and a mnemonic or addressing mode means the same thing everywhere
+fn check_rows(rows: &[Row]) {
+    let mut decoded: HashMap<(u8, usize), usize> = HashMap::new();
+    let mut descriptions: HashMap<&str, &str> = HashMap::new();
+    let mut sizes: HashMap<&str, u8> = HashMap::new();
+    for row in rows.iter() {
+        let line = row.line;
+        for &variant in row.variants.iter() {
+            if let Some(other) = decoded.insert((row.opcode, variant), line) {
+                panic!(
+                    "instructions.csv:{}: ${:02X} is already on line {} for {}",
+                    line, row.opcode, other, VARIANTS[variant]
+                );
+            }
+        }
+        let description = descriptions
+            .entry(&row.mnemonic)
+            .or_insert(&row.description);
+        if *description != row.description {
+            panic!(
+                "instructions.csv:{}: {} is described differently elsewhere",
+                line, row.mnemonic
+            );
+        }
+        let size = sizes.entry(&row.addressing_mode).or_insert(row.bytes);
+        if *size != row.bytes {
+            panic!(
+                "instructions.csv:{}: {} is {} bytes long elsewhere",
+                line, row.addressing_mode, size
+            );
+        }
+    }
+}
+
+fn generate(rows: &[Row]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// Generated by build.rs from instructions.csv, edit that instead."
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "pub static OPCODE_TABLE: [OpcodeMetadata; {}] = [",
+        rows.len()
+    )
+    .unwrap();
+    for row in rows {
+        let variants: Vec<String> = row
+            .variants
+            .iter()
+            .map(|&variant| format!("CpuVariant::{}", VARIANTS[variant]))
+            .collect();
+        writeln!(
+            out,
+            "    OpcodeMetadata {{ opcode: 0x{:02X}, mnemonic: Mnemonic::{}, addressing_mode: AddressingModes::{}, \
+             bytes: {}, cycles: {}, page_penalty: {}, flags_read: 0b{:08b}, flags_written: 0b{:08b}, \
+             undocumented: {}, variants: &[{}] }},",
+            row.opcode,
+            row.mnemonic,
+            row.addressing_mode,
+            row.bytes,
+            row.cycles,
+            row.page_penalty,
+            row.flags_read,
+            row.flags_written,
+            row.undocumented,
+            variants.join(", ")
+        )
+        .unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+
+    // Row of OPCODE_TABLE for each opcode of each variant, u16::MAX where the opcode isn't assigned
+    let mut index = vec![[u16::MAX; 256]; VARIANTS.len()];
+    for (position, row) in rows.iter().enumerate() {
+        for &variant in row.variants.iter() {
+            index[variant][row.opcode as usize] = position as u16;
+        }
+    }
+    writeln!(
+        out,
+        "static DECODE_INDEX: [[u16; 256]; {}] = [",
+        VARIANTS.len()
+    )
+    .unwrap();
+    for variant in index.iter() {
+        let entries: Vec<String> = variant.iter().map(|entry| entry.to_string()).collect();
+        writeln!(out, "    [{}],", entries.join(", ")).unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "fn variant_index(variant: CpuVariant) -> usize {{").unwrap();
+    writeln!(out, "    return match variant {{").unwrap();
+    for (position, variant) in VARIANTS.iter().enumerate() {
+        writeln!(out, "        CpuVariant::{} => {},", variant, position).unwrap();
+    }
+    writeln!(out, "    }};").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    // check_rows made sure every row of an addressing mode has the same length. ImmediateWord isn't in the table,
+    // it is what decode turns Immediate into when the register is 16 bits wide, one byte longer.
+    let mut sizes: Vec<(&str, u8)> = Vec::new();
+    for row in rows {
+        if !sizes.iter().any(|(mode, _)| *mode == row.addressing_mode) {
+            sizes.push((&row.addressing_mode, row.bytes));
+        }
+    }
+    let immediate = match sizes.iter().find(|(mode, _)| *mode == "Immediate") {
+        Some((_, bytes)) => *bytes,
+        None => panic!("instructions.csv: no row uses the Immediate addressing mode"),
+    };
+    sizes.push(("ImmediateWord", immediate + 1));
+    writeln!(out, "impl AddressingModes {{").unwrap();
+    writeln!(
+        out,
+        "    /// How many operand bytes follow the opcode, the instruction is one longer."
+    )
+    .unwrap();
+    writeln!(out, "    pub fn parameter_bytes(&self) -> u16 {{").unwrap();
+    writeln!(out, "        return match self {{").unwrap();
+    for (mode, bytes) in sizes {
+        writeln!(
+            out,
+            "            AddressingModes::{} => {},",
+            mode,
+            bytes - 1
+        )
+        .unwrap();
+    }
+    writeln!(out, "        }};").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    let mut described: Vec<(&str, &str)> = Vec::new();
+    for row in rows {
+        if !described
+            .iter()
+            .any(|(mnemonic, _)| *mnemonic == row.mnemonic)
+        {
+            described.push((&row.mnemonic, &row.description));
+        }
+    }
+    writeln!(out, "impl Mnemonic {{").unwrap();
+    writeln!(out, "    /// What the instruction does, in a few words.").unwrap();
+    writeln!(out, "    pub fn description(&self) -> &'static str {{").unwrap();
+    writeln!(out, "        return match self {{").unwrap();
+    for (mnemonic, description) in described {
+        writeln!(
+            out,
+            "            Mnemonic::{} => {:?},",
+            mnemonic, description
+        )
+        .unwrap();
+    }
+    writeln!(out, "        }};").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    return out;
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=instructions.csv");
+    println!("cargo:rerun-if-changed=build.rs");
+    let table = fs::read_to_string("instructions.csv").expect("instructions.csv is missing");
+    let rows: Vec<Row> = table
+        .lines()
+        .enumerate()
+        .skip(1) // Header
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(index, text)| parse_row(text, index + 1))
+        .collect();
+    check_rows(&rows);
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("opcode_table.rs"), generate(&rows)).unwrap();
+}
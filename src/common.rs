@@ -5,3 +5,42 @@ pub fn reverse_endianness(word: u16) -> u16 {
     let new_word: u16 = (new_high_byte << 8) | new_low_byte;
     return new_word;
 }
+
+/// What registers and RAM hold when the power comes on. The real chips and RAMs come up with whatever happens to be
+/// in them, filling them with a pattern or random data helps catch code that reads memory before initializing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PowerOnState {
+    #[default]
+    Zeroed,
+    Pattern(u8),
+    Random(u64), // Seed, the same seed always gives the same contents
+}
+
+impl PowerOnState {
+    /// The values to power on with, one byte per location.
+    pub fn values(&self) -> impl Iterator<Item = u8> {
+        let state = *self;
+        // splitmix64, which takes any seed and gives every seed its own sequence
+        let mut seed = match state {
+            PowerOnState::Random(seed) => seed,
+            _ => 0,
+        };
+        return std::iter::repeat_with(move || match state {
+            PowerOnState::Zeroed => 0,
+            PowerOnState::Pattern(pattern) => pattern,
+            PowerOnState::Random(_) => {
+                seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+                let mut mixed = seed;
+                mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+                mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D049BB133111EB);
+                ((mixed ^ (mixed >> 31)) >> 56) as u8
+            }
+        });
+    }
+
+    pub fn fill(&self, buffer: &mut [u8]) {
+        for (byte, value) in buffer.iter_mut().zip(self.values()) {
+            *byte = value;
+        }
+    }
+}
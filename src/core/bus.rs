@@ -0,0 +1,21 @@
+/// Everything the CPU can see on its address and data lines. Addresses are 24 bits wide so the 65C816's banks fit,
+/// the 8 bit variants only ever drive the low 16 bits.
+///
+/// Both methods take `&mut self` because a read on real hardware is a bus cycle like any other: reading a
+/// memory-mapped register can acknowledge an interrupt, pop a FIFO or clear a status bit.
+pub trait Bus {
+    fn read(&mut self, address: u32) -> u8;
+    fn write(&mut self, address: u32, data: u8);
+
+    /// Looks at an address for tools like the disassembler, without the side effects of a read. Buses with devices on
+    /// them override it so the devices aren't touched, the rest can leave it as a read.
+    fn peek(&mut self, address: u32) -> u8 {
+        return self.read(address);
+    }
+
+    /// Reports an access the bus couldn't complete since the last call, clearing it. The CPU checks this after every
+    /// instruction and stops with EmulationError::BusFault.
+    fn take_fault(&mut self) -> Option<u32> {
+        return None;
+    }
+}
@@ -1,13 +1,104 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::rc::Rc;
 
-use crate::peripherals::memory::VirtualMemory;
+use crate::{common::PowerOnState, peripherals::memory::VirtualMemory};
 
-use super::{instructions::utils::AddressingModes, register::*};
+use super::{
+    bus::Bus,
+    dma::DmaBus,
+    error::EmulationError,
+    instructions::{
+        alu::DecimalMode,
+        decode::{decode, unassigned_nop, Instruction, Mnemonic},
+        utils::{AddressingModes, BranchMode},
+    },
+    io::{BusCycle, BusOperation, PinIO},
+    register::*,
+    variant::CpuVariant,
+};
 
+pub const COP_VECTOR: u16 = 0xFFF4;
+pub const NMI_VECTOR: u16 = 0xFFFA;
+pub const RESET_VECTOR: u16 = 0xFFFC;
+pub const IRQ_VECTOR: u16 = 0xFFFE;
+
+pub const DEFAULT_MAGIC_CONSTANT: u8 = 0xEE;
+
+// The 65C816 has its own set of vectors for native mode, BRK and IRQ no longer share one
+pub const NATIVE_COP_VECTOR: u16 = 0xFFE4;
+pub const NATIVE_BRK_VECTOR: u16 = 0xFFE6;
+pub const NATIVE_NMI_VECTOR: u16 = 0xFFEA;
+pub const NATIVE_IRQ_VECTOR: u16 = 0xFFEE;
+
+// The addressing mode of the instruction being stepped with its base and effective addresses
+type LatchedAddress = (AddressingModes, Option<(u32, u32)>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Running,
+    Waiting, // WAI, wakes up on IRQ, NMI or reset
+    Stopped, // STP, only a reset starts it again
+    Jammed,  // One of the NMOS JAM opcodes locked the chip up, only a reset gets it going again
+}
+
+/// Why one of the run methods gave control back, with the address of the instruction it stopped on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint(u32), // The condition of run_until held, or run_until_pc got to its address
+    CycleBudget,     // run_for_cycles used up its cycles
+    InstructionBudget, // run_for_instructions ran all of its instructions
+    Break(u32),      // A BRK ran with stop_on_break set, running again carries on in its handler
+    Stopped(u32),    // STP stopped the clock
+    Jammed(u32),     // A JAM opcode locked the CPU up
+    Waiting(u32),    // WAI is waiting for an interrupt that won't come without the host
+    SelfJump(u32),   // A jump or branch to itself, the usual way test programs signal they are done
+}
+
+/// Handles an opcode the variant doesn't assign, see IllegalOpcodePolicy::Handler.
+pub type IllegalOpcodeHandler<B> =
+    Box<dyn FnMut(&mut CPU<B>, u8) -> Result<Option<Instruction>, EmulationError>>;
+
+/// What step does with an opcode the selected variant doesn't assign, only the 65C02s have any.
+pub enum IllegalOpcodePolicy<B: Bus = VirtualMemory> {
+    Nop,   // Runs it as the NOP of the same length and cycles the 65C02 treats it as
+    Jam,   // Locks the CPU up like an NMOS JAM opcode, only a reset gets it going again
+    Error, // step returns EmulationError::IllegalOpcode and leaves the program counter on the opcode
+    // Calls the handler with the opcode while the program counter is still on it, step returns whatever it does
+    Handler(IllegalOpcodeHandler<B>),
+}
+
+impl<B: Bus> IllegalOpcodePolicy<B> {
+    /// The policy CPU::with_variant starts with. The 65C02s run their unassigned opcodes as NOPs like the real chips,
+    /// the other variants assign every opcode so Error only ever catches a decoding bug.
+    pub fn for_variant(variant: CpuVariant) -> Self {
+        return match variant {
+            CpuVariant::Wdc65C02 | CpuVariant::Rockwell65C02 => IllegalOpcodePolicy::Nop,
+            _ => IllegalOpcodePolicy::Error,
+        };
+    }
+}
+
+impl<B: Bus> std::fmt::Debug for IllegalOpcodePolicy<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Self::Nop => write!(f, "Nop"),
+            Self::Jam => write!(f, "Jam"),
+            Self::Error => write!(f, "Error"),
+            Self::Handler(_) => write!(f, "Handler"),
+        };
+    }
+}
+
+/// A 65xx CPU wired up to a bus, by default plain RAM in the form of VirtualMemory.
 #[derive(Debug)]
-pub struct CPU {
+pub struct CPU<B: Bus = VirtualMemory> {
+    pub variant: CpuVariant,
+
     // IO
+    pub pins: PinIO,
+    reset_pending: bool,
+    pub run_state: RunState,
 
     // Registers
     pub accumulator_cell: Rc<RefCell<DataRegister>>,
@@ -17,68 +108,1128 @@ pub struct CPU {
     pub stack_pointer: StackPointerRegister,
     pub processor_status_flags: StatusRegister,
 
+    // 65C816 registers, they stay 0 on the other variants
+    pub direct_page: u16, // Base address of the zeropage addressing modes
+    pub data_bank: u8,    // Bank of the absolute addressing modes
+    pub program_bank: u8, // Bank the program counter is in
+
+    // Clock cycles elapsed since power on
+    pub cycles: u64,
+
+    // How ADC and SBC behave with the decimal flag set
+    pub decimal_mode: DecimalMode,
+
+    // The unstable NMOS opcodes XAA and LXA OR the accumulator with a value that varies between chips
+    // and even with temperature before using it, 0xEE is the most commonly measured one.
+    pub magic_constant: u8,
+
+    // What to do with opcodes the variant doesn't assign
+    pub illegal_opcodes: IllegalOpcodePolicy<B>,
+
+    // Makes the run methods give control back after every BRK, otherwise it is an interrupt like any other
+    pub stop_on_break: bool,
+
+    // Cycle stepped mode, see step_cycle
+    pub cycle_stepped: bool,
+    bus_log: RefCell<Vec<BusCycle>>, // Bus cycles of the instruction being stepped
+    pending_cycles: VecDeque<BusCycle>,
+
+    // Operand address of the instruction being stepped, latched so the operand bytes are only read once
+    latched_address: Cell<Option<LatchedAddress>>,
+    // The last byte read or written, handed back instead of going to the bus again while reuse_operand is set
+    last_data: Cell<Option<(u32, u8)>>,
+    pub(crate) reuse_operand: Cell<bool>,
+
     // Memory
-    pub memory_rc: Rc<RefCell<VirtualMemory>>,
+    pub memory_rc: Rc<RefCell<B>>,
 }
 
-impl CPU {
+impl<B: Bus> CPU<B> {
     // TODO: Add configs if needed.
-    pub fn new(memory_arc: Rc<RefCell<VirtualMemory>>) -> Self {
+    pub fn new(memory_arc: Rc<RefCell<B>>) -> Self {
+        return CPU::with_variant(memory_arc, CpuVariant::default());
+    }
+
+    pub fn with_variant(memory_arc: Rc<RefCell<B>>, variant: CpuVariant) -> Self {
         let mem_arc = memory_arc.clone();
         return CPU {
+            variant,
+            pins: PinIO::new(),
+            reset_pending: false,
+            run_state: RunState::Running,
             accumulator_cell: Rc::new(RefCell::new(DataRegister::new(String::from("A")))),
             x_cell: Rc::new(RefCell::new(DataRegister::new(String::from("X")))),
             y_cell: Rc::new(RefCell::new(DataRegister::new(String::from("Y")))),
             program_counter: ProgramCounter::from(0),
-            stack_pointer: StackPointerRegister::new(0x01, 0xFF, memory_arc),
+            stack_pointer: StackPointerRegister::new(0x01, 0xFF),
             processor_status_flags: StatusRegister::new(),
+            direct_page: 0,
+            data_bank: 0,
+            program_bank: 0,
+            cycles: 0,
+            decimal_mode: variant.decimal_mode(),
+            magic_constant: DEFAULT_MAGIC_CONSTANT,
+            illegal_opcodes: IllegalOpcodePolicy::for_variant(variant),
+            stop_on_break: false,
+            cycle_stepped: false,
+            bus_log: RefCell::new(Vec::new()),
+            pending_cycles: VecDeque::new(),
+            latched_address: Cell::new(None),
+            last_data: Cell::new(None),
+            reuse_operand: Cell::new(false),
             memory_rc: mem_arc,
         };
     }
 
+    /// Starts the CPU from the reset vector with the stack pointer left at $FF. A shortcut for setting up programs,
+    /// see reset for what the chip really does.
     pub fn boot_cycle(&mut self) {
-        // TODO: 7 clock cycles
+        self.cycles += 7;
         self.program_counter.reset_register();
-        let program_start_location: u16;
-        {
-            let memory = self.memory_rc.borrow();
-            program_start_location = memory.read_word(0xFFFC);
+        if self.variant.has_native_mode() {
+            // The 65C816 always comes out of reset in emulation mode
+            self.processor_status_flags.set_emulation(true);
+            self.direct_page = 0;
+            self.data_bank = 0;
+            self.program_bank = 0;
+            self.sync_register_widths();
+        }
+        self.program_counter.value = self.read_word(RESET_VECTOR);
+    }
+
+    /// The reset sequence. It takes 7 cycles and runs like an interrupt whose three stack pushes are turned into
+    /// reads, so the stack pointer still goes down by 3. The I flag is set and the CMOS chips clear D, everything
+    /// else stays as it was. A 65C816 goes back into emulation mode.
+    pub fn reset(&mut self) {
+        self.run_state = RunState::Running;
+        let program_address = self.program_address();
+        self.read_byte(program_address);
+        self.read_byte(program_address);
+        for _ in 0..3 {
+            self.read_byte(self.stack_pointer.get_word() as u32);
+            self.stack_pointer.decrement();
+        }
+        self.processor_status_flags
+            .set_flag(StatusFlags::InterruptDisable);
+        if self.variant.is_cmos() {
+            self.processor_status_flags.clear_flag(StatusFlags::Decimal);
+        }
+        if self.variant.has_native_mode() {
+            self.processor_status_flags.set_emulation(true);
+            self.direct_page = 0;
+            self.data_bank = 0;
+            self.program_bank = 0;
+            self.sync_register_widths();
+        }
+        self.program_counter.value = self.read_word(RESET_VECTOR);
+        self.cycles += 7;
+    }
+
+    /// Fills the registers the way they come up at power on and resets the CPU. RAM is filled separately,
+    /// see VirtualMemory::power_on.
+    pub fn power_on(&mut self, state: PowerOnState) {
+        let mut values = state.values();
+        let mut next = || values.next().unwrap();
+        for register_cell in [&self.accumulator_cell, &self.x_cell, &self.y_cell] {
+            let mut register = register_cell.borrow_mut();
+            register.value = next();
+            if self.variant.has_native_mode() {
+                register.high = next();
+            }
+        }
+        self.stack_pointer.set_pointer(next());
+        self.processor_status_flags.set_mask(next());
+        self.pins.take_nmi_edge();
+        self.pins.take_so_edge();
+        self.reset();
+    }
+
+    /// True when a 65C816 has left emulation mode.
+    pub fn is_native(&self) -> bool {
+        return !self.processor_status_flags.is_emulation();
+    }
+
+    /// True when A and memory operands are 16 bits wide, the M flag is clear in native mode.
+    pub fn accumulator_is_wide(&self) -> bool {
+        return self.is_native() && !self.processor_status_flags.check_mask(MEMORY_SELECT_MASK);
+    }
+
+    /// True when X and Y are 16 bits wide, the X flag is clear in native mode.
+    pub fn index_is_wide(&self) -> bool {
+        return self.is_native() && !self.processor_status_flags.check_mask(INDEX_SELECT_MASK);
+    }
+
+    // The width of one of the data registers, the accumulator follows M and the index registers follow X
+    pub(crate) fn register_is_wide(&self, register_cell: &Rc<RefCell<DataRegister>>) -> bool {
+        if Rc::ptr_eq(register_cell, &self.accumulator_cell) {
+            return self.accumulator_is_wide();
+        }
+        return self.index_is_wide();
+    }
+
+    // Brings the registers in line with the E, M and X flags after anything that may have changed them.
+    // 8 bit index registers lose their high byte and the stack goes back to page 1 in emulation mode.
+    pub(crate) fn sync_register_widths(&mut self) {
+        if !self.index_is_wide() {
+            self.x_cell.borrow_mut().high = 0;
+            self.y_cell.borrow_mut().high = 0;
+        }
+        self.stack_pointer.set_native(self.is_native());
+    }
+
+    /// The 24 bit address of the program counter, always in bank 0 except on the 65C816.
+    pub fn program_address(&self) -> u32 {
+        return ((self.program_bank as u32) << 16) | self.program_counter.value as u32;
+    }
+
+    pub fn read_byte(&self, address: u32) -> u8 {
+        if self.reuse_operand.get() {
+            if let Some((last_address, data)) = self.last_data.get() {
+                if last_address == address {
+                    return data;
+                }
+            }
+        }
+        let data = self.memory_rc.borrow_mut().read(address);
+        self.last_data.set(Some((address, data)));
+        self.log_cycle(address, data, BusOperation::Read, false);
+        return data;
+    }
+
+    pub fn write_byte(&self, address: u32, data: u8) {
+        self.memory_rc.borrow_mut().write(address, data);
+        self.last_data.set(Some((address, data)));
+        self.log_cycle(address, data, BusOperation::Write, false);
+    }
+
+    // Reads the opcode at the program counter with SYNC raised
+    fn fetch_opcode(&self) -> u8 {
+        let address = self.program_address();
+        let data = self.memory_rc.borrow_mut().read(address);
+        self.log_cycle(address, data, BusOperation::Read, true);
+        return data;
+    }
+
+    fn log_cycle(&self, address: u32, data: u8, operation: BusOperation, sync: bool) {
+        if self.cycle_stepped {
+            self.bus_log.borrow_mut().push(BusCycle {
+                address,
+                data,
+                operation,
+                sync,
+            });
+        }
+    }
+
+    /// Pushes a byte onto the stack through the bus.
+    pub fn push_byte(&mut self, data: u8) {
+        self.write_byte(self.stack_pointer.get_word() as u32, data);
+        self.stack_pointer.decrement();
+    }
+
+    /// Pops a byte off the stack through the bus.
+    pub fn pop_byte(&mut self) -> u8 {
+        self.stack_pointer.increment();
+        return self.read_byte(self.stack_pointer.get_word() as u32);
+    }
+
+    /// Reads a little endian word from bank 0, the vectors and the pointers of the indirect modes are stored like this.
+    pub fn read_word(&self, low_byte_addr: u16) -> u16 {
+        let low_byte = self.read_byte(low_byte_addr as u32) as u16;
+        let high_byte = self.read_byte(low_byte_addr.wrapping_add(1) as u32) as u16;
+        return (high_byte << 8) | low_byte;
+    }
+
+    /// Reads an operand that is 16 bits wide if wide is set, the high byte comes from the following address.
+    pub fn read_operand(&self, address: u32, wide: bool) -> u16 {
+        let low_byte = self.read_byte(address) as u16;
+        if !wide {
+            return low_byte;
+        }
+        let high_byte = self.read_byte((address + 1) & 0xFFFFFF) as u16;
+        return (high_byte << 8) | low_byte;
+    }
+
+    pub fn write_operand(&self, address: u32, data: u16, wide: bool) {
+        self.write_byte(address, (data & 0xFF) as u8);
+        if wide {
+            self.write_byte((address + 1) & 0xFFFFFF, (data >> 8) as u8);
         }
-        self.program_counter.value = program_start_location;
     }
 
+    /// The effective address of the operand as it would be in bank 0. See fetch_long_address.
     pub fn fetch_address(&self, addressing_mode: &AddressingModes) -> Option<u16> {
-        let memory = self.memory_rc.borrow();
-        let pc = &self.program_counter;
-        let x = self.x_cell.borrow();
-        let y = self.y_cell.borrow();
-        match addressing_mode {
-            AddressingModes::Immediate | AddressingModes::Relative => Some(pc.value + 1),
-            AddressingModes::Absolute => Some(memory.read_word(pc.value + 1)),
+        return self
+            .fetch_long_address(addressing_mode)
+            .map(|address| (address & 0xFFFF) as u16);
+    }
+
+    /// The 24 bit effective address of the operand, None for the accumulator and implied modes.
+    /// Only the 65C816 ever leaves bank 0, for the immediate and relative modes this is the address of the operand itself.
+    /// While an instruction is being stepped the address it was decoded with is worked out once and reused, so the
+    /// operand and pointer bytes only go over the bus once.
+    pub fn fetch_long_address(&self, addressing_mode: &AddressingModes) -> Option<u32> {
+        return self
+            .effective_address(addressing_mode)
+            .map(|(_, address)| address);
+    }
+
+    /// fetch_long_address for handlers that need an operand in memory, an error for the accumulator and implied modes.
+    pub fn operand_address(
+        &self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<u32, EmulationError> {
+        return self
+            .fetch_long_address(addressing_mode)
+            .ok_or(self.invalid_addressing_mode(addressing_mode));
+    }
+
+    /// fetch_base_address for handlers that only work with the indexed modes.
+    pub fn operand_base_address(
+        &self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<u32, EmulationError> {
+        return self
+            .fetch_base_address(addressing_mode)
+            .ok_or(self.invalid_addressing_mode(addressing_mode));
+    }
+
+    pub(crate) fn invalid_addressing_mode(
+        &self,
+        addressing_mode: &AddressingModes,
+    ) -> EmulationError {
+        return EmulationError::InvalidAddressingMode {
+            addressing_mode: *addressing_mode,
+            address: self.program_address(),
+        };
+    }
+
+    // The address of the operand together with the address an indexed mode started from before the index was added
+    fn effective_address(&self, addressing_mode: &AddressingModes) -> Option<(u32, u32)> {
+        if let Some((latched_mode, latched)) = self.latched_address.get() {
+            if latched_mode == *addressing_mode {
+                return latched;
+            }
+        }
+        let program_bank = (self.program_bank as u32) << 16;
+        let data_bank = (self.data_bank as u32) << 16;
+        // The operand bytes follow the opcode within the program bank
+        let operand = |offset: u16| {
+            let pc = self.program_counter.value.wrapping_add(offset);
+            return self.read_byte(program_bank | pc as u32);
+        };
+        let operand_word = || {
+            let low_byte = operand(1) as u16;
+            return ((operand(2) as u16) << 8) | low_byte;
+        };
+        let operand_address = program_bank | self.program_counter.value.wrapping_add(1) as u32;
+        let x = self.x_cell.borrow().get_word();
+        let y = self.y_cell.borrow().get_word();
+        let mut base_address = None;
+        let address = match addressing_mode {
+            AddressingModes::Immediate
+            | AddressingModes::ImmediateWord
+            | AddressingModes::Relative
+            | AddressingModes::RelativeLong
+            | AddressingModes::BlockMove => operand_address,
+            AddressingModes::Absolute => data_bank | operand_word() as u32,
             AddressingModes::AbsoluteXIndex => {
-                Some(memory.read_word(pc.value + 1) + (x.value as u16))
+                let base = data_bank | operand_word() as u32;
+                base_address = Some(base);
+                self.index_address(base, x)
             }
             AddressingModes::AbsoluteYIndex => {
-                Some(memory.read_word(pc.value + 1) + (y.value as u16))
+                let base = data_bank | operand_word() as u32;
+                base_address = Some(base);
+                self.index_address(base, y)
+            }
+            AddressingModes::AbsoluteLong => {
+                let word = operand_word() as u32;
+                ((operand(3) as u32) << 16) | word
+            }
+            AddressingModes::AbsoluteLongXIndex => {
+                let word = operand_word() as u32;
+                ((((operand(3) as u32) << 16) | word) + x as u32) & 0xFFFFFF
             }
             AddressingModes::Indirect => {
-                let lookup_addr = memory.read_word(pc.value + 1);
-                Some(memory.read_word(lookup_addr))
+                let lookup_addr = operand_word();
+                // The 65C02 spends a cycle on the page crossing fix for the pointer whether it's needed or not
+                if self.variant.is_cmos() && !self.variant.has_native_mode() {
+                    self.dummy_read(
+                        program_bank | self.program_counter.value.wrapping_add(2) as u32,
+                    );
+                }
+                if self.variant.has_indirect_jump_bug() && (lookup_addr & 0xFF) == 0xFF {
+                    // The NMOS chips don't carry into the high byte of the pointer, $xxFF wraps to $xx00
+                    let low_byte = self.read_byte(lookup_addr as u32) as u32;
+                    let high_byte = self.read_byte((lookup_addr & 0xFF00) as u32) as u32;
+                    program_bank | (high_byte << 8) | low_byte
+                } else {
+                    program_bank | self.read_word(lookup_addr) as u32
+                }
+            }
+            AddressingModes::AbsoluteIndexIndirect => {
+                let word = operand_word();
+                self.dummy_read(program_bank | self.program_counter.value.wrapping_add(2) as u32);
+                let lookup_addr = program_bank | word.wrapping_add(x) as u32;
+                let low_byte = self.read_byte(lookup_addr) as u32;
+                let high_byte = self
+                    .read_byte(program_bank | (lookup_addr as u16).wrapping_add(1) as u32)
+                    as u32;
+                program_bank | (high_byte << 8) | low_byte
+            }
+            AddressingModes::AbsoluteIndirectLong => {
+                let lookup_addr = operand_word();
+                let low_word = self.read_word(lookup_addr) as u32;
+                let bank = self.read_byte(lookup_addr.wrapping_add(2) as u32) as u32;
+                (bank << 16) | low_word
+            }
+            AddressingModes::ZeroPage | AddressingModes::ZeroPageRelative => {
+                self.direct_address(operand(1), 0) as u32
+            }
+            AddressingModes::ZeroPageXIndex => {
+                let offset = operand(1);
+                self.direct_index_dummy_read(offset);
+                self.direct_address(offset, x) as u32
+            }
+            AddressingModes::ZeroPageYIndex => {
+                let offset = operand(1);
+                self.direct_index_dummy_read(offset);
+                self.direct_address(offset, y) as u32
             }
-            AddressingModes::ZeroPage => Some(memory[pc.value + 1] as u16),
-            AddressingModes::ZeroPageXIndex => Some((memory[pc.value + 1] + x.value) as u16),
-            AddressingModes::ZeroPageYIndex => Some((memory[pc.value + 1] + y.value) as u16),
 
+            // The pointer is always read from the direct page, which is the zero page unless a 65C816 moved it
             AddressingModes::PreIndexIndirect => {
-                let lookup_addr = (memory[pc.value + 1 + (x.value as u16)]) as u16;
-                Some(memory.read_word(lookup_addr))
+                let offset = operand(1);
+                self.direct_index_dummy_read(offset);
+                let lookup_addr = self.direct_address(offset, x);
+                data_bank | self.read_direct_word(lookup_addr) as u32
             }
             AddressingModes::PostIndexIndirect => {
-                let lookup_addr = (memory[pc.value + 1] + y.value) as u16;
-                Some(memory.read_word(lookup_addr))
+                let lookup_addr = self.direct_address(operand(1), 0);
+                let base = data_bank | self.read_direct_word(lookup_addr) as u32;
+                base_address = Some(base);
+                self.index_address(base, y)
+            }
+            AddressingModes::ZeroPageIndirect => {
+                let lookup_addr = self.direct_address(operand(1), 0);
+                data_bank | self.read_direct_word(lookup_addr) as u32
+            }
+            AddressingModes::ZeroPageIndirectLong => {
+                let lookup_addr = self.direct_address(operand(1), 0);
+                self.read_direct_long(lookup_addr)
+            }
+            AddressingModes::ZeroPageIndirectLongYIndex => {
+                let lookup_addr = self.direct_address(operand(1), 0);
+                (self.read_direct_long(lookup_addr) + y as u32) & 0xFFFFFF
             }
+            AddressingModes::StackRelative => {
+                self.stack_pointer
+                    .get_word()
+                    .wrapping_add(operand(1) as u16) as u32
+            }
+            AddressingModes::StackRelativeIndirectYIndex => {
+                let lookup_addr = self
+                    .stack_pointer
+                    .get_word()
+                    .wrapping_add(operand(1) as u16);
+                let pointer = self.read_word(lookup_addr) as u32;
+                self.index_address(data_bank | pointer, y)
+            }
+
+            AddressingModes::Accumulator | AddressingModes::Implied => return None,
+        };
+        return Some((base_address.unwrap_or(address), address));
+    }
 
+    // Adds an index to an absolute address. The 65C816 carries into the next bank, the others wrap around to $0000.
+    fn index_address(&self, base_address: u32, index: u16) -> u32 {
+        let address = base_address + index as u32;
+        if self.variant.has_native_mode() {
+            return address & 0xFFFFFF;
+        }
+        return address & 0xFFFF;
+    }
+
+    // Offsets into the direct page. In emulation mode with the direct page on a page boundary indexing wraps around
+    // within the page just like the 6502's zero page, otherwise it can run on into the next page.
+    fn direct_address(&self, offset: u8, index: u16) -> u16 {
+        if self.direct_page_wraps() {
+            return self.direct_page | offset.wrapping_add(index as u8) as u16;
+        }
+        return self
+            .direct_page
+            .wrapping_add(offset as u16)
+            .wrapping_add(index);
+    }
+
+    fn direct_page_wraps(&self) -> bool {
+        return self.processor_status_flags.is_emulation() && self.direct_page & 0xFF == 0;
+    }
+
+    fn read_direct_word(&self, low_byte_addr: u16) -> u16 {
+        let high_byte_addr = if self.direct_page_wraps() {
+            (low_byte_addr & 0xFF00) | (low_byte_addr as u8).wrapping_add(1) as u16
+        } else {
+            low_byte_addr.wrapping_add(1)
+        };
+        let low_byte = self.read_byte(low_byte_addr as u32) as u16;
+        let high_byte = self.read_byte(high_byte_addr as u32) as u16;
+        return (high_byte << 8) | low_byte;
+    }
+
+    fn read_direct_long(&self, low_byte_addr: u16) -> u32 {
+        let word = self.read_direct_word(low_byte_addr) as u32;
+        let bank = self.read_byte(low_byte_addr.wrapping_add(2) as u32) as u32;
+        return (bank << 16) | word;
+    }
+
+    /// The address an indexed addressing mode starts from before the index is added, None for the other modes.
+    pub fn fetch_base_address(&self, addressing_mode: &AddressingModes) -> Option<u32> {
+        return match addressing_mode {
+            AddressingModes::AbsoluteXIndex
+            | AddressingModes::AbsoluteYIndex
+            | AddressingModes::PostIndexIndirect => self
+                .effective_address(addressing_mode)
+                .map(|(base_address, _)| base_address),
             _ => None,
+        };
+    }
+
+    /// Returns true if indexing the operand's base address moves it onto another page.
+    pub fn crosses_page(&self, addressing_mode: &AddressingModes) -> bool {
+        let (base_address, indexed_address) = match self.effective_address(addressing_mode) {
+            Some(addresses) => addresses,
+            None => return false,
+        };
+        return (base_address & 0xFFFF00) != (indexed_address & 0xFFFF00);
+    }
+
+    /// Services an IRQ or NMI, taking 7 cycles (8 in 65C816 native mode).
+    pub fn interrupt(&mut self, vector: u16) {
+        // The opcode that would have run is fetched and thrown away, then read again in place of an operand
+        self.fetch_opcode();
+        self.dummy_read(self.program_address());
+        if self.is_native() {
+            self.cycles += 1;
+        }
+        self.enter_interrupt(vector, false);
+        self.cycles += 7;
+    }
+
+    // Pushes PC and the status register then jumps through the given vector. Shared by IRQ, NMI, BRK and COP,
+    // the only difference between them on the stack is the break bit. In native mode the program bank is pushed
+    // first and the native vectors are used instead.
+    pub(crate) fn enter_interrupt(&mut self, vector: u16, is_break: bool) {
+        let native = self.is_native();
+        if native {
+            self.push_byte(self.program_bank);
+        }
+        self.push_byte(self.program_counter.get_pch());
+        self.push_byte(self.program_counter.get_pcl());
+        let flags = self.processor_status_flags.get_pushed_flags(is_break);
+        self.push_byte(flags);
+        self.processor_status_flags
+            .set_flag(StatusFlags::InterruptDisable);
+        if self.variant.clears_decimal_on_interrupt() {
+            self.processor_status_flags.clear_flag(StatusFlags::Decimal);
+        }
+        let vector = if native {
+            match vector {
+                NMI_VECTOR => NATIVE_NMI_VECTOR,
+                COP_VECTOR => NATIVE_COP_VECTOR,
+                _ if is_break => NATIVE_BRK_VECTOR,
+                _ => NATIVE_IRQ_VECTOR,
+            }
+        } else {
+            vector
+        };
+        self.program_bank = 0;
+        self.program_counter.value = self.read_word(vector);
+    }
+
+    // Samples the interrupt lines, the real chip does this between instructions.
+    // Returns false if the CPU is being held in reset or is asleep after WAI or STP.
+    fn poll_interrupts(&mut self) -> bool {
+        if self.pins.reset {
+            self.reset_pending = true;
+            return false;
+        }
+        if self.reset_pending {
+            self.reset_pending = false;
+            self.run_state = RunState::Running;
+            self.pins.take_nmi_edge();
+            self.reset();
+            return true;
+        }
+        if self.pins.take_so_edge() {
+            self.processor_status_flags.set_flag(StatusFlags::Overflow);
+        }
+        if let RunState::Stopped | RunState::Jammed = self.run_state {
+            return false;
+        }
+
+        let nmi = self.pins.take_nmi_edge();
+        if self.run_state == RunState::Waiting {
+            if !nmi && !self.pins.irq {
+                return false;
+            }
+            // IRQ wakes the CPU up even when masked, it then carries on after the WAI
+            self.run_state = RunState::Running;
+        }
+        if nmi {
+            self.interrupt(NMI_VECTOR);
+        } else if self.pins.irq
+            && !self
+                .processor_status_flags
+                .check_flag(StatusFlags::InterruptDisable)
+        {
+            self.interrupt(IRQ_VECTOR);
+        }
+        return true;
+    }
+
+    /// Fetches the opcode at the program counter, decodes it and executes it, adding its cycles to the count.
+    /// Pending interrupts are serviced first, in which case the instruction run is the first one of the handler.
+    /// Returns the instruction that ran, or None if the CPU is held in reset or waiting for an interrupt.
+    /// While RDY is low the CPU is held on the opcode fetch, each call spends a cycle and returns None.
+    /// An opcode that isn't assigned is an error and leaves the CPU untouched, so is stepping a stopped or jammed CPU.
+    pub fn step(&mut self) -> Result<Option<Instruction>, EmulationError> {
+        if !self.pins.rdy {
+            self.cycles += 1;
+            return Ok(None);
+        }
+        if !self.poll_interrupts() {
+            if let (false, RunState::Stopped | RunState::Jammed) = (self.pins.reset, self.run_state)
+            {
+                return Err(EmulationError::Halted {
+                    state: self.run_state,
+                    address: self.program_address(),
+                });
+            }
+            return Ok(None);
+        }
+        let opcode_address = self.program_address();
+        let opcode = self.fetch_opcode();
+        let mut instruction = match (decode(opcode, self.variant), &self.illegal_opcodes) {
+            (Some(instruction), _) => instruction,
+            (None, IllegalOpcodePolicy::Nop) => unassigned_nop(opcode),
+            (None, IllegalOpcodePolicy::Jam) => Instruction {
+                opcode,
+                mnemonic: Mnemonic::JAM,
+                addressing_mode: AddressingModes::Implied,
+                cycles: 2,
+                page_penalty: false,
+                undocumented: true,
+            },
+            (None, IllegalOpcodePolicy::Error) => {
+                self.bus_log.borrow_mut().clear();
+                return Err(EmulationError::IllegalOpcode {
+                    opcode,
+                    address: opcode_address,
+                });
+            }
+            (None, IllegalOpcodePolicy::Handler(_)) => {
+                return self.call_illegal_opcode_handler(opcode);
+            }
+        };
+        instruction.widen_immediate(self.accumulator_is_wide(), self.index_is_wide());
+        let addressing_mode = instruction.addressing_mode;
+        // JSR and JSL push the return address before they're done reading their operand, they read it themselves
+        if !matches!(instruction.mnemonic, Mnemonic::JSR | Mnemonic::JSL) {
+            let addresses = self.effective_address(&addressing_mode);
+            self.latched_address.set(Some((addressing_mode, addresses)));
+        }
+        // The single cycle NOPs of the 65C02 are done before they get to the dummy read
+        if matches!(
+            addressing_mode,
+            AddressingModes::Implied | AddressingModes::Accumulator
+        ) && instruction.cycles > 1
+        {
+            self.implied_dummy_read();
+        }
+        // 16 bit index registers always take the extra cycle of an indexed read
+        let page_crossed = instruction.page_penalty
+            && (self.index_is_wide() || self.crosses_page(&addressing_mode));
+        if page_crossed || (!instruction.page_penalty && instruction.writes_memory()) {
+            self.indexed_dummy_read(&instruction);
+        }
+        let native_cycles = self.native_cycles(&instruction);
+        let result = self.execute(&instruction);
+        self.latched_address.set(None);
+        result?;
+        self.cycles += instruction.cycles as u64 + page_crossed as u64 + native_cycles;
+        let fault = self.memory_rc.borrow_mut().take_fault();
+        if let Some(address) = fault {
+            return Err(EmulationError::BusFault { address });
+        }
+        return Ok(Some(instruction));
+    }
+
+    /// Steps until at least cycles clock cycles have gone by, the last instruction may run over.
+    /// Like the other run methods it can come back early, see StopReason. The CPU is left as it is so calling it
+    /// again picks up where it stopped, after a Break that's the start of the BRK handler.
+    pub fn run_for_cycles(&mut self, cycles: u64) -> Result<StopReason, EmulationError> {
+        let start = self.cycles;
+        return self.run(|cpu, _| {
+            if cpu.cycles - start >= cycles {
+                return Some(StopReason::CycleBudget);
+            }
+            return None;
+        });
+    }
+
+    pub fn run_for_instructions(
+        &mut self,
+        instructions: u64,
+    ) -> Result<StopReason, EmulationError> {
+        return self.run(|_, ran| {
+            if ran >= instructions {
+                return Some(StopReason::InstructionBudget);
+            }
+            return None;
+        });
+    }
+
+    /// Steps until condition holds, it is checked before every instruction so the instruction it stopped on
+    /// hasn't run yet. There is no budget, a program that loops forever without jumping to itself won't come back.
+    pub fn run_until(
+        &mut self,
+        mut condition: impl FnMut(&Self) -> bool,
+    ) -> Result<StopReason, EmulationError> {
+        return self.run(|cpu, _| {
+            if condition(cpu) {
+                return Some(StopReason::Breakpoint(cpu.program_address()));
+            }
+            return None;
+        });
+    }
+
+    /// Steps until the program counter gets to address, including the bank on the 65C816.
+    pub fn run_until_pc(&mut self, address: u32) -> Result<StopReason, EmulationError> {
+        return self.run_until(|cpu| cpu.program_address() == address);
+    }
+
+    // Steps until stop gives a reason, it is handed the number of instructions run so far. Also stops on the
+    // instructions that make the CPU wait for the host, or loop in place forever.
+    fn run(
+        &mut self,
+        mut stop: impl FnMut(&Self, u64) -> Option<StopReason>,
+    ) -> Result<StopReason, EmulationError> {
+        let mut instructions = 0;
+        loop {
+            if let Some(reason) = stop(self, instructions) {
+                return Ok(reason);
+            }
+            let address = self.program_address();
+            let start = self.cycles;
+            let instruction = match self.step()? {
+                Some(instruction) => instruction,
+                None if self.run_state == RunState::Waiting => {
+                    return Ok(StopReason::Waiting(address));
+                }
+                None => {
+                    // Held in reset, the clock keeps running
+                    if self.cycles == start {
+                        self.cycles += 1;
+                    }
+                    continue;
+                }
+            };
+            instructions += 1;
+            match (self.run_state, instruction.mnemonic) {
+                (RunState::Stopped, _) => return Ok(StopReason::Stopped(address)),
+                (RunState::Jammed, _) => return Ok(StopReason::Jammed(address)),
+                (RunState::Waiting, _) => return Ok(StopReason::Waiting(address)),
+                (_, Mnemonic::BRK) if self.stop_on_break => {
+                    return Ok(StopReason::Break(address));
+                }
+                // The block moves stay on their opcode until the whole block is copied, and an interrupt handler
+                // that is only an RTI goes straight back to where it was
+                (_, Mnemonic::MVN | Mnemonic::MVP | Mnemonic::RTI) => {}
+                _ if self.program_address() == address => {
+                    return Ok(StopReason::SelfJump(address));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // The handler is taken out while it runs so it can be handed the CPU, then put back
+    fn call_illegal_opcode_handler(
+        &mut self,
+        opcode: u8,
+    ) -> Result<Option<Instruction>, EmulationError> {
+        let mut policy = std::mem::replace(&mut self.illegal_opcodes, IllegalOpcodePolicy::Error);
+        let result = match &mut policy {
+            IllegalOpcodePolicy::Handler(handler) => handler(self, opcode),
+            _ => Ok(None),
+        };
+        self.illegal_opcodes = policy;
+        return result;
+    }
+
+    /// A cycle spent on the bus without using what comes back, the real chip has to put some address out while it
+    /// works. The 65C816 marks these as internal operations with VDA and VPA low, so nothing is read.
+    pub(crate) fn dummy_read(&self, address: u32) {
+        if self.variant.has_native_mode() {
+            self.log_cycle(address, 0, BusOperation::Internal, false);
+        } else {
+            self.read_byte(address);
+        }
+    }
+
+    /// The stack pulls spend a cycle reading the top of the stack before the stack pointer is incremented.
+    pub(crate) fn stack_dummy_read(&self) {
+        self.dummy_read(self.stack_pointer.get_word() as u32);
+    }
+
+    // Instructions without an operand still read the byte after the opcode on their second cycle and throw it away
+    fn implied_dummy_read(&self) {
+        self.dummy_read(self.program_address().wrapping_add(1) & 0xFFFFFF);
+    }
+
+    // Adding the index to a direct page address takes a cycle. The NMOS chips spend it reading the unindexed address,
+    // the 65C02 reads the operand byte again.
+    fn direct_index_dummy_read(&self, offset: u8) {
+        let address = if self.variant.is_cmos() {
+            (self.program_address() & 0xFF0000) | self.program_counter.value.wrapping_add(1) as u32
+        } else {
+            self.direct_address(offset, 0) as u32
+        };
+        self.dummy_read(address);
+    }
+
+    // Indexing is done a byte at a time, so while the high byte of the address is being fixed up the CPU reads
+    // from the address with the carry still missing. Stores and read-modify-write instructions always spend that
+    // cycle, reads only when the index crosses a page. The 65C02 reads the last operand byte again instead of
+    // the half-indexed address.
+    fn indexed_dummy_read(&self, instruction: &Instruction) {
+        let (base_address, address) = match self.effective_address(&instruction.addressing_mode) {
+            Some(addresses) => addresses,
+            None => return,
+        };
+        if !matches!(
+            instruction.addressing_mode,
+            AddressingModes::AbsoluteXIndex
+                | AddressingModes::AbsoluteYIndex
+                | AddressingModes::PostIndexIndirect
+        ) {
+            return;
+        }
+        let uncarried_address = (base_address & 0xFFFF00) | (address & 0xFF);
+        if self.variant.is_cmos() && uncarried_address != address {
+            let last_operand_byte = self
+                .program_counter
+                .value
+                .wrapping_add(instruction.addressing_mode.parameter_bytes());
+            self.dummy_read(((self.program_bank as u32) << 16) | last_operand_byte as u32);
+        } else {
+            self.dummy_read(uncarried_address);
+        }
+    }
+
+    /// The extra cycle a read-modify-write instruction spends working out the result, between reading the operand
+    /// and writing it back. See CpuVariant::writes_operand_twice.
+    pub(crate) fn modify_cycle(&self, address: u32, original: u8) {
+        if self.is_native() {
+            self.log_cycle(address, original, BusOperation::Internal, false);
+        } else if self.variant.writes_operand_twice() {
+            self.write_byte(address, original);
+        } else {
+            self.read_byte(address);
+        }
+    }
+
+    /// Advances the CPU by one clock cycle, returning the bus cycle it ran and driving the pins with it.
+    /// Only works with cycle_stepped set. The bus is accessed in the right order and the right number of times,
+    /// but an instruction's accesses all happen when its first cycle is stepped, the later cycles are played back
+    /// from what was recorded. The 65C816's cycles that aren't a bus access are reported as internal.
+    /// Errors from stepping the next instruction are passed on.
+    pub fn step_cycle(&mut self) -> Result<BusCycle, EmulationError> {
+        if !self.pins.rdy {
+            // Held on the cycle it was about to run, which stays on the bus. The NMOS chips carry on with writes.
+            let held_cycle = match self.pending_cycles.front() {
+                Some(cycle)
+                    if cycle.operation == BusOperation::Write && !self.variant.is_cmos() =>
+                {
+                    None
+                }
+                Some(cycle) => Some(*cycle),
+                None => Some(BusCycle {
+                    address: self.program_address(),
+                    data: self.pins.data_buffer,
+                    operation: BusOperation::Read,
+                    sync: true,
+                }),
+            };
+            if let Some(cycle) = held_cycle {
+                self.cycles += 1;
+                self.pins.drive(&cycle);
+                return Ok(cycle);
+            }
+        }
+        if self.pending_cycles.is_empty() {
+            self.bus_log.borrow_mut().clear();
+            let start = self.cycles;
+            self.step()?;
+            let mut cycles: Vec<BusCycle> = self.bus_log.borrow_mut().drain(..).collect();
+            // Held in reset, waiting or stopped, the clock keeps running with nothing on the bus
+            let elapsed = (self.cycles - start).max(1) as usize;
+            let last_address = match cycles.last() {
+                Some(cycle) => cycle.address,
+                None => self.program_address(),
+            };
+            while cycles.len() < elapsed {
+                cycles.push(BusCycle {
+                    address: last_address,
+                    data: 0,
+                    operation: BusOperation::Internal,
+                    sync: false,
+                });
+            }
+            if self.cycles == start {
+                self.cycles += 1;
+            }
+            self.pending_cycles.extend(cycles);
+        }
+        let cycle = self.pending_cycles.pop_front().unwrap();
+        self.pins.drive(&cycle);
+        return Ok(cycle);
+    }
+
+    /// Hands the bus to another bus master, a DMA controller or video chip that holds the CPU off with RDY.
+    /// The transfer runs between instructions and every cycle it spends on the bus is taken from the CPU.
+    /// Returns the number of cycles taken.
+    pub fn dma(&mut self, transfer: impl FnOnce(&mut DmaBus<B>)) -> u64 {
+        let cycles = {
+            let mut bus = self.memory_rc.borrow_mut();
+            let mut dma_bus = DmaBus::new(&mut *bus);
+            transfer(&mut dma_bus);
+            dma_bus.log
+        };
+        let taken = cycles.len() as u64;
+        self.cycles += taken;
+        if self.cycle_stepped {
+            self.pending_cycles.extend(cycles);
+        } else if let Some(cycle) = cycles.last() {
+            self.pins.drive(cycle);
+        }
+        return taken;
+    }
+
+    // The extra cycles the 65C816 spends on top of an instruction's base count: one for each extra byte a
+    // 16 bit register moves (two for read-modify-write), one when the direct page is not page aligned and one
+    // for pushing the program bank in native mode. Worked out before executing, the instruction may change the widths.
+    fn native_cycles(&self, instruction: &Instruction) -> u64 {
+        if !self.variant.has_native_mode() {
+            return 0;
+        }
+        let mut cycles = 0;
+        if self.direct_page & 0xFF != 0
+            && matches!(
+                instruction.addressing_mode,
+                AddressingModes::ZeroPage
+                    | AddressingModes::ZeroPageXIndex
+                    | AddressingModes::ZeroPageYIndex
+                    | AddressingModes::PreIndexIndirect
+                    | AddressingModes::PostIndexIndirect
+                    | AddressingModes::ZeroPageIndirect
+                    | AddressingModes::ZeroPageIndirectLong
+                    | AddressingModes::ZeroPageIndirectLongYIndex
+            )
+        {
+            cycles += 1;
+        }
+        let accumulator_wide = self.accumulator_is_wide() as u64;
+        let index_wide = self.index_is_wide() as u64;
+        cycles += match instruction.mnemonic {
+            Mnemonic::ASL
+            | Mnemonic::DEC
+            | Mnemonic::INC
+            | Mnemonic::LSR
+            | Mnemonic::ROL
+            | Mnemonic::ROR
+            | Mnemonic::TRB
+            | Mnemonic::TSB => {
+                if instruction.addressing_mode == AddressingModes::Accumulator {
+                    0
+                } else {
+                    2 * accumulator_wide
+                }
+            }
+            Mnemonic::ADC
+            | Mnemonic::AND
+            | Mnemonic::BIT
+            | Mnemonic::CMP
+            | Mnemonic::EOR
+            | Mnemonic::LDA
+            | Mnemonic::ORA
+            | Mnemonic::PHA
+            | Mnemonic::PLA
+            | Mnemonic::SBC
+            | Mnemonic::STA
+            | Mnemonic::STZ => accumulator_wide,
+            Mnemonic::CPX
+            | Mnemonic::CPY
+            | Mnemonic::LDX
+            | Mnemonic::LDY
+            | Mnemonic::PHX
+            | Mnemonic::PHY
+            | Mnemonic::PLX
+            | Mnemonic::PLY
+            | Mnemonic::STX
+            | Mnemonic::STY => index_wide,
+            Mnemonic::BRK | Mnemonic::COP | Mnemonic::RTI => self.is_native() as u64,
+            _ => 0,
+        };
+        return cycles;
+    }
+
+    /// Runs a single decoded instruction through its handler.
+    pub fn execute(&mut self, instruction: &Instruction) -> Result<(), EmulationError> {
+        let addressing_mode = &instruction.addressing_mode;
+        let a = self.accumulator_cell.clone();
+        let x = self.x_cell.clone();
+        let y = self.y_cell.clone();
+        match instruction.mnemonic {
+            // Arithmetic and logic
+            Mnemonic::ADC => self.sum_with_carry(addressing_mode, false)?,
+            Mnemonic::SBC => self.sum_with_carry(addressing_mode, true)?,
+            Mnemonic::AND => self.bitwise_and(addressing_mode)?,
+            Mnemonic::ORA => self.bitwise_or(addressing_mode)?,
+            Mnemonic::EOR => self.bitwise_exclusive_or(addressing_mode)?,
+            Mnemonic::ASL => self.left_shift(addressing_mode, false),
+            Mnemonic::ROL => self.left_shift(addressing_mode, true),
+            Mnemonic::LSR => self.right_shift(addressing_mode, false),
+            Mnemonic::ROR => self.right_shift(addressing_mode, true),
+            Mnemonic::BIT => self.bit_instruction(addressing_mode)?,
+            Mnemonic::TSB => self.test_and_modify_bits(addressing_mode, true)?,
+            Mnemonic::TRB => self.test_and_modify_bits(addressing_mode, false)?,
+            Mnemonic::XBA => self.exchange_accumulator(),
+            Mnemonic::RMB => self.modify_bit(addressing_mode, instruction.bit_index(), false)?,
+            Mnemonic::SMB => self.modify_bit(addressing_mode, instruction.bit_index(), true)?,
+            Mnemonic::INC => self.inc_dec_memory(addressing_mode, false),
+            Mnemonic::DEC => self.inc_dec_memory(addressing_mode, true),
+            Mnemonic::INX => self.inc_dec_register(x, false),
+            Mnemonic::INY => self.inc_dec_register(y, false),
+            Mnemonic::DEX => self.inc_dec_register(x, true),
+            Mnemonic::DEY => self.inc_dec_register(y, true),
+
+            // Control flow
+            Mnemonic::CMP => self.compare(addressing_mode, a)?,
+            Mnemonic::CPX => self.compare(addressing_mode, x)?,
+            Mnemonic::CPY => self.compare(addressing_mode, y)?,
+            Mnemonic::JMP => self.jump(addressing_mode, false)?,
+            Mnemonic::JSR => self.jump(addressing_mode, true)?,
+            Mnemonic::JML => self.jump(addressing_mode, false)?,
+            Mnemonic::JSL => self.jump(addressing_mode, true)?,
+            Mnemonic::RTS => self.subroutine_return(),
+            Mnemonic::RTL => self.subroutine_return_long(),
+            Mnemonic::BRK => self.break_instruction(),
+            Mnemonic::COP => self.coprocessor_instruction(),
+            Mnemonic::RTI => self.interrupt_return(),
+            Mnemonic::BCC => self.branch_exec(BranchMode::BCC)?,
+            Mnemonic::BCS => self.branch_exec(BranchMode::BCS)?,
+            Mnemonic::BEQ => self.branch_exec(BranchMode::BEQ)?,
+            Mnemonic::BMI => self.branch_exec(BranchMode::BMI)?,
+            Mnemonic::BNE => self.branch_exec(BranchMode::BNE)?,
+            Mnemonic::BPL => self.branch_exec(BranchMode::BPL)?,
+            Mnemonic::BVC => self.branch_exec(BranchMode::BVC)?,
+            Mnemonic::BVS => self.branch_exec(BranchMode::BVS)?,
+            Mnemonic::BRA => self.branch_exec(BranchMode::BRA)?,
+            Mnemonic::BRL => self.branch_long()?,
+            Mnemonic::BBR => self.branch_on_bit(instruction.bit_index(), false)?,
+            Mnemonic::BBS => self.branch_on_bit(instruction.bit_index(), true)?,
+            Mnemonic::WAI => self.wait_for_interrupt(),
+            Mnemonic::STP => self.stop(),
+
+            // Memory and registers
+            Mnemonic::LDA => self.load_instruction(*addressing_mode, a)?,
+            Mnemonic::LDX => self.load_instruction(*addressing_mode, x)?,
+            Mnemonic::LDY => self.load_instruction(*addressing_mode, y)?,
+            Mnemonic::STA => self.store_instruction(*addressing_mode, a)?,
+            Mnemonic::STX => self.store_instruction(*addressing_mode, x)?,
+            Mnemonic::STY => self.store_instruction(*addressing_mode, y)?,
+            Mnemonic::STZ => self.store_zero(*addressing_mode)?,
+            Mnemonic::TAX => self.transfer_register(a, x),
+            Mnemonic::TAY => self.transfer_register(a, y),
+            Mnemonic::TXA => self.transfer_register(x, a),
+            Mnemonic::TYA => self.transfer_register(y, a),
+            Mnemonic::TSX => self.transfer_from_stack_pointer(x),
+            Mnemonic::TXS => self.transfer_to_stack_pointer(x),
+            Mnemonic::TXY => self.transfer_register(x, y),
+            Mnemonic::TYX => self.transfer_register(y, x),
+            Mnemonic::TCS => self.transfer_to_stack_pointer(a),
+            Mnemonic::TSC => self.transfer_stack_pointer_to_accumulator(),
+            Mnemonic::TCD => self.transfer_to_direct_page(),
+            Mnemonic::TDC => self.transfer_from_direct_page(),
+            Mnemonic::MVN => self.block_move(true)?,
+            Mnemonic::MVP => self.block_move(false)?,
+
+            // Stack
+            Mnemonic::PHA => self.push_register(a),
+            Mnemonic::PHX => self.push_register(x),
+            Mnemonic::PHY => self.push_register(y),
+            Mnemonic::PHP => self.push_status(),
+            Mnemonic::PLA => self.pop_register(a),
+            Mnemonic::PLX => self.pop_register(x),
+            Mnemonic::PLY => self.pop_register(y),
+            Mnemonic::PLP => self.pop_status(),
+            Mnemonic::PHB => self.push_bank(self.data_bank),
+            Mnemonic::PHK => self.push_bank(self.program_bank),
+            Mnemonic::PLB => self.pop_data_bank(),
+            Mnemonic::PHD => self.push_direct_page(),
+            Mnemonic::PLD => self.pop_direct_page(),
+            Mnemonic::PEA => self.push_effective_address(addressing_mode)?,
+            Mnemonic::PEI => self.push_effective_address(addressing_mode)?,
+            Mnemonic::PER => self.push_effective_address(addressing_mode)?,
+
+            // Status flags
+            Mnemonic::CLC => self.clear_carry_flag(),
+            Mnemonic::CLD => self.clear_decimal_flag(),
+            Mnemonic::CLI => self.clear_interrupt_disable_flag(),
+            Mnemonic::CLV => self.clear_overflow_flag(),
+            Mnemonic::SEC => self.set_carry_flag(),
+            Mnemonic::SED => self.set_decimal_flag(),
+            Mnemonic::SEI => self.set_interrupt_disable_flag(),
+            Mnemonic::REP => self.reset_status_bits()?,
+            Mnemonic::SEP => self.set_status_bits()?,
+            Mnemonic::XCE => self.exchange_carry_emulation(),
+
+            // Undocumented NMOS opcodes
+            Mnemonic::SLO => self.shift_left_or(addressing_mode)?,
+            Mnemonic::RLA => self.rotate_left_and(addressing_mode)?,
+            Mnemonic::SRE => self.shift_right_exclusive_or(addressing_mode)?,
+            Mnemonic::RRA => self.rotate_right_add(addressing_mode)?,
+            Mnemonic::DCP => self.decrement_compare(addressing_mode)?,
+            Mnemonic::ISC => self.increment_subtract(addressing_mode)?,
+            Mnemonic::LAX => self.load_accumulator_index(addressing_mode)?,
+            Mnemonic::SAX => self.store_accumulator_and_index(addressing_mode)?,
+            Mnemonic::ANC => self.and_carry(addressing_mode)?,
+            Mnemonic::ALR => self.and_shift_right(addressing_mode)?,
+            Mnemonic::ARR => self.and_rotate_right(addressing_mode)?,
+            Mnemonic::SBX => self.subtract_from_and_index(addressing_mode)?,
+            Mnemonic::XAA => self.magic_and(addressing_mode, false)?,
+            Mnemonic::LXA => self.magic_and(addressing_mode, true)?,
+            Mnemonic::SHA => {
+                let data = self.accumulator_cell.borrow().value & self.x_cell.borrow().value;
+                self.store_and_high_byte(addressing_mode, data)?;
+            }
+            Mnemonic::SHX => {
+                let data = self.x_cell.borrow().value;
+                self.store_and_high_byte(addressing_mode, data)?;
+            }
+            Mnemonic::SHY => {
+                let data = self.y_cell.borrow().value;
+                self.store_and_high_byte(addressing_mode, data)?;
+            }
+            Mnemonic::TAS => self.transfer_and_store_high_byte(addressing_mode)?,
+            Mnemonic::LAS => self.load_and_stack_pointer(addressing_mode)?,
+            Mnemonic::JAM => self.jam(),
+
+            Mnemonic::NOP | Mnemonic::WDM => self.no_operation(addressing_mode)?,
         }
+        return Ok(());
     }
 }
@@ -0,0 +1,61 @@
+use super::{
+    bus::Bus,
+    io::{BusCycle, BusOperation},
+};
+
+/// The bus as seen by another bus master, handed out by CPU::dma while the CPU is held off the bus.
+/// Every access takes one cycle away from the CPU.
+pub struct DmaBus<'a, B: Bus> {
+    bus: &'a mut B,
+    pub(crate) log: Vec<BusCycle>,
+}
+
+impl<'a, B: Bus> DmaBus<'a, B> {
+    pub(crate) fn new(bus: &'a mut B) -> Self {
+        return DmaBus {
+            bus,
+            log: Vec::new(),
+        };
+    }
+
+    /// Spends a cycle without touching the bus, a DMA controller waiting for an alignment cycle for example.
+    pub fn idle(&mut self) {
+        let address = match self.log.last() {
+            Some(cycle) => cycle.address,
+            None => 0,
+        };
+        self.log_cycle(address, 0, BusOperation::Internal);
+    }
+
+    /// Cycles taken from the CPU so far.
+    pub fn cycles(&self) -> u64 {
+        return self.log.len() as u64;
+    }
+
+    fn log_cycle(&mut self, address: u32, data: u8, operation: BusOperation) {
+        self.log.push(BusCycle {
+            address,
+            data,
+            operation,
+            sync: false,
+        });
+    }
+}
+
+impl<B: Bus> Bus for DmaBus<'_, B> {
+    fn read(&mut self, address: u32) -> u8 {
+        let data = self.bus.read(address);
+        self.log_cycle(address, data, BusOperation::Read);
+        return data;
+    }
+
+    // Not a bus cycle, so it isn't logged or taken from the CPU
+    fn peek(&mut self, address: u32) -> u8 {
+        return self.bus.peek(address);
+    }
+
+    fn write(&mut self, address: u32, data: u8) {
+        self.bus.write(address, data);
+        self.log_cycle(address, data, BusOperation::Write);
+    }
+}
@@ -0,0 +1,59 @@
+use std::{error::Error, fmt::Display};
+
+use super::{cpu::RunState, instructions::utils::AddressingModes};
+
+/// Everything that can go wrong while setting up or running the emulator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmulationError {
+    // The opcode isn't assigned on the selected variant
+    IllegalOpcode {
+        opcode: u8,
+        address: u32,
+    },
+    // An instruction was run with an addressing mode its handler can't work with
+    InvalidAddressingMode {
+        addressing_mode: AddressingModes,
+        address: u32,
+    },
+    // The bus reported an access it couldn't complete, see Bus::take_fault
+    BusFault {
+        address: u32,
+    },
+    // STP or a JAM opcode stopped the CPU, only a reset gets it going again
+    Halted {
+        state: RunState,
+        address: u32,
+    },
+    // A ROM image or memory layout that doesn't fit
+    LoadFailure {
+        address: u32,
+        reason: &'static str,
+    },
+}
+
+impl Display for EmulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Self::IllegalOpcode { opcode, address } => {
+                write!(f, "Illegal opcode ${:02X} at ${:04X}", opcode, address)
+            }
+            Self::InvalidAddressingMode {
+                addressing_mode,
+                address,
+            } => write!(
+                f,
+                "Invalid addressing mode {:?} at ${:04X}",
+                addressing_mode, address
+            ),
+            Self::BusFault { address } => write!(f, "Bus fault at ${:04X}", address),
+            Self::Halted { state, address } => {
+                write!(f, "CPU halted ({:?}) at ${:04X}", state, address)
+            }
+            Self::LoadFailure { address, reason } => {
+                write!(f, "Failed to load at ${:04X}: {}", address, reason)
+            }
+        };
+    }
+}
+
+impl Error for EmulationError {}
@@ -2,83 +2,308 @@ use std::{cell::RefCell, rc::Rc};
 
 use super::utils::AddressingModes;
 use crate::core::{
+    bus::Bus,
     cpu::CPU,
+    error::EmulationError,
     register::{DataRegister, StatusFlags, StatusRegister},
 };
 
 // Arithmetic functionality
 pub fn add_two_numbers(status_flags: &mut StatusRegister, first: u8, second: u8) -> u8 {
-    let carry_flag = status_flags.check_flag(StatusFlags::Carry) as u8;
-    let second_operand = second + carry_flag;
-    let sum = first.wrapping_add(second_operand);
-    status_flags.add_update_carry_flag(first, second_operand);
+    let carry_flag = status_flags.check_flag(StatusFlags::Carry) as u16;
+    let true_sum = first as u16 + second as u16 + carry_flag;
+    let sum = true_sum as u8;
+    if true_sum > 0xFF {
+        status_flags.set_flag(StatusFlags::Carry);
+    } else {
+        status_flags.clear_flag(StatusFlags::Carry);
+    }
     status_flags.update_nz_flags(sum);
 
     return sum;
 }
 
+// The 16 bit version of add_two_numbers for the 65C816's wide accumulator
+pub fn add_two_words(status_flags: &mut StatusRegister, first: u16, second: u16) -> u16 {
+    let carry_flag = status_flags.check_flag(StatusFlags::Carry) as u32;
+    let true_sum = first as u32 + second as u32 + carry_flag;
+    let sum = true_sum as u16;
+    if true_sum > 0xFFFF {
+        status_flags.set_flag(StatusFlags::Carry);
+    } else {
+        status_flags.clear_flag(StatusFlags::Carry);
+    }
+    status_flags.update_nz_flags_word(sum);
+
+    return sum;
+}
+
+// How the chip behaves when the decimal flag is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecimalMode {
+    Nmos,     // Only C is valid afterwards, N, V and Z come from intermediate results
+    Cmos,     // N and Z are valid, costs an extra cycle
+    Disabled, // The decimal flag can be set but ADC and SBC ignore it
+}
+
+// Decimal (BCD) arithmetic, see http://www.6502.org/tutorials/decimal_mode.html (appendix A)
+// Anything but DecimalMode::Cmos gets the NMOS flag behavior.
+pub fn add_decimal(
+    status_flags: &mut StatusRegister,
+    first: u8,
+    second: u8,
+    decimal_mode: DecimalMode,
+) -> u8 {
+    let carry_flag = status_flags.check_flag(StatusFlags::Carry) as i16;
+    let binary_sum = first.wrapping_add(second).wrapping_add(carry_flag as u8);
+
+    let mut low_nibble = (first & 0x0F) as i16 + (second & 0x0F) as i16 + carry_flag;
+    if low_nibble >= 0x0A {
+        low_nibble = ((low_nibble + 0x06) & 0x0F) + 0x10;
+    }
+    let mut sum = (first & 0xF0) as i16 + (second & 0xF0) as i16 + low_nibble;
+    // N and V are taken before the high nibble is adjusted, V treats the high nibbles as signed
+    let signed_sum = (first & 0xF0) as i8 as i16 + (second & 0xF0) as i8 as i16 + low_nibble;
+    let intermediate_negative = (sum & 0x80) != 0;
+    if sum >= 0xA0 {
+        sum += 0x60;
+    }
+    let result = (sum & 0xFF) as u8;
+
+    if sum >= 0x100 {
+        status_flags.set_flag(StatusFlags::Carry);
+    } else {
+        status_flags.clear_flag(StatusFlags::Carry);
+    }
+    if !(-128..=127).contains(&signed_sum) {
+        status_flags.set_flag(StatusFlags::Overflow);
+    } else {
+        status_flags.clear_flag(StatusFlags::Overflow);
+    }
+    if decimal_mode == DecimalMode::Cmos {
+        status_flags.update_nz_flags(result);
+    } else {
+        status_flags.update_nz_flags(binary_sum);
+        if intermediate_negative {
+            status_flags.set_flag(StatusFlags::Negative);
+        } else {
+            status_flags.clear_flag(StatusFlags::Negative);
+        }
+    }
+
+    return result;
+}
+
+pub fn subtract_decimal(
+    status_flags: &mut StatusRegister,
+    first: u8,
+    second: u8,
+    decimal_mode: DecimalMode,
+) -> u8 {
+    let borrow = 1 - status_flags.check_flag(StatusFlags::Carry) as i16;
+    // C and V are always the same as a binary subtraction, on NMOS N and Z are too
+    let binary_difference = add_two_numbers(status_flags, first, !second);
+    status_flags.update_overflow_flag(first, !second, binary_difference);
+
+    let low_nibble = (first & 0x0F) as i16 - (second & 0x0F) as i16 - borrow;
+    let result = if decimal_mode == DecimalMode::Cmos {
+        let mut difference = first as i16 - second as i16 - borrow;
+        if difference < 0 {
+            difference -= 0x60;
+        }
+        if low_nibble < 0 {
+            difference -= 0x06;
+        }
+        let difference = difference as u8;
+        status_flags.update_nz_flags(difference);
+        difference
+    } else {
+        let mut adjusted_low = low_nibble;
+        if adjusted_low < 0 {
+            adjusted_low = ((adjusted_low - 0x06) & 0x0F) - 0x10;
+        }
+        let mut difference = (first & 0xF0) as i16 - (second & 0xF0) as i16 + adjusted_low;
+        if difference < 0 {
+            difference -= 0x60;
+        }
+        difference as u8
+    };
+
+    return result;
+}
+
+// 16 bit decimal arithmetic is two 8 bit decimal operations chained through the carry, V comes from the high byte
+pub fn add_decimal_word(
+    status_flags: &mut StatusRegister,
+    first: u16,
+    second: u16,
+    decimal_mode: DecimalMode,
+) -> u16 {
+    let low_byte = add_decimal(status_flags, first as u8, second as u8, decimal_mode) as u16;
+    let high_byte = add_decimal(
+        status_flags,
+        (first >> 8) as u8,
+        (second >> 8) as u8,
+        decimal_mode,
+    ) as u16;
+    let result = (high_byte << 8) | low_byte;
+    status_flags.update_nz_flags_word(result);
+    return result;
+}
+
+pub fn subtract_decimal_word(
+    status_flags: &mut StatusRegister,
+    first: u16,
+    second: u16,
+    decimal_mode: DecimalMode,
+) -> u16 {
+    let low_byte = subtract_decimal(status_flags, first as u8, second as u8, decimal_mode) as u16;
+    let high_byte = subtract_decimal(
+        status_flags,
+        (first >> 8) as u8,
+        (second >> 8) as u8,
+        decimal_mode,
+    ) as u16;
+    let result = (high_byte << 8) | low_byte;
+    status_flags.update_nz_flags_word(result);
+    return result;
+}
+
 // ADC, SBC
-impl CPU {
-    pub fn sum_with_carry(&mut self, addressing_mode: &AddressingModes, subtract: bool) {
-        let address = self.fetch_address(&addressing_mode).unwrap(); // Should always return an address, this does not support an addressing mode that doesn't
-        let mut memory_data = self.memory_rc.borrow()[address];
-        if subtract {
-            memory_data = !memory_data;
-        }
-        let acc_data = self.accumulator_cell.borrow().value;
-        let sum = add_two_numbers(&mut self.processor_status_flags, acc_data, memory_data);
-        self.processor_status_flags
-            .update_overflow_flag(acc_data, memory_data, sum);
-        self.accumulator_cell.borrow_mut().value = sum;
+impl<B: Bus> CPU<B> {
+    pub fn sum_with_carry(
+        &mut self,
+        addressing_mode: &AddressingModes,
+        subtract: bool,
+    ) -> Result<(), EmulationError> {
+        let wide = self.accumulator_is_wide();
+        let address = self.operand_address(addressing_mode)?; // Should always return an address, this does not support an addressing mode that doesn't
+        let memory_data = self.read_operand(address, wide);
+        let acc_data = self.accumulator_cell.borrow().get_value(wide);
+        let decimal = self.processor_status_flags.check_flag(StatusFlags::Decimal)
+            && self.decimal_mode != DecimalMode::Disabled;
+        // The 65C02 reads the next opcode while it adjusts the result, the 65C816 gets the adjustment in for free
+        if decimal && self.decimal_mode == DecimalMode::Cmos && !self.variant.has_native_mode() {
+            let next_opcode = self
+                .program_counter
+                .value
+                .wrapping_add(addressing_mode.parameter_bytes() + 1);
+            self.dummy_read(next_opcode as u32);
+            self.cycles += 1;
+        }
+        let flags = &mut self.processor_status_flags;
+        let sum = if decimal {
+            match (subtract, wide) {
+                (false, false) => {
+                    add_decimal(flags, acc_data as u8, memory_data as u8, self.decimal_mode) as u16
+                }
+                (true, false) => {
+                    subtract_decimal(flags, acc_data as u8, memory_data as u8, self.decimal_mode)
+                        as u16
+                }
+                (false, true) => add_decimal_word(flags, acc_data, memory_data, self.decimal_mode),
+                (true, true) => {
+                    subtract_decimal_word(flags, acc_data, memory_data, self.decimal_mode)
+                }
+            }
+        } else if wide {
+            let operand = if subtract { !memory_data } else { memory_data };
+            let sum = add_two_words(flags, acc_data, operand);
+            flags.update_overflow_flag_word(acc_data, operand, sum);
+            sum
+        } else {
+            // Subtraction is addition of the ones' complement, the carry completes the two's complement
+            let acc_data = acc_data as u8;
+            let operand = if subtract {
+                !(memory_data as u8)
+            } else {
+                memory_data as u8
+            };
+            let sum = add_two_numbers(flags, acc_data, operand);
+            flags.update_overflow_flag(acc_data, operand, sum);
+            sum as u16
+        };
+        self.accumulator_cell.borrow_mut().set_value(sum, wide);
         self.program_counter
             .increment(addressing_mode.parameter_bytes());
+        return Ok(());
     }
 
     // Bitwise logic functionality
     fn bitwise_operations(
         &mut self,
         addressing_mode: &AddressingModes,
-        operation: impl Fn(u8, u8) -> u8,
-    ) {
-        let address = self.fetch_address(&addressing_mode).unwrap();
-        let memory_data = self.memory_rc.borrow()[address];
-        let op_result: u8;
+        operation: impl Fn(u16, u16) -> u16,
+    ) -> Result<(), EmulationError> {
+        let wide = self.accumulator_is_wide();
+        let address = self.operand_address(addressing_mode)?;
+        let memory_data = self.read_operand(address, wide);
+        let op_result: u16;
         {
             let mut accumulator = self.accumulator_cell.borrow_mut();
-            let acc_data = accumulator.value;
+            let acc_data = accumulator.get_value(wide);
             op_result = operation(acc_data, memory_data);
-            accumulator.value = op_result;
+            accumulator.set_value(op_result, wide);
         }
 
-        self.processor_status_flags.update_nz_flags(op_result);
+        self.processor_status_flags
+            .update_nz_flags_sized(op_result, wide);
         self.program_counter
             .increment(addressing_mode.parameter_bytes());
+        return Ok(());
     }
 
     // AND
-    pub fn bitwise_and(&mut self, addressing_mode: &AddressingModes) {
-        self.bitwise_operations(addressing_mode, |x, y| x & y);
+    pub fn bitwise_and(&mut self, addressing_mode: &AddressingModes) -> Result<(), EmulationError> {
+        return self.bitwise_operations(addressing_mode, |x, y| x & y);
     }
 
     // ORA
-    pub fn bitwise_or(&mut self, addressing_mode: &AddressingModes) {
-        self.bitwise_operations(addressing_mode, |x, y| x | y);
+    pub fn bitwise_or(&mut self, addressing_mode: &AddressingModes) -> Result<(), EmulationError> {
+        return self.bitwise_operations(addressing_mode, |x, y| x | y);
     }
 
     // EOR
-    pub fn bitwise_exclusive_or(&mut self, addressing_mode: &AddressingModes) {
-        self.bitwise_operations(addressing_mode, |x, y| x ^ y);
+    pub fn bitwise_exclusive_or(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        return self.bitwise_operations(addressing_mode, |x, y| x ^ y);
+    }
+
+    // Reads the operand of a read-modify-write instruction, None for the address means the accumulator
+    fn read_modify_operand(&self, address_option: Option<u32>, wide: bool) -> u16 {
+        return match address_option {
+            Some(addr) => self.read_operand(addr, wide),
+            None => self.accumulator_cell.borrow().get_value(wide),
+        };
+    }
+
+    fn write_modify_operand(
+        &self,
+        address_option: Option<u32>,
+        original: u16,
+        data: u16,
+        wide: bool,
+    ) {
+        match address_option {
+            Some(addr) => {
+                self.modify_cycle(addr, original as u8);
+                self.write_operand(addr, data, wide);
+            }
+            None => self.accumulator_cell.borrow_mut().set_value(data, wide),
+        };
     }
 
     // ASL, ROL
     pub fn left_shift(&mut self, addressing_mode: &AddressingModes, rotate: bool) {
-        let address_option = self.fetch_address(addressing_mode); // None means the addressing mode is the accumulator
-        let mut data = match address_option {
-            Some(addr) => self.memory_rc.borrow()[addr],
-            None => self.accumulator_cell.borrow().value,
-        };
-        let old_carry = self.processor_status_flags.check_flag(StatusFlags::Carry) as u8;
-        let new_carry = data & 0x80; // shift the MSB down to be a 1 if its set at all to be set in the carry.
+        let wide = self.accumulator_is_wide();
+        let sign_bit: u16 = if wide { 0x8000 } else { 0x80 };
+        let address_option = self.fetch_long_address(addressing_mode); // None means the addressing mode is the accumulator
+        let original = self.read_modify_operand(address_option, wide);
+        let mut data = original;
+        let old_carry = self.processor_status_flags.check_flag(StatusFlags::Carry) as u16;
+        let new_carry = data & sign_bit; // the MSB is shifted out into the carry
         data <<= 1;
 
         if new_carry != 0 {
@@ -90,23 +315,21 @@ impl CPU {
         if rotate {
             data |= old_carry;
         }
-        match address_option {
-            Some(addr) => self.memory_rc.borrow_mut()[addr] = data,
-            None => self.accumulator_cell.borrow_mut().value = data,
-        };
-        self.processor_status_flags.update_nz_flags(data);
+        self.write_modify_operand(address_option, original, data, wide);
+        self.processor_status_flags
+            .update_nz_flags_sized(data, wide);
         self.program_counter
             .increment(addressing_mode.parameter_bytes());
     }
 
     // LSR, ROR
     pub fn right_shift(&mut self, addressing_mode: &AddressingModes, rotate: bool) {
-        let old_carry = self.processor_status_flags.check_flag(StatusFlags::Carry) as u8;
-        let address_option = self.fetch_address(addressing_mode);
-        let mut data = match address_option {
-            Some(addr) => self.memory_rc.borrow()[addr],
-            None => self.accumulator_cell.borrow().value,
-        };
+        let wide = self.accumulator_is_wide();
+        let sign_bit: u16 = if wide { 0x8000 } else { 0x80 };
+        let old_carry = self.processor_status_flags.check_flag(StatusFlags::Carry);
+        let address_option = self.fetch_long_address(addressing_mode);
+        let original = self.read_modify_operand(address_option, wide);
+        let mut data = original;
         let new_carry = data & 1;
         data >>= 1;
         if new_carry != 0 {
@@ -115,69 +338,161 @@ impl CPU {
             self.processor_status_flags.clear_flag(StatusFlags::Carry);
         }
 
-        if rotate {
-            data |= old_carry << 7;
+        if rotate && old_carry {
+            data |= sign_bit;
         }
 
-        match address_option {
-            Some(addr) => self.memory_rc.borrow_mut()[addr] = data,
-            None => self.accumulator_cell.borrow_mut().value = data,
-        };
-
-        self.processor_status_flags.update_nz_flags(data);
+        self.write_modify_operand(address_option, original, data, wide);
+        self.processor_status_flags
+            .update_nz_flags_sized(data, wide);
         self.program_counter
             .increment(addressing_mode.parameter_bytes());
     }
 
-    // BIT
-    pub fn bit_instruction(&mut self, addressing_mode: &AddressingModes) {
-        let address = self.fetch_address(addressing_mode).unwrap();
-        let mem_operand = self.memory_rc.borrow()[address];
-        let result = mem_operand & self.accumulator_cell.borrow().value;
+    // BIT, the 65C02's BIT #$BB only updates Z
+    pub fn bit_instruction(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let wide = self.accumulator_is_wide();
+        let address = self.operand_address(addressing_mode)?;
+        let mem_operand = self.read_operand(address, wide);
+        let result = mem_operand & self.accumulator_cell.borrow().get_value(wide);
 
-        // Set Flags
+        // Set Flags, N and V are copied straight from the memory operand
         if result == 0 {
             self.processor_status_flags.set_flag(StatusFlags::Zero);
+        } else {
+            self.processor_status_flags.clear_flag(StatusFlags::Zero);
+        }
+
+        if matches!(
+            addressing_mode,
+            AddressingModes::Immediate | AddressingModes::ImmediateWord
+        ) {
+            self.program_counter
+                .increment(addressing_mode.parameter_bytes());
+            return Ok(());
         }
 
-        if (result & 1 << 7) != 0 {
+        let high_bits = if wide { mem_operand >> 8 } else { mem_operand };
+        if (high_bits & 1 << 7) != 0 {
             self.processor_status_flags.set_flag(StatusFlags::Negative);
         } else {
             self.processor_status_flags
                 .clear_flag(StatusFlags::Negative);
         }
 
-        if (result & 1 << 6) != 0 {
+        if (high_bits & 1 << 6) != 0 {
             self.processor_status_flags.set_flag(StatusFlags::Overflow);
         } else {
             self.processor_status_flags
                 .clear_flag(StatusFlags::Overflow);
         }
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // TSB, TRB (65C02). Z is set from A AND M, then the bits set in A are set or cleared in memory.
+    pub fn test_and_modify_bits(
+        &mut self,
+        addressing_mode: &AddressingModes,
+        set_bits: bool,
+    ) -> Result<(), EmulationError> {
+        let wide = self.accumulator_is_wide();
+        let address = self.operand_address(addressing_mode)?;
+        let acc_data = self.accumulator_cell.borrow().get_value(wide);
+        let mem_data = self.read_operand(address, wide);
+
+        if acc_data & mem_data == 0 {
+            self.processor_status_flags.set_flag(StatusFlags::Zero);
+        } else {
+            self.processor_status_flags.clear_flag(StatusFlags::Zero);
+        }
+        let result = if set_bits {
+            mem_data | acc_data
+        } else {
+            mem_data & !acc_data
+        };
+        self.modify_cycle(address, mem_data as u8);
+        self.write_operand(address, result, wide);
+
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // RMB, SMB (Rockwell and WDC 65C02). Clears or sets a single bit of a zeropage byte, no flags are affected.
+    pub fn modify_bit(
+        &mut self,
+        addressing_mode: &AddressingModes,
+        bit: u8,
+        set_bit: bool,
+    ) -> Result<(), EmulationError> {
+        let address = self.operand_address(addressing_mode)?;
+        let data = self.read_byte(address);
+        self.modify_cycle(address, data);
+        if set_bit {
+            self.write_byte(address, data | 1 << bit);
+        } else {
+            self.write_byte(address, data & !(1 << bit));
+        }
+
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
     }
 
+    // INC, DEC (INC A and DEC A on the 65C02)
     pub fn inc_dec_memory(&mut self, addressing_mode: &AddressingModes, dec: bool) {
-        let address = self.fetch_address(addressing_mode).unwrap();
-        let mut memory = self.memory_rc.borrow_mut();
-        let value = memory[address];
-        memory[address] = if dec {
+        let wide = self.accumulator_is_wide();
+        let address_option = self.fetch_long_address(addressing_mode); // None means the addressing mode is the accumulator
+        let value = self.read_modify_operand(address_option, wide);
+        let mut result = if dec {
             value.wrapping_sub(1)
         } else {
             value.wrapping_add(1)
         };
+        if !wide {
+            result &= 0xFF;
+        }
+        self.write_modify_operand(address_option, value, result, wide);
 
+        self.processor_status_flags
+            .update_nz_flags_sized(result, wide);
         self.program_counter
             .increment(addressing_mode.parameter_bytes());
     }
 
+    // INX, INY, DEX, DEY
     pub fn inc_dec_register(&mut self, register_cell: Rc<RefCell<DataRegister>>, dec: bool) {
+        let wide = self.register_is_wide(&register_cell);
         let mut register = register_cell.borrow_mut();
-        let value = register.value;
-        register.value = if dec {
+        let value = register.get_value(wide);
+        let mut result = if dec {
             value.wrapping_sub(1)
         } else {
             value.wrapping_add(1)
         };
+        if !wide {
+            result &= 0xFF;
+        }
+        register.set_value(result, wide);
+
+        self.processor_status_flags
+            .update_nz_flags_sized(result, wide);
+        self.program_counter.increment(0);
+    }
 
+    // XBA (65C816), swaps the two halves of the accumulator. N and Z follow the new low byte.
+    pub fn exchange_accumulator(&mut self) {
+        let mut accumulator = self.accumulator_cell.borrow_mut();
+        let low_byte = accumulator.value;
+        accumulator.value = accumulator.high;
+        accumulator.high = low_byte;
+        self.processor_status_flags
+            .update_nz_flags(accumulator.value);
         self.program_counter.increment(0);
     }
 }
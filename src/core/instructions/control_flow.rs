@@ -2,65 +2,264 @@ use std::{cell::RefCell, rc::Rc};
 
 use super::{alu, utils::AddressingModes, utils::BranchMode};
 use crate::core::{
-    cpu::CPU,
+    bus::Bus,
+    cpu::{RunState, COP_VECTOR, CPU, IRQ_VECTOR},
+    error::EmulationError,
     register::{DataRegister, StatusFlags},
 };
 
-impl CPU {
-    // CMP
+impl<B: Bus> CPU<B> {
+    // CMP, CPX, CPY
     pub fn compare(
         &mut self,
         addressing_mode: &AddressingModes,
         reg_cell: Rc<RefCell<DataRegister>>,
-    ) {
-        let address = self.fetch_address(addressing_mode).unwrap();
-        let mem_data = self.memory_rc.borrow()[address];
+    ) -> Result<(), EmulationError> {
+        let wide = self.register_is_wide(&reg_cell);
+        let address = self.operand_address(addressing_mode)?;
+        let mem_data = self.read_operand(address, wide);
+        let reg_data = reg_cell.borrow().get_value(wide);
         self.processor_status_flags.set_flag(StatusFlags::Carry); // Carry flag will be updated regardless
-        alu::add_two_numbers(
-            &mut self.processor_status_flags,
-            reg_cell.borrow().value,
-            !mem_data,
-        );
+        if wide {
+            alu::add_two_words(&mut self.processor_status_flags, reg_data, !mem_data);
+        } else {
+            alu::add_two_numbers(
+                &mut self.processor_status_flags,
+                reg_data as u8,
+                !(mem_data as u8),
+            );
+        }
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
     }
 
-    // JMP, JSR
-    pub fn jump(&mut self, addressing_mode: &AddressingModes, is_subroutine: bool, is_irq: bool) {
-        if let AddressingModes::Absolute | AddressingModes::Indirect | AddressingModes::Relative =
-            addressing_mode
+    // JMP, JSR, and the 65C816's JML and JSL which also change the program bank
+    pub fn jump(
+        &mut self,
+        addressing_mode: &AddressingModes,
+        is_subroutine: bool,
+    ) -> Result<(), EmulationError> {
+        if let AddressingModes::Absolute
+        | AddressingModes::Indirect
+        | AddressingModes::AbsoluteIndexIndirect
+        | AddressingModes::AbsoluteLong
+        | AddressingModes::AbsoluteIndirectLong = addressing_mode
         {
-            let pc_val = self.program_counter.value;
-            let address = self.fetch_address(addressing_mode).unwrap();
-            let delta = self.memory_rc.borrow_mut()[address] as i8;
-            let new_pc = pc_val.wrapping_add(delta as u16);
-            if is_subroutine {
-                // account for the current jump instruction. NOTE: this does not include the third byte of the JSR
-                // instruction due to the way the actual hardware works. RTS will increment PC by 1 before setting PC.
-                self.stack_pointer.push(self.program_counter.get_pch());
-                self.stack_pointer.push(self.program_counter.get_pcl());
-                if is_irq {
-                    self.push_status();
-                }
+            let long = matches!(
+                addressing_mode,
+                AddressingModes::AbsoluteLong | AddressingModes::AbsoluteIndirectLong
+            );
+            let new_pc = if is_subroutine {
+                self.call_address(addressing_mode)?
+            } else {
+                self.operand_address(addressing_mode)?
+            };
+            self.program_counter.value = (new_pc & 0xFFFF) as u16; // Jump
+            if long {
+                self.program_bank = (new_pc >> 16) as u8;
             }
-            self.program_counter.value = new_pc; // Jump
         } else {
-            todo!("Handle bad addressing modes");
+            return Err(self.invalid_addressing_mode(addressing_mode));
         }
+        return Ok(());
+    }
+
+    // Reads the target of JSR or JSL, pushing the return address in the middle of it the way the hardware does.
+    // The address of the last byte of the instruction is pushed, not the next instruction, RTS increments it.
+    fn call_address(&mut self, addressing_mode: &AddressingModes) -> Result<u32, EmulationError> {
+        let program_bank = (self.program_bank as u32) << 16;
+        let pc = self.program_counter.value;
+        let return_address = pc.wrapping_add(addressing_mode.parameter_bytes());
+        let low_byte = self.read_byte(program_bank | pc.wrapping_add(1) as u32) as u32;
+        let target = match addressing_mode {
+            // The 6502 has nowhere to keep the low byte but the stack pointer, it reads the stack while moving it
+            // there and only fetches the high byte after pushing
+            AddressingModes::Absolute if !self.variant.has_native_mode() => {
+                self.stack_dummy_read();
+                self.push_byte((return_address >> 8) as u8);
+                self.push_byte(return_address as u8);
+                let high_byte = self.read_byte(program_bank | pc.wrapping_add(2) as u32) as u32;
+                program_bank | (high_byte << 8) | low_byte
+            }
+            AddressingModes::Absolute => {
+                let high_byte = self.read_byte(program_bank | pc.wrapping_add(2) as u32) as u32;
+                self.dummy_read(program_bank | pc.wrapping_add(2) as u32);
+                self.push_byte((return_address >> 8) as u8);
+                self.push_byte(return_address as u8);
+                program_bank | (high_byte << 8) | low_byte
+            }
+            // JSL pushes the program bank before reading the new one
+            AddressingModes::AbsoluteLong => {
+                let high_byte = self.read_byte(program_bank | pc.wrapping_add(2) as u32) as u32;
+                self.push_byte(self.program_bank);
+                self.dummy_read(program_bank | pc.wrapping_add(2) as u32);
+                let bank = self.read_byte(program_bank | pc.wrapping_add(3) as u32) as u32;
+                self.push_byte((return_address >> 8) as u8);
+                self.push_byte(return_address as u8);
+                (bank << 16) | (high_byte << 8) | low_byte
+            }
+            // JSR (a,X) on the 65C816, the pointer is in the program bank
+            AddressingModes::AbsoluteIndexIndirect => {
+                self.push_byte((return_address >> 8) as u8);
+                self.push_byte(return_address as u8);
+                let high_byte = self.read_byte(program_bank | pc.wrapping_add(2) as u32) as u16;
+                self.dummy_read(program_bank | pc.wrapping_add(2) as u32);
+                let x = self.x_cell.borrow().get_word();
+                let pointer = ((high_byte << 8) | low_byte as u16).wrapping_add(x);
+                let target_low = self.read_byte(program_bank | pointer as u32) as u32;
+                let target_high =
+                    self.read_byte(program_bank | pointer.wrapping_add(1) as u32) as u32;
+                program_bank | (target_high << 8) | target_low
+            }
+            _ => return Err(self.invalid_addressing_mode(addressing_mode)),
+        };
+        return Ok(target);
     }
 
     // RTS
     pub fn subroutine_return(&mut self) {
-        let new_pcl = self.stack_pointer.pop();
-        let new_pch = self.stack_pointer.pop();
+        self.stack_dummy_read();
+        let new_pcl = self.pop_byte();
+        let new_pch = self.pop_byte();
 
         self.program_counter.set_pch(new_pch);
         self.program_counter.set_pcl(new_pcl);
+        // The pulled address is the last byte of the JSR, it's read while being stepped over
+        self.dummy_read(self.program_address());
         self.program_counter.increment(0);
     }
 
-    // BEQ, BNE, BMI, BCC, BCS, BVC, BVS, BPL
-    pub fn branch_exec(&mut self, branch_mode: BranchMode) {
+    // RTL (65C816), RTS that also pulls the program bank pushed by JSL
+    pub fn subroutine_return_long(&mut self) {
+        self.stack_dummy_read();
+        let new_pcl = self.pop_byte();
+        let new_pch = self.pop_byte();
+        self.program_bank = self.pop_byte();
+
+        self.program_counter.set_pch(new_pch);
+        self.program_counter.set_pcl(new_pcl);
+        self.program_counter.increment(0);
+    }
+
+    // BEQ, BNE, BMI, BCC, BCS, BVC, BVS, BPL, BRA
+    pub fn branch_exec(&mut self, branch_mode: BranchMode) -> Result<(), EmulationError> {
+        let address = self.operand_address(&AddressingModes::Relative)?;
+        let offset = self.read_byte(address) as i8;
+        // The offset is relative to the instruction following the branch
+        self.program_counter
+            .increment(AddressingModes::Relative.parameter_bytes());
         if branch_mode.verify(&self.processor_status_flags) {
-            self.jump(&AddressingModes::Relative, false, false);
+            self.take_branch(offset);
+        }
+        return Ok(());
+    }
+
+    // BBR, BBS (Rockwell and WDC 65C02). Branches when the given bit of a zeropage byte is clear or set.
+    pub fn branch_on_bit(&mut self, bit: u8, branch_if_set: bool) -> Result<(), EmulationError> {
+        let address = self.operand_address(&AddressingModes::ZeroPageRelative)?;
+        let value = self.read_byte(address);
+        self.dummy_read(address);
+        let program_bank = (self.program_bank as u32) << 16;
+        let offset =
+            self.read_byte(program_bank | self.program_counter.value.wrapping_add(2) as u32) as i8;
+        self.program_counter
+            .increment(AddressingModes::ZeroPageRelative.parameter_bytes());
+        if ((value >> bit) & 1 != 0) == branch_if_set {
+            self.take_branch(offset);
+        }
+        return Ok(());
+    }
+
+    // A taken branch costs one extra cycle, and another if it lands on a different page (not in 65C816 native mode).
+    // The first reads the next opcode while the offset is added. On a page crossing the NMOS chips read the target
+    // before the carry reaches its high byte, the 65C02 reads the next opcode again.
+    fn take_branch(&mut self, offset: i8) {
+        let next_pc = self.program_counter.value;
+        let target = next_pc.wrapping_add(offset as u16);
+        let program_bank = (self.program_bank as u32) << 16;
+        self.dummy_read(program_bank | next_pc as u32);
+        self.cycles += 1;
+        if (next_pc & 0xFF00) != (target & 0xFF00) && !self.is_native() {
+            let address = if self.variant.is_cmos() {
+                next_pc
+            } else {
+                (next_pc & 0xFF00) | (target & 0x00FF)
+            };
+            self.dummy_read(program_bank | address as u32);
+            self.cycles += 1;
+        }
+        self.program_counter.value = target;
+    }
+
+    // BRL (65C816), always taken with a 16 bit offset. Stays within the program bank.
+    pub fn branch_long(&mut self) -> Result<(), EmulationError> {
+        let address = self.operand_address(&AddressingModes::RelativeLong)?;
+        let offset = self.read_operand(address, true);
+        self.program_counter
+            .increment(AddressingModes::RelativeLong.parameter_bytes());
+        self.dummy_read(self.program_address());
+        self.program_counter.value = self.program_counter.value.wrapping_add(offset);
+        return Ok(());
+    }
+
+    // BRK
+    pub fn break_instruction(&mut self) {
+        // BRK skips the padding byte after its opcode, the return address is PC + 2
+        self.program_counter.increment(1);
+        self.enter_interrupt(IRQ_VECTOR, true);
+    }
+
+    // COP (65C816), a software interrupt like BRK through its own vector
+    pub fn coprocessor_instruction(&mut self) {
+        // Just like BRK, the signature byte after the opcode is skipped
+        self.program_counter.increment(1);
+        self.enter_interrupt(COP_VECTOR, false);
+    }
+
+    // RTI
+    pub fn interrupt_return(&mut self) {
+        // Unlike RTS the pulled address is not incremented, it already points at the next instruction
+        self.stack_dummy_read();
+        let flags = self.pop_byte();
+        self.processor_status_flags.set_mask(flags); // drops the break bit
+        let new_pcl = self.pop_byte();
+        let new_pch = self.pop_byte();
+
+        self.program_counter.set_pch(new_pch);
+        self.program_counter.set_pcl(new_pcl);
+        if self.is_native() {
+            self.program_bank = self.pop_byte();
+        }
+        self.sync_register_widths();
+    }
+
+    // WAI (65C02), sleeps until an interrupt line is asserted
+    pub fn wait_for_interrupt(&mut self) {
+        self.dummy_read(self.program_address().wrapping_add(1) & 0xFFFFFF);
+        self.program_counter.increment(0);
+        self.run_state = RunState::Waiting;
+    }
+
+    // STP (WDC 65C02), stops the clock until the next reset
+    pub fn stop(&mut self) {
+        self.dummy_read(self.program_address().wrapping_add(1) & 0xFFFFFF);
+        self.program_counter.increment(0);
+        self.run_state = RunState::Stopped;
+    }
+
+    // NOP, WDM. The ones with an operand read it, which includes the 65C02's unassigned opcodes and the undocumented
+    // NMOS NOPs.
+    pub fn no_operation(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        if addressing_mode.parameter_bytes() > 0 {
+            let address = self.operand_address(addressing_mode)?;
+            self.read_byte(address);
         }
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
     }
 }
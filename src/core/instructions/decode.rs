@@ -0,0 +1,266 @@
+use strum::{Display, EnumIter};
+
+use super::utils::AddressingModes;
+use crate::core::variant::CpuVariant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Display)]
+pub enum Mnemonic {
+    ADC,
+    AND,
+    ASL,
+    BBR,
+    BBS,
+    BCC,
+    BCS,
+    BEQ,
+    BIT,
+    BMI,
+    BNE,
+    BPL,
+    BRA,
+    BRK,
+    BRL,
+    BVC,
+    BVS,
+    CLC,
+    CLD,
+    CLI,
+    CLV,
+    CMP,
+    COP,
+    CPX,
+    CPY,
+    DEC,
+    DEX,
+    DEY,
+    EOR,
+    INC,
+    INX,
+    INY,
+    JML,
+    JMP,
+    JSL,
+    JSR,
+    LDA,
+    LDX,
+    LDY,
+    LSR,
+    MVN,
+    MVP,
+    NOP,
+    ORA,
+    PEA,
+    PEI,
+    PER,
+    PHA,
+    PHB,
+    PHD,
+    PHK,
+    PHP,
+    PHX,
+    PHY,
+    PLA,
+    PLB,
+    PLD,
+    PLP,
+    PLX,
+    PLY,
+    REP,
+    RMB,
+    ROL,
+    ROR,
+    RTI,
+    RTL,
+    RTS,
+    SBC,
+    SEC,
+    SED,
+    SEI,
+    SEP,
+    SMB,
+    STA,
+    STP,
+    STX,
+    STY,
+    STZ,
+    TAX,
+    TAY,
+    TCD,
+    TCS,
+    TDC,
+    TRB,
+    TSB,
+    TSC,
+    TSX,
+    TXA,
+    TXS,
+    TXY,
+    TYA,
+    TYX,
+    WAI,
+    WDM,
+    XBA,
+    XCE,
+
+    // Undocumented NMOS opcodes
+    ALR,
+    ANC,
+    ARR,
+    DCP,
+    ISC,
+    JAM,
+    LAS,
+    LAX,
+    LXA,
+    RLA,
+    RRA,
+    SAX,
+    SBX,
+    SHA,
+    SHX,
+    SHY,
+    SLO,
+    SRE,
+    TAS,
+    XAA,
+}
+
+// A decoded opcode, what step() hands back after running it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: u8,
+    pub mnemonic: Mnemonic,
+    pub addressing_mode: AddressingModes,
+    pub cycles: u8,         // Base cycle count
+    pub page_penalty: bool, // Takes an extra cycle when the indexed address crosses a page
+    pub undocumented: bool, // One of the NMOS opcodes that only work by accident of the chip's decoding logic
+}
+
+impl Instruction {
+    /// Total size of the instruction in bytes, opcode included.
+    pub fn size(&self) -> u16 {
+        return self.addressing_mode.parameter_bytes() + 1;
+    }
+
+    /// Switches the immediate mode to its two byte form when the register the instruction works on is 16 bits wide,
+    /// only ever the case on a 65C816 in native mode.
+    pub fn widen_immediate(&mut self, accumulator_wide: bool, index_wide: bool) {
+        if self.addressing_mode != AddressingModes::Immediate {
+            return;
+        }
+        let wide = match self.mnemonic {
+            Mnemonic::CPX | Mnemonic::CPY | Mnemonic::LDX | Mnemonic::LDY => index_wide,
+            Mnemonic::ADC
+            | Mnemonic::AND
+            | Mnemonic::BIT
+            | Mnemonic::CMP
+            | Mnemonic::EOR
+            | Mnemonic::LDA
+            | Mnemonic::ORA
+            | Mnemonic::SBC => accumulator_wide,
+            _ => false, // REP, SEP, COP and WDM always take a single byte
+        };
+        if wide {
+            self.addressing_mode = AddressingModes::ImmediateWord;
+        }
+    }
+
+    /// True for the stores and read-modify-write instructions, the ones that write their operand back to memory.
+    pub fn writes_memory(&self) -> bool {
+        if self.addressing_mode == AddressingModes::Accumulator {
+            return false;
+        }
+        return matches!(
+            self.mnemonic,
+            Mnemonic::ASL
+                | Mnemonic::DEC
+                | Mnemonic::INC
+                | Mnemonic::LSR
+                | Mnemonic::ROL
+                | Mnemonic::ROR
+                | Mnemonic::STA
+                | Mnemonic::STX
+                | Mnemonic::STY
+                | Mnemonic::STZ
+                | Mnemonic::TRB
+                | Mnemonic::TSB
+                | Mnemonic::DCP
+                | Mnemonic::ISC
+                | Mnemonic::RLA
+                | Mnemonic::RRA
+                | Mnemonic::SAX
+                | Mnemonic::SHA
+                | Mnemonic::SHX
+                | Mnemonic::SHY
+                | Mnemonic::SLO
+                | Mnemonic::SRE
+                | Mnemonic::TAS
+        );
+    }
+
+    /// The bit RMB, SMB, BBR and BBS work on, encoded in the high nibble of the opcode.
+    pub fn bit_index(&self) -> u8 {
+        return (self.opcode >> 4) & 0x07;
+    }
+}
+
+/// Everything instructions.csv says about an opcode on the variants in variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeMetadata {
+    pub opcode: u8,
+    pub mnemonic: Mnemonic,
+    pub addressing_mode: AddressingModes,
+    pub bytes: u8, // Opcode included, the 65C816's immediate operands grow by a byte with 16 bit registers
+    pub cycles: u8, // Base cycle count
+    pub page_penalty: bool,
+    // Status register bits, M and X are the 65C816's width flags. XCE also swaps the E flag, which has no bit here.
+    pub flags_read: u8,
+    pub flags_written: u8,
+    pub undocumented: bool,
+    pub variants: &'static [CpuVariant],
+}
+
+include!(concat!(env!("OUT_DIR"), "/opcode_table.rs"));
+
+/// The row of OPCODE_TABLE for an opcode on the given CPU variant, None for the unassigned opcodes.
+pub fn opcode_metadata(opcode: u8, variant: CpuVariant) -> Option<&'static OpcodeMetadata> {
+    let index = DECODE_INDEX[variant_index(variant)][opcode as usize];
+    if index == u16::MAX {
+        return None;
+    }
+    return Some(&OPCODE_TABLE[index as usize]);
+}
+
+/// Looks up an opcode for the given CPU variant. Returns None for the unassigned opcodes.
+pub fn decode(opcode: u8, variant: CpuVariant) -> Option<Instruction> {
+    let metadata = opcode_metadata(opcode, variant)?;
+    return Some(Instruction {
+        opcode,
+        mnemonic: metadata.mnemonic,
+        addressing_mode: metadata.addressing_mode,
+        cycles: metadata.cycles,
+        page_penalty: metadata.page_penalty,
+        undocumented: metadata.undocumented,
+    });
+}
+
+/// The NOP a 65C02 runs in place of an opcode it doesn't assign, as long and as slow as on the real chip.
+/// See http://www.6502.org/tutorials/65c02opcodes.html#7
+pub fn unassigned_nop(opcode: u8) -> Instruction {
+    let (addressing_mode, cycles) = match opcode {
+        0x44 => (AddressingModes::ZeroPage, 3),
+        0x54 | 0xD4 | 0xF4 => (AddressingModes::ZeroPageXIndex, 4),
+        0x5C => (AddressingModes::Absolute, 8),
+        0xDC | 0xFC => (AddressingModes::Absolute, 4),
+        _ if opcode & 0x0F == 0x02 => (AddressingModes::Immediate, 2),
+        _ => (AddressingModes::Implied, 1),
+    };
+    return Instruction {
+        opcode,
+        mnemonic: Mnemonic::NOP,
+        addressing_mode,
+        cycles,
+        page_penalty: false,
+        undocumented: true,
+    };
+}
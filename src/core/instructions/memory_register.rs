@@ -1,28 +1,28 @@
 use std::{cell::RefCell, rc::Rc};
 
 use super::utils::AddressingModes;
-use crate::core::{cpu::CPU, register::DataRegister};
+use crate::core::{bus::Bus, cpu::CPU, error::EmulationError, register::DataRegister};
 
 // new LDA, LDX, LDY
 
-impl CPU {
+impl<B: Bus> CPU<B> {
     // LDA, LDX, LDY
     pub fn load_instruction(
         &mut self,
         addressing_mode: AddressingModes,
         destination_reg_cell: Rc<RefCell<DataRegister>>,
-    ) {
-        let address: u16 = self.fetch_address(&addressing_mode).unwrap();
-        let data;
-
-        let memory = self.memory_rc.borrow_mut();
-        data = memory[address];
+    ) -> Result<(), EmulationError> {
+        let wide = self.register_is_wide(&destination_reg_cell);
+        let address = self.operand_address(&addressing_mode)?;
+        let data = self.read_operand(address, wide);
 
         let mut register = destination_reg_cell.borrow_mut();
-        register.value = data;
-        self.processor_status_flags.update_nz_flags(register.value);
+        register.set_value(data, wide);
+        self.processor_status_flags
+            .update_nz_flags_sized(data, wide);
         self.program_counter
             .increment(addressing_mode.parameter_bytes());
+        return Ok(());
     }
 
     // STA, STX, STY
@@ -30,27 +30,121 @@ impl CPU {
         &mut self,
         addressing_mode: AddressingModes,
         source_reg_cell: Rc<RefCell<DataRegister>>,
-    ) {
-        let address = self.fetch_address(&addressing_mode).unwrap();
-        {
-            let mut memory = self.memory_rc.borrow_mut();
-            memory[address] = source_reg_cell.borrow().value;
-        }
+    ) -> Result<(), EmulationError> {
+        let wide = self.register_is_wide(&source_reg_cell);
+        let address = self.operand_address(&addressing_mode)?;
+        let data = source_reg_cell.borrow().get_value(wide);
+        self.write_operand(address, data, wide);
 
         self.program_counter
             .increment(addressing_mode.parameter_bytes());
+        return Ok(());
     }
 
-    // TAX, TAY, TSX, TXA, TXS, TYA
+    // STZ (65C02)
+    pub fn store_zero(&mut self, addressing_mode: AddressingModes) -> Result<(), EmulationError> {
+        let address = self.operand_address(&addressing_mode)?;
+        self.write_operand(address, 0, self.accumulator_is_wide());
+
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // TAX, TAY, TXA, TYA, and TXY, TYX on the 65C816. The width of the destination decides how much is copied,
+    // the high byte of an 8 bit accumulator is left alone.
     pub fn transfer_register(
         &mut self,
         source_register: Rc<RefCell<DataRegister>>,
         destination_register: Rc<RefCell<DataRegister>>,
     ) {
-        let data = source_register.borrow().value;
-        destination_register.borrow_mut().value = data;
+        let wide = self.register_is_wide(&destination_register);
+        let data = source_register.borrow().get_value(wide);
+        destination_register.borrow_mut().set_value(data, wide);
         self.processor_status_flags
-            .update_nz_flags(destination_register.borrow().value);
+            .update_nz_flags_sized(data, wide);
         self.program_counter.increment(0); // Only possible addressing mode is implied which has no parameters.
     }
+
+    // TSX
+    pub fn transfer_from_stack_pointer(&mut self, destination_register: Rc<RefCell<DataRegister>>) {
+        let wide = self.register_is_wide(&destination_register);
+        let data = self.stack_pointer.get_word();
+        destination_register.borrow_mut().set_value(data, wide);
+        self.processor_status_flags
+            .update_nz_flags_sized(data, wide);
+        self.program_counter.increment(0);
+    }
+
+    // TXS, and TCS on the 65C816
+    pub fn transfer_to_stack_pointer(&mut self, source_register: Rc<RefCell<DataRegister>>) {
+        // Unlike the other transfers, TXS leaves the flags alone
+        let data = source_register.borrow().get_word();
+        self.stack_pointer.set_word(data);
+        self.program_counter.increment(0);
+    }
+
+    // TSC (65C816), always copies all 16 bits
+    pub fn transfer_stack_pointer_to_accumulator(&mut self) {
+        let data = self.stack_pointer.get_word();
+        self.accumulator_cell.borrow_mut().set_word(data);
+        self.processor_status_flags.update_nz_flags_word(data);
+        self.program_counter.increment(0);
+    }
+
+    // TCD (65C816)
+    pub fn transfer_to_direct_page(&mut self) {
+        self.direct_page = self.accumulator_cell.borrow().get_word();
+        self.processor_status_flags
+            .update_nz_flags_word(self.direct_page);
+        self.program_counter.increment(0);
+    }
+
+    // TDC (65C816)
+    pub fn transfer_from_direct_page(&mut self) {
+        self.accumulator_cell
+            .borrow_mut()
+            .set_word(self.direct_page);
+        self.processor_status_flags
+            .update_nz_flags_word(self.direct_page);
+        self.program_counter.increment(0);
+    }
+
+    // MVN, MVP (65C816). Copies one byte from the source bank at X to the destination bank at Y per execution,
+    // counting the 16 bit accumulator down. The instruction repeats itself until the count wraps past zero.
+    pub fn block_move(&mut self, increment: bool) -> Result<(), EmulationError> {
+        let address = self.operand_address(&AddressingModes::BlockMove)?;
+        let destination_bank = self.read_byte(address);
+        let source_bank = self.read_byte((address + 1) & 0xFFFFFF);
+        let wide = self.index_is_wide();
+        let source = self.x_cell.borrow().get_value(wide);
+        let destination = self.y_cell.borrow().get_value(wide);
+
+        let data = self.read_byte(((source_bank as u32) << 16) | source as u32);
+        self.write_byte(((destination_bank as u32) << 16) | destination as u32, data);
+        self.data_bank = destination_bank;
+
+        let step = |index: u16| {
+            let index = if increment {
+                index.wrapping_add(1)
+            } else {
+                index.wrapping_sub(1)
+            };
+            if wide {
+                index
+            } else {
+                index & 0xFF
+            }
+        };
+        self.x_cell.borrow_mut().set_value(step(source), wide);
+        self.y_cell.borrow_mut().set_value(step(destination), wide);
+
+        let count = self.accumulator_cell.borrow().get_word().wrapping_sub(1);
+        self.accumulator_cell.borrow_mut().set_word(count);
+        if count == 0xFFFF {
+            self.program_counter
+                .increment(AddressingModes::BlockMove.parameter_bytes());
+        }
+        return Ok(());
+    }
 }
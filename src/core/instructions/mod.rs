@@ -2,9 +2,11 @@ use std::{thread, time};
 
 pub mod alu;
 pub mod control_flow;
+pub mod decode;
 pub mod memory_register;
 pub mod stack;
 pub mod status_flags;
+pub mod undocumented;
 pub mod utils;
 
 pub fn no_operation() {
@@ -1,31 +1,116 @@
-use crate::core::cpu::CPU;
+use std::{cell::RefCell, rc::Rc};
 
-impl CPU {
-    // PHA
-    pub fn push_accumulator(&mut self) {
-        let acc_value = self.accumulator_cell.borrow().value;
-        self.stack_pointer.push(acc_value);
+use super::utils::AddressingModes;
+use crate::core::{bus::Bus, cpu::CPU, error::EmulationError, register::DataRegister};
+
+impl<B: Bus> CPU<B> {
+    // PHA, PHX, PHY
+    pub fn push_register(&mut self, source_register: Rc<RefCell<DataRegister>>) {
+        let wide = self.register_is_wide(&source_register);
+        let data = source_register.borrow().get_value(wide);
+        self.push_value(data, wide);
         self.program_counter.increment(0);
     }
 
-    // PHP
+    // PHP, pushes with the break bit set just like BRK
     pub fn push_status(&mut self) {
-        let flags = self.processor_status_flags.get_flags();
-        self.stack_pointer.push(flags);
+        let flags = self.processor_status_flags.get_pushed_flags(true);
+        self.push_byte(flags);
         self.program_counter.increment(0);
     }
 
-    // PLA
-    pub fn pop_accumulator(&mut self) {
-        let acc_value = self.stack_pointer.pop();
-        self.accumulator_cell.borrow_mut().value = acc_value;
+    // PLA, PLX, PLY
+    pub fn pop_register(&mut self, destination_register: Rc<RefCell<DataRegister>>) {
+        self.stack_dummy_read();
+        let wide = self.register_is_wide(&destination_register);
+        let data = self.pop_value(wide);
+        destination_register.borrow_mut().set_value(data, wide);
+        self.processor_status_flags
+            .update_nz_flags_sized(data, wide);
         self.program_counter.increment(0);
     }
 
     // PLP
     pub fn pop_status(&mut self) {
-        let flags = self.stack_pointer.pop();
+        self.stack_dummy_read();
+        let flags = self.pop_byte();
         self.processor_status_flags.set_mask(flags);
+        self.sync_register_widths();
+        self.program_counter.increment(0);
+    }
+
+    // PHB, PHK (65C816)
+    pub fn push_bank(&mut self, bank: u8) {
+        self.push_byte(bank);
+        self.program_counter.increment(0);
+    }
+
+    // PLB (65C816)
+    pub fn pop_data_bank(&mut self) {
+        self.stack_dummy_read();
+        self.data_bank = self.pop_byte();
+        self.processor_status_flags.update_nz_flags(self.data_bank);
+        self.program_counter.increment(0);
+    }
+
+    // PHD (65C816)
+    pub fn push_direct_page(&mut self) {
+        self.push_value(self.direct_page, true);
         self.program_counter.increment(0);
     }
+
+    // PLD (65C816)
+    pub fn pop_direct_page(&mut self) {
+        self.stack_dummy_read();
+        self.direct_page = self.pop_value(true);
+        self.processor_status_flags
+            .update_nz_flags_word(self.direct_page);
+        self.program_counter.increment(0);
+    }
+
+    // PEA, PEI, PER (65C816). Pushes a 16 bit value worked out from the operand: PEA pushes the operand itself,
+    // PEI the word stored at a direct page address and PER the branch target of the operand.
+    pub fn push_effective_address(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let data = match addressing_mode {
+            AddressingModes::Absolute => (self.operand_address(addressing_mode)? & 0xFFFF) as u16,
+            AddressingModes::ZeroPage => {
+                let address = self.operand_address(addressing_mode)?;
+                self.read_operand(address, true)
+            }
+            AddressingModes::RelativeLong => {
+                let address = self.operand_address(addressing_mode)?;
+                let offset = self.read_operand(address, true);
+                let next_pc = self
+                    .program_counter
+                    .value
+                    .wrapping_add(addressing_mode.parameter_bytes() + 1);
+                next_pc.wrapping_add(offset)
+            }
+            _ => return Err(self.invalid_addressing_mode(addressing_mode)),
+        };
+        self.push_value(data, true);
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // Pushes the high byte first so the value ends up little endian in memory
+    fn push_value(&mut self, data: u16, wide: bool) {
+        if wide {
+            self.push_byte((data >> 8) as u8);
+        }
+        self.push_byte((data & 0xFF) as u8);
+    }
+
+    fn pop_value(&mut self, wide: bool) -> u16 {
+        let low_byte = self.pop_byte() as u16;
+        if !wide {
+            return low_byte;
+        }
+        let high_byte = self.pop_byte() as u16;
+        return (high_byte << 8) | low_byte;
+    }
 }
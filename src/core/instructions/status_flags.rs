@@ -1,28 +1,33 @@
-use crate::core::{cpu::CPU, register::StatusFlags};
+use super::utils::AddressingModes;
+use crate::core::{bus::Bus, cpu::CPU, error::EmulationError, register::StatusFlags};
 
-impl CPU {
+impl<B: Bus> CPU<B> {
     // Clear flags
 
     // CLC
     pub fn clear_carry_flag(&mut self) {
         self.processor_status_flags.clear_flag(StatusFlags::Carry);
+        self.program_counter.increment(0);
     }
 
     // CLD
     pub fn clear_decimal_flag(&mut self) {
         self.processor_status_flags.clear_flag(StatusFlags::Decimal);
+        self.program_counter.increment(0);
     }
 
     // CLI
     pub fn clear_interrupt_disable_flag(&mut self) {
         self.processor_status_flags
             .clear_flag(StatusFlags::InterruptDisable);
+        self.program_counter.increment(0);
     }
 
     // CLV
     pub fn clear_overflow_flag(&mut self) {
         self.processor_status_flags
             .clear_flag(StatusFlags::Overflow);
+        self.program_counter.increment(0);
     }
 
     // Set flags
@@ -30,16 +35,58 @@ impl CPU {
     // SEC
     pub fn set_carry_flag(&mut self) {
         self.processor_status_flags.set_flag(StatusFlags::Carry);
+        self.program_counter.increment(0);
     }
 
     // SED
     pub fn set_decimal_flag(&mut self) {
         self.processor_status_flags.set_flag(StatusFlags::Decimal);
+        self.program_counter.increment(0);
     }
 
     // SEI
     pub fn set_interrupt_disable_flag(&mut self) {
         self.processor_status_flags
             .set_flag(StatusFlags::InterruptDisable);
+        self.program_counter.increment(0);
+    }
+}
+
+// 65C816 status instructions
+impl<B: Bus> CPU<B> {
+    // REP, clears every flag set in the immediate operand
+    pub fn reset_status_bits(&mut self) -> Result<(), EmulationError> {
+        let address = self.operand_address(&AddressingModes::Immediate)?;
+        let mask = self.read_byte(address);
+        self.processor_status_flags.clear_mask(mask);
+        self.sync_register_widths();
+        self.program_counter
+            .increment(AddressingModes::Immediate.parameter_bytes());
+        return Ok(());
+    }
+
+    // SEP, sets every flag set in the immediate operand
+    pub fn set_status_bits(&mut self) -> Result<(), EmulationError> {
+        let address = self.operand_address(&AddressingModes::Immediate)?;
+        let mask = self.read_byte(address);
+        self.processor_status_flags.set_bits(mask);
+        self.sync_register_widths();
+        self.program_counter
+            .increment(AddressingModes::Immediate.parameter_bytes());
+        return Ok(());
+    }
+
+    // XCE, swaps the carry with the hidden emulation flag. Going either way leaves 8 bit registers behind.
+    pub fn exchange_carry_emulation(&mut self) {
+        let flags = &mut self.processor_status_flags;
+        let carry = flags.check_flag(StatusFlags::Carry);
+        if flags.is_emulation() {
+            flags.set_flag(StatusFlags::Carry);
+        } else {
+            flags.clear_flag(StatusFlags::Carry);
+        }
+        flags.set_emulation(carry);
+        self.sync_register_widths();
+        self.program_counter.increment(0);
     }
 }
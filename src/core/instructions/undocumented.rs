@@ -0,0 +1,321 @@
+use super::{alu::DecimalMode, utils::AddressingModes};
+use crate::core::{
+    bus::Bus,
+    cpu::{RunState, CPU},
+    error::EmulationError,
+    register::StatusFlags,
+};
+
+// The undocumented opcodes of the NMOS 6502, see http://www.oxyron.de/html/opcodes02.html.
+// Most of them are two documented instructions run back to back by the decoding logic, those are built out of the
+// handlers of the documented ones.
+impl<B: Bus> CPU<B> {
+    // Runs two instruction handlers over the same operand. Each handler moves the program counter on by itself,
+    // so it is put back in between and moved past the whole instruction at the end. The second handler gets the
+    // operand the first one read or wrote from inside the CPU, it only goes over the bus once.
+    fn combine(
+        &mut self,
+        addressing_mode: &AddressingModes,
+        first: impl FnOnce(&mut Self) -> Result<(), EmulationError>,
+        second: impl FnOnce(&mut Self) -> Result<(), EmulationError>,
+    ) -> Result<(), EmulationError> {
+        let pc = self.program_counter.value;
+        first(self)?;
+        self.program_counter.value = pc;
+        self.reuse_operand.set(true);
+        let result = second(self);
+        self.reuse_operand.set(false);
+        result?;
+        self.program_counter.value = pc;
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // SLO, ASL then ORA
+    pub fn shift_left_or(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        return self.combine(
+            addressing_mode,
+            |cpu| {
+                cpu.left_shift(addressing_mode, false);
+                return Ok(());
+            },
+            |cpu| cpu.bitwise_or(addressing_mode),
+        );
+    }
+
+    // RLA, ROL then AND
+    pub fn rotate_left_and(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        return self.combine(
+            addressing_mode,
+            |cpu| {
+                cpu.left_shift(addressing_mode, true);
+                return Ok(());
+            },
+            |cpu| cpu.bitwise_and(addressing_mode),
+        );
+    }
+
+    // SRE, LSR then EOR
+    pub fn shift_right_exclusive_or(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        return self.combine(
+            addressing_mode,
+            |cpu| {
+                cpu.right_shift(addressing_mode, false);
+                return Ok(());
+            },
+            |cpu| cpu.bitwise_exclusive_or(addressing_mode),
+        );
+    }
+
+    // RRA, ROR then ADC with the carry ROR shifted out
+    pub fn rotate_right_add(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        return self.combine(
+            addressing_mode,
+            |cpu| {
+                cpu.right_shift(addressing_mode, true);
+                return Ok(());
+            },
+            |cpu| cpu.sum_with_carry(addressing_mode, false),
+        );
+    }
+
+    // DCP, DEC then CMP
+    pub fn decrement_compare(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let a = self.accumulator_cell.clone();
+        return self.combine(
+            addressing_mode,
+            |cpu| {
+                cpu.inc_dec_memory(addressing_mode, true);
+                return Ok(());
+            },
+            |cpu| cpu.compare(addressing_mode, a),
+        );
+    }
+
+    // ISC, INC then SBC
+    pub fn increment_subtract(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        return self.combine(
+            addressing_mode,
+            |cpu| {
+                cpu.inc_dec_memory(addressing_mode, false);
+                return Ok(());
+            },
+            |cpu| cpu.sum_with_carry(addressing_mode, true),
+        );
+    }
+
+    // LAX, LDA and LDX at once
+    pub fn load_accumulator_index(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let a = self.accumulator_cell.clone();
+        let x = self.x_cell.clone();
+        return self.combine(
+            addressing_mode,
+            |cpu| cpu.load_instruction(*addressing_mode, a),
+            |cpu| cpu.load_instruction(*addressing_mode, x),
+        );
+    }
+
+    // SAX, stores A AND X without touching the flags
+    pub fn store_accumulator_and_index(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let address = self.operand_address(addressing_mode)?;
+        let data = self.accumulator_cell.borrow().value & self.x_cell.borrow().value;
+        self.write_byte(address, data);
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // ANC, AND with bit 7 of the result copied into the carry
+    pub fn and_carry(&mut self, addressing_mode: &AddressingModes) -> Result<(), EmulationError> {
+        self.bitwise_and(addressing_mode)?;
+        if self
+            .processor_status_flags
+            .check_flag(StatusFlags::Negative)
+        {
+            self.processor_status_flags.set_flag(StatusFlags::Carry);
+        } else {
+            self.processor_status_flags.clear_flag(StatusFlags::Carry);
+        }
+        return Ok(());
+    }
+
+    // ALR, AND then LSR A
+    pub fn and_shift_right(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        return self.combine(
+            addressing_mode,
+            |cpu| cpu.bitwise_and(addressing_mode),
+            |cpu| {
+                cpu.right_shift(&AddressingModes::Accumulator, false);
+                return Ok(());
+            },
+        );
+    }
+
+    // ARR, AND then ROR A, except that the flags come out of the adder: C is bit 6 and V is bit 6 XOR bit 5.
+    // In decimal mode the result also gets a BCD correction of sorts, and N is the carry that was rotated in.
+    pub fn and_rotate_right(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let address = self.operand_address(addressing_mode)?;
+        let anded = self.accumulator_cell.borrow().value & self.read_byte(address);
+        let flags = &mut self.processor_status_flags;
+        let old_carry = flags.check_flag(StatusFlags::Carry);
+        let mut result = (anded >> 1) | ((old_carry as u8) << 7);
+        flags.update_nz_flags(result);
+
+        let decimal =
+            flags.check_flag(StatusFlags::Decimal) && self.decimal_mode != DecimalMode::Disabled;
+        let (carry, overflow) = if decimal {
+            let overflow = (anded ^ result) & 0x40 != 0;
+            if (anded & 0x0F) + (anded & 0x01) > 0x05 {
+                result = (result & 0xF0) | (result.wrapping_add(0x06) & 0x0F);
+            }
+            let carry = (anded as u16 & 0xF0) + (anded as u16 & 0x10) > 0x50;
+            if carry {
+                result = result.wrapping_add(0x60);
+            }
+            (carry, overflow)
+        } else {
+            (result & 0x40 != 0, ((result >> 6) ^ (result >> 5)) & 1 != 0)
+        };
+
+        if carry {
+            flags.set_flag(StatusFlags::Carry);
+        } else {
+            flags.clear_flag(StatusFlags::Carry);
+        }
+        if overflow {
+            flags.set_flag(StatusFlags::Overflow);
+        } else {
+            flags.clear_flag(StatusFlags::Overflow);
+        }
+        self.accumulator_cell.borrow_mut().value = result;
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // SBX, X = (A AND X) - operand. The carry is set like CMP and the borrow isn't used.
+    pub fn subtract_from_and_index(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let address = self.operand_address(addressing_mode)?;
+        let operand = self.read_byte(address);
+        let anded = self.accumulator_cell.borrow().value & self.x_cell.borrow().value;
+        let result = anded.wrapping_sub(operand);
+        if anded >= operand {
+            self.processor_status_flags.set_flag(StatusFlags::Carry);
+        } else {
+            self.processor_status_flags.clear_flag(StatusFlags::Carry);
+        }
+        self.processor_status_flags.update_nz_flags(result);
+        self.x_cell.borrow_mut().value = result;
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // XAA, A = (A OR magic) AND X AND operand. LXA, A = X = (A OR magic) AND operand.
+    pub fn magic_and(
+        &mut self,
+        addressing_mode: &AddressingModes,
+        load_index: bool,
+    ) -> Result<(), EmulationError> {
+        let address = self.operand_address(addressing_mode)?;
+        let operand = self.read_byte(address);
+        let accumulator = self.accumulator_cell.borrow().value | self.magic_constant;
+        let result = if load_index {
+            let result = accumulator & operand;
+            self.x_cell.borrow_mut().value = result;
+            result
+        } else {
+            accumulator & self.x_cell.borrow().value & operand
+        };
+        self.accumulator_cell.borrow_mut().value = result;
+        self.processor_status_flags.update_nz_flags(result);
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // SHA, SHX, SHY. Stores the value ANDed with the high byte of the base address plus one. When the index crosses
+    // a page the high byte of the target address is replaced with the stored value as well.
+    pub fn store_and_high_byte(
+        &mut self,
+        addressing_mode: &AddressingModes,
+        data: u8,
+    ) -> Result<(), EmulationError> {
+        let base_address = self.operand_base_address(addressing_mode)?;
+        let mut address = self.operand_address(addressing_mode)?;
+        let high_byte = ((base_address >> 8) & 0xFF) as u8;
+        let data = data & high_byte.wrapping_add(1);
+        if self.crosses_page(addressing_mode) {
+            address = ((data as u32) << 8) | (address & 0xFF);
+        }
+        self.write_byte(address, data);
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // TAS, S = A AND X, then stored like SHA
+    pub fn transfer_and_store_high_byte(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let data = self.accumulator_cell.borrow().value & self.x_cell.borrow().value;
+        self.stack_pointer.set_pointer(data);
+        return self.store_and_high_byte(addressing_mode, data);
+    }
+
+    // LAS, A = X = S = operand AND S
+    pub fn load_and_stack_pointer(
+        &mut self,
+        addressing_mode: &AddressingModes,
+    ) -> Result<(), EmulationError> {
+        let address = self.operand_address(addressing_mode)?;
+        let result = self.read_byte(address) & self.stack_pointer.get_pointer();
+        self.accumulator_cell.borrow_mut().value = result;
+        self.x_cell.borrow_mut().value = result;
+        self.stack_pointer.set_pointer(result);
+        self.processor_status_flags.update_nz_flags(result);
+        self.program_counter
+            .increment(addressing_mode.parameter_bytes());
+        return Ok(());
+    }
+
+    // JAM, the chip locks up with the opcode on the bus until it is reset. The program counter stays on the opcode.
+    pub fn jam(&mut self) {
+        self.run_state = RunState::Jammed;
+    }
+}
@@ -1,11 +1,12 @@
 use std::fmt::Display;
 
-use crate::core::register::{StatusRegister, StatusFlags};
+use crate::core::register::{StatusFlags, StatusRegister};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddressingModes {
     // Param == Operand, considering all words with little endian as thats how they will appear in machine code.
-    Accumulator,       // OPC A
-    Absolute,          // OPC $LLHH where $LLHH is an address (2 params)
+    Accumulator,           // OPC A
+    Absolute,              // OPC $LLHH where $LLHH is an address (2 params)
     AbsoluteXIndex, // OPC $LLHH, X, where X is the value in the X register and the indexed address is $LLHH + X with Carry
     AbsoluteYIndex, // OPC $LLHH, X, where X is the value in the Y register and the indexed address is $LLHH + Y with Carry
     Immediate,      // OPC #$BB where the parameter is just the value $BB
@@ -17,6 +18,21 @@ pub enum AddressingModes {
     ZeroPage,          // OPC $LL. Param is zeropage address, high byte is $00, low byte is $LL.
     ZeroPageXIndex, // OPC $LL, X. Param is a zeropage address offset by the number in the X register so $LL + X (addition without carry)
     ZeroPageYIndex, // OPC $LL, Y. Param is a zeropage address offset by the number in the Y register so $LL + X (addition without carry)
+    ZeroPageIndirect, // OPC ($LL). 65C02 only, the effective address is the word stored at zeropage address $LL.
+    AbsoluteIndexIndirect, // OPC ($LLHH, X). 65C02 JMP only, the effective address is the word stored at $LLHH + X.
+    ZeroPageRelative, // OPC $LL, $BB. BBR and BBS only, tests a bit of zeropage address $LL and branches to PC + SIGNED $BB.
+
+    // 65C816 only. The zeropage modes above are relative to the direct page register, absolute addresses to the data bank.
+    ImmediateWord, // OPC #$LLHH, the immediate modes when the register they work on is 16 bits wide
+    AbsoluteLong,  // OPC $LLHHBB, a full 24 bit address with the bank byte $BB last
+    AbsoluteLongXIndex, // OPC $LLHHBB, X. The 24 bit address $BBHHLL + X
+    AbsoluteIndirectLong, // OPC [$LLHH], JML only. The effective address is the 24 bit address stored at $LLHH in bank 0
+    ZeroPageIndirectLong, // OPC [$LL]. The effective address is the 24 bit address stored at direct page address $LL
+    ZeroPageIndirectLongYIndex, // OPC [$LL], Y. The 24 bit address stored at direct page address $LL, plus Y
+    StackRelative,              // OPC $LL, S. The effective address is S + $LL in bank 0
+    StackRelativeIndirectYIndex, // OPC ($LL, S), Y. The word stored at S + $LL, plus Y
+    RelativeLong,               // OPC $LLHH. BRL and PER, target location is PC + SIGNED $HHLL
+    BlockMove,                  // OPC $DD, $SS. MVN and MVP, the destination and source banks
 }
 
 impl Display for AddressingModes {
@@ -35,32 +51,25 @@ impl Display for AddressingModes {
             Self::ZeroPage => "ZeroPage",
             Self::ZeroPageXIndex => "ZeroPageXIndex",
             Self::ZeroPageYIndex => "ZeroPageYIndex",
+            Self::ZeroPageIndirect => "ZeroPageIndirect",
+            Self::AbsoluteIndexIndirect => "AbsoluteIndexIndirect",
+            Self::ZeroPageRelative => "ZeroPageRelative",
+            Self::ImmediateWord => "ImmediateWord",
+            Self::AbsoluteLong => "AbsoluteLong",
+            Self::AbsoluteLongXIndex => "AbsoluteLongXIndex",
+            Self::AbsoluteIndirectLong => "AbsoluteIndirectLong",
+            Self::ZeroPageIndirectLong => "ZeroPageIndirectLong",
+            Self::ZeroPageIndirectLongYIndex => "ZeroPageIndirectLongYIndex",
+            Self::StackRelative => "StackRelative",
+            Self::StackRelativeIndirectYIndex => "StackRelativeIndirectYIndex",
+            Self::RelativeLong => "RelativeLong",
+            Self::BlockMove => "BlockMove",
         };
         return write!(f, "{}", string);
     }
 }
 
-// https://www.masswerk.at/6502/6502_instruction_set.html
-impl AddressingModes {
-    pub fn parameter_bytes(&self) -> u16 {
-        match self {
-            Self::Accumulator => 0,
-            Self::Absolute => 2,
-            Self::AbsoluteXIndex => 2,
-            Self::AbsoluteYIndex => 2,
-            Self::Immediate => 1,
-            Self::Implied => 0,
-            Self::Indirect => 2,
-            Self::PreIndexIndirect => 1,
-            Self::PostIndexIndirect => 1,
-            Self::Relative => 1,
-            Self::ZeroPage => 1,
-            Self::ZeroPageXIndex => 1,
-            Self::ZeroPageYIndex => 1,
-        }
-    }
-}
-
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BranchMode {
     BMI,
     BNE,
@@ -70,6 +79,7 @@ pub enum BranchMode {
     BCS,
     BCC,
     BEQ,
+    BRA, // 65C02, always taken
 }
 
 impl BranchMode {
@@ -83,16 +93,18 @@ impl BranchMode {
             Self::BPL => !flag_reg.check_flag(StatusFlags::Negative), // N = 0
             Self::BNE => !flag_reg.check_flag(StatusFlags::Zero), // Z = 0
             Self::BMI => flag_reg.check_flag(StatusFlags::Negative), // N = 1
+            Self::BRA => true,
         }
     }
 }
 
 // TODO: Move to register.rs
 #[cfg(test)]
+#[allow(clippy::useless_vec, clippy::bool_assert_comparison)]
 mod tests {
     use std::vec;
 
-    use crate::core::register::{StatusRegister, StatusFlags};
+    use crate::core::register::{StatusFlags, StatusRegister};
 
     #[test]
     fn overflow_test_addition() {
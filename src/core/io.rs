@@ -1,10 +1,43 @@
-// Handles buffers for 6502
-// TODO: Figure out a use for
+/// What the CPU does with the bus during a cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusOperation {
+    Read,
+    Write,
+    // A cycle spent inside the CPU. The 65C816 flags these with VDA and VPA low, on the others it stands in for a
+    // dummy access that isn't put on the bus.
+    Internal,
+}
+
+/// One clock cycle's worth of bus activity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusCycle {
+    pub address: u32, // The 65C816 puts the bank on the data lines first, the others only drive the low 16 bits
+    pub data: u8,
+    pub operation: BusOperation,
+    pub sync: bool, // This read is an opcode fetch
+}
+
+// Handles buffers and control lines for 6502
 #[derive(Debug)]
 pub struct PinIO {
-    pub data_buffer: u8, // Represents D0-D7
+    // Only driven while the CPU is cycle stepped, they hold whatever the last bus cycle put on them
+    pub data_buffer: u8,     // Represents D0-D7
     pub address_buffer: u16, // Represents A0-A15
-                         // TODO: add more pins
+    pub bank_address: u8,    // The 65C816's A16-A23, multiplexed onto D0-D7
+    pub read: bool,          // R/W, high for a read
+    pub sync: bool, // SYNC, high while fetching an opcode (VPA and VDA both high on the 65C816)
+
+    // Interrupt inputs. The real lines are active low, here true means the line is being pulled low (asserted).
+    pub irq: bool,   // IRQB, level triggered and masked by the interrupt disable flag
+    nmi: bool,       // NMIB, edge triggered, use set_nmi so the edge gets latched
+    nmi_edge: bool,  // A falling edge was seen on NMIB and has not been serviced yet
+    pub reset: bool, // RESB, the CPU is held while asserted and resets once released
+
+    // RDY is active high, false means a slow device or DMA controller is pulling it low to hold the CPU.
+    // The NMOS chips only stop on a read cycle, the CMOS ones on any cycle.
+    pub rdy: bool,
+    so: bool,      // SO, set overflow, use set_so so the edge gets latched
+    so_edge: bool, // A falling edge was seen on SO and the overflow flag has not been set yet
 }
 
 impl PinIO {
@@ -12,15 +45,68 @@ impl PinIO {
         return PinIO {
             data_buffer: 0,
             address_buffer: 0,
+            bank_address: 0,
+            read: true,
+            sync: false,
+            irq: false,
+            nmi: false,
+            nmi_edge: false,
+            reset: false,
+            rdy: true,
+            so: false,
+            so_edge: false,
         };
     }
+
+    /// Puts a bus cycle on the address, data and control lines.
+    pub fn drive(&mut self, cycle: &BusCycle) {
+        self.address_buffer = (cycle.address & 0xFFFF) as u16;
+        self.bank_address = (cycle.address >> 16) as u8;
+        self.data_buffer = cycle.data;
+        self.read = cycle.operation != BusOperation::Write;
+        self.sync = cycle.sync;
+    }
+
+    pub fn set_nmi(&mut self, asserted: bool) {
+        if asserted && !self.nmi {
+            self.nmi_edge = true;
+        }
+        self.nmi = asserted;
+    }
+
+    pub fn get_nmi(&self) -> bool {
+        return self.nmi;
+    }
+
+    /// Asserts or releases SO. Only the falling edge matters, it sets the overflow flag.
+    pub fn set_so(&mut self, asserted: bool) {
+        if asserted && !self.so {
+            self.so_edge = true;
+        }
+        self.so = asserted;
+    }
+
+    pub fn get_so(&self) -> bool {
+        return self.so;
+    }
+
+    /// Returns true if an SO edge is waiting to set the overflow flag, clearing it.
+    pub fn take_so_edge(&mut self) -> bool {
+        let edge = self.so_edge;
+        self.so_edge = false;
+        return edge;
+    }
+
+    /// Returns true if an NMI edge is waiting to be serviced, clearing it.
+    pub fn take_nmi_edge(&mut self) -> bool {
+        let edge = self.nmi_edge;
+        self.nmi_edge = false;
+        return edge;
+    }
 }
 
 impl Default for PinIO {
     fn default() -> Self {
-        return PinIO {
-            data_buffer: 0,
-            address_buffer: 0,
-        };
+        return PinIO::new();
     }
 }
@@ -1,7 +1,11 @@
+pub mod bus;
 pub mod cpu;
+pub mod dma;
+pub mod error;
 pub mod instructions;
 pub mod io;
 pub mod register;
+pub mod variant;
 
 pub fn test() {
     println!("Hello from core.");
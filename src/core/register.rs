@@ -1,18 +1,17 @@
-use std::{cell::RefCell, error::Error, fmt::Display, rc::Rc};
-
-use crate::peripherals::memory::VirtualMemory;
 use strum::EnumIter;
 
 #[derive(Debug)]
 pub struct DataRegister {
     name: String,
     pub value: u8,
+    pub high: u8, // Only used by the 65C816, B for the accumulator or the high byte of a 16 bit index register
 }
 impl DataRegister {
     pub fn new(p_name: impl Into<String>) -> Self {
         return DataRegister {
             name: p_name.into(),
             value: 0,
+            high: 0,
         };
     }
 
@@ -20,8 +19,36 @@ impl DataRegister {
         return self.name.as_str();
     }
 
+    /// Both bytes of the register as 0xHHLL, the full C accumulator or 16 bit index register of the 65C816.
+    pub fn get_word(&self) -> u16 {
+        return ((self.high as u16) << 8) | self.value as u16;
+    }
+
+    pub fn set_word(&mut self, word: u16) {
+        self.value = (word & 0xFF) as u8;
+        self.high = (word >> 8) as u8;
+    }
+
+    /// The low byte, or the whole register when it is 16 bits wide.
+    pub fn get_value(&self, wide: bool) -> u16 {
+        if wide {
+            return self.get_word();
+        }
+        return self.value as u16;
+    }
+
+    /// Sets the low byte, or the whole register when it is 16 bits wide. The high byte is left alone otherwise.
+    pub fn set_value(&mut self, data: u16, wide: bool) {
+        if wide {
+            self.set_word(data);
+        } else {
+            self.value = (data & 0xFF) as u8;
+        }
+    }
+
     pub fn reset_register(&mut self) {
         self.value = 0;
+        self.high = 0;
     }
 }
 
@@ -55,7 +82,7 @@ impl ProgramCounter {
         } else {
             self.value &= 0xFF; // clear higher byte
             let zero_extended_byte = byte as u16;
-            self.value |= (zero_extended_byte << 8) as u16;
+            self.value |= zero_extended_byte << 8;
         }
     }
 
@@ -75,8 +102,9 @@ impl ProgramCounter {
     }
 
     /// Increments program counter at least by 1. Adds how many parameters were used into the sum. 1 + number of parameters used
+    /// Wraps around from $FFFF to $0000 like the real program counter.
     pub fn increment(&mut self, num_params: u16) {
-        self.value += num_params + 1;
+        self.value = self.value.wrapping_add(num_params + 1);
     }
 
     pub fn reset_register(&mut self) {
@@ -85,36 +113,70 @@ impl ProgramCounter {
 }
 
 #[derive(Debug)]
-// Register for the pointer to manage the 256 byte stack
+// Register for the pointer to manage the 256 byte stack, pushes and pops go through CPU::push_byte and CPU::pop_byte
+// so they show up on the bus like any other access
 pub struct StackPointerRegister {
     page: u8, // the high byte of the stack pointer address range
     pointer: u8,
-    memory_rc: Rc<RefCell<VirtualMemory>>,
+    native: bool, // 65C816 native mode, the stack can be anywhere in bank 0
 }
 
 impl StackPointerRegister {
-    pub fn new(p_page: u8, p_start_addr: u8, p_memory_rc: Rc<RefCell<VirtualMemory>>) -> Self {
+    pub fn new(p_page: u8, p_start_addr: u8) -> Self {
         return StackPointerRegister {
             page: p_page,
             pointer: p_start_addr,
-            memory_rc: p_memory_rc,
+            native: false,
         };
     }
 
-    pub fn push(&mut self, p_data: u8) {
-        self.memory_rc.borrow_mut()[((self.page as u16) << 8) | self.pointer as u16] = p_data;
-        self.pointer = self.pointer.wrapping_sub(1); // decrement stack pointer, allows for overflows
+    /// Moves the pointer down after a push without touching memory, wrapping within page 1 outside of native mode.
+    pub fn decrement(&mut self) {
+        if self.native {
+            self.set_word(self.get_word().wrapping_sub(1));
+        } else {
+            self.pointer = self.pointer.wrapping_sub(1); // decrement stack pointer, allows for overflows
+        }
     }
-    pub fn pop(&mut self) -> u8 {
-        self.pointer = self.pointer.wrapping_add(1); // increment stack pointer, allows for overflows
-        let byte = self.memory_rc.borrow()[((self.page as u16) << 8) | self.pointer as u16];
-        return byte;
+
+    /// Moves the pointer up before a pop without touching memory.
+    pub fn increment(&mut self) {
+        if self.native {
+            self.set_word(self.get_word().wrapping_add(1));
+        } else {
+            self.pointer = self.pointer.wrapping_add(1); // increment stack pointer, allows for overflows
+        }
+    }
+
+    /// The full address the stack pointer points at, 0x01LL unless a 65C816 has moved the stack.
+    pub fn get_word(&self) -> u16 {
+        return ((self.page as u16) << 8) | self.pointer as u16;
+    }
+
+    /// Moves the stack, outside of native mode only the low byte changes.
+    pub fn set_word(&mut self, word: u16) {
+        if self.native {
+            self.page = (word >> 8) as u8;
+        }
+        self.pointer = (word & 0xFF) as u8;
+    }
+
+    /// Switches between the 65C816's native 16 bit stack pointer and the 6502's stack on page 1.
+    pub fn set_native(&mut self, native: bool) {
+        self.native = native;
+        if !native {
+            self.page = 0x01;
+        }
     }
 
     pub fn get_pointer(&self) -> u8 {
         return self.pointer;
     }
 
+    pub fn set_pointer(&mut self, p_pointer: u8) {
+        self.pointer = p_pointer;
+    }
+
     pub fn reset_register(&mut self) {
         self.page = 0x01;
         self.pointer = 0xFF;
@@ -127,7 +189,6 @@ pub enum StatusFlags {
     Zero,
     InterruptDisable,
     Decimal,
-    BRK,
     Overflow,
     Negative,
 }
@@ -139,23 +200,35 @@ impl StatusFlags {
             Self::Zero => 1 << 1,
             Self::InterruptDisable => 1 << 2,
             Self::Decimal => 1 << 3,
-            Self::BRK => 1 << 4,
             Self::Overflow => 1 << 6,
             Self::Negative => 1 << 7,
         };
     }
 }
 
+// Bits that only exist in the copy of the flags pushed onto the stack. B is set when PHP or BRK did the
+// push and clear for IRQ and NMI, bit 5 is not wired to anything and always reads as 1.
+pub const BREAK_MASK: u8 = 1 << 4;
+pub const UNUSED_MASK: u8 = 1 << 5;
+
+// In 65C816 native mode the two bits are real flags selecting the register widths, a set bit means 8 bits.
+pub const INDEX_SELECT_MASK: u8 = 1 << 4; // X, index registers
+pub const MEMORY_SELECT_MASK: u8 = 1 << 5; // M, accumulator and memory
+
 // The flag byte of the 6502 are Negative, Overflow, (padding bit), Break mark (BRK) command, decimal mode, Interupt Request, Zero, and Carry.
-// Each are to be
+// The break mark is not a real flag, see BREAK_MASK.
 #[derive(Debug)]
 pub struct StatusRegister {
     flags: u8,
+    emulation: bool, // The 65C816's hidden E flag, always set on the 6502 and 65C02
 }
 
 impl StatusRegister {
     pub fn new() -> Self {
-        return StatusRegister { flags: 0b00100000 };
+        return StatusRegister {
+            flags: 0b00100000,
+            emulation: true,
+        };
     }
 
     fn bit_manager(&mut self, flag: StatusFlags, to_set: bool) {
@@ -186,19 +259,62 @@ impl StatusRegister {
         return self.flags & mask == mask;
     }
 
+    /// Replaces the flags with the mask, the break and unused bits of the mask are ignored outside of native mode.
     pub fn set_mask(&mut self, mask: u8) {
+        if !self.emulation {
+            self.flags = mask;
+            return;
+        }
         self.flags &= 0;
-        self.flags |= mask | 32;
+        self.flags |= (mask & !BREAK_MASK) | UNUSED_MASK;
+    }
+
+    /// Sets every flag in the mask, SEP on the 65C816.
+    pub fn set_bits(&mut self, mask: u8) {
+        if self.emulation {
+            self.flags |= mask & !BREAK_MASK;
+        } else {
+            self.flags |= mask;
+        }
     }
 
     pub fn clear_mask(&mut self, mask: u8) {
-        self.flags &= !mask | 32;
+        if self.emulation {
+            self.flags &= !mask | UNUSED_MASK;
+        } else {
+            self.flags &= !mask;
+        }
+    }
+
+    pub fn is_emulation(&self) -> bool {
+        return self.emulation;
+    }
+
+    /// Sets the 65C816's E flag. Either way M and X come out set, which are the unused and break bits in emulation mode.
+    pub fn set_emulation(&mut self, emulation: bool) {
+        self.emulation = emulation;
+        if emulation {
+            self.flags = (self.flags & !BREAK_MASK) | UNUSED_MASK;
+        } else {
+            self.flags |= INDEX_SELECT_MASK | MEMORY_SELECT_MASK;
+        }
     }
 
     pub fn get_flags(&self) -> u8 {
         return self.flags;
     }
 
+    /// The flags as they are pushed onto the stack, with the break bit set if PHP or BRK did the push.
+    pub fn get_pushed_flags(&self, is_break: bool) -> u8 {
+        if !self.emulation {
+            return self.flags; // Bit 4 is X in native mode, BRK has its own vector instead
+        }
+        if is_break {
+            return self.flags | BREAK_MASK | UNUSED_MASK;
+        }
+        return self.flags | UNUSED_MASK;
+    }
+
     pub fn add_update_carry_flag(&mut self, first_operand: u8, second_operand: u8) {
         // Tests by summing numbers together and detecting if it overflows or underflows
         // The inverse of the carry flag is the borrow flag. ~C = B
@@ -226,6 +342,36 @@ impl StatusRegister {
         }
     }
 
+    pub fn update_overflow_flag_word(&mut self, m: u16, n: u16, result: u16) {
+        if (m ^ result) & (n ^ result) & 0x8000 != 0 {
+            self.set_flag(StatusFlags::Overflow)
+        } else {
+            self.clear_flag(StatusFlags::Overflow)
+        }
+    }
+
+    /// Updates N and Z from the low byte of data, or all of it if it is 16 bits wide.
+    pub fn update_nz_flags_sized(&mut self, data: u16, wide: bool) {
+        if wide {
+            self.update_nz_flags_word(data);
+        } else {
+            self.update_nz_flags(data as u8);
+        }
+    }
+
+    pub fn update_nz_flags_word(&mut self, data: u16) {
+        if data & 0x8000 != 0 {
+            self.set_flag(StatusFlags::Negative);
+        } else {
+            self.clear_flag(StatusFlags::Negative);
+        }
+        if data == 0 {
+            self.set_flag(StatusFlags::Zero);
+        } else {
+            self.clear_flag(StatusFlags::Zero);
+        }
+    }
+
     pub fn update_nz_flags(&mut self, data: u8) {
         let signed_data = data as i8;
         if signed_data < 0 {
@@ -241,20 +387,8 @@ impl StatusRegister {
     }
 }
 
-#[derive(Debug)]
-pub struct RegisterError {
-    err_msg: String,
-}
-impl From<&str> for RegisterError {
-    fn from(value: &str) -> Self {
-        return RegisterError {
-            err_msg: value.to_owned(),
-        };
-    }
-}
-impl Display for RegisterError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.err_msg)
+impl Default for StatusRegister {
+    fn default() -> Self {
+        return StatusRegister::new();
     }
 }
-impl Error for RegisterError {}
@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+use strum::EnumIter;
+
+use super::instructions::alu::DecimalMode;
+
+// The members of the 6502 family the emulator can behave like
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter)]
+pub enum CpuVariant {
+    #[default]
+    Nmos6502, // Original MOS 6502
+    Wdc65C02,      // WDC W65C02S
+    Rockwell65C02, // Rockwell R65C02
+    Ricoh2A03,     // NMOS core without decimal mode, used in the NES
+    Wdc65C816,     // WDC W65C816S, starts in 65C02 emulation mode
+}
+
+impl Display for CpuVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            Self::Nmos6502 => "NMOS 6502",
+            Self::Wdc65C02 => "WDC 65C02",
+            Self::Rockwell65C02 => "Rockwell R65C02",
+            Self::Ricoh2A03 => "Ricoh 2A03",
+            Self::Wdc65C816 => "WDC 65C816",
+        };
+        return write!(f, "{}", string);
+    }
+}
+
+impl CpuVariant {
+    pub fn is_cmos(&self) -> bool {
+        return matches!(self, Self::Wdc65C02 | Self::Rockwell65C02 | Self::Wdc65C816);
+    }
+
+    /// Has the 65C816's native mode, 16 bit registers and 24 bit addresses.
+    pub fn has_native_mode(&self) -> bool {
+        return *self == Self::Wdc65C816;
+    }
+
+    /// JMP ($xxFF) reads the high byte of the target from $xx00 instead of crossing into the next page.
+    pub fn has_indirect_jump_bug(&self) -> bool {
+        return !self.is_cmos();
+    }
+
+    /// Read-modify-write instructions write the unmodified operand back before writing the result, the 65C02 reads
+    /// it a second time instead. The 65C816 only does this in emulation mode.
+    pub fn writes_operand_twice(&self) -> bool {
+        return !matches!(self, Self::Wdc65C02 | Self::Rockwell65C02);
+    }
+
+    /// The CMOS parts clear the decimal flag when taking an interrupt or BRK.
+    pub fn clears_decimal_on_interrupt(&self) -> bool {
+        return self.is_cmos();
+    }
+
+    pub fn decimal_mode(&self) -> DecimalMode {
+        return match self {
+            Self::Nmos6502 => DecimalMode::Nmos,
+            Self::Wdc65C02 | Self::Rockwell65C02 | Self::Wdc65C816 => DecimalMode::Cmos,
+            Self::Ricoh2A03 => DecimalMode::Disabled,
+        };
+    }
+}
@@ -1,6 +1,7 @@
 pub mod common;
 pub mod core;
 pub mod peripherals;
+pub mod tools;
 
 pub fn lib_function() {
     println!("Hello from lib");
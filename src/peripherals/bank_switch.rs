@@ -0,0 +1,191 @@
+use super::memory::VirtualMemory;
+use crate::core::{bus::Bus, error::EmulationError};
+
+// Banks an image doesn't fill all the way are padded like an erased EPROM
+const ERASED_BYTE: u8 = 0xFF;
+
+#[derive(Debug)]
+struct BankWindow {
+    start: u16,
+    size: u16,
+    control_address: u16, // A write here selects the bank shown in the window
+    banks: Vec<Vec<u8>>,
+    writable: bool, // RAM banks take writes, ROM banks ignore them
+    selected: usize,
+}
+
+impl BankWindow {
+    fn contains(&self, address: u16) -> bool {
+        return address >= self.start && address - self.start < self.size;
+    }
+}
+
+/// VirtualMemory with windows that a write to a control register can point at different banks, like the mapper
+/// chips on cartridges and larger single board computers. Anything outside a window goes to the memory underneath.
+#[derive(Debug)]
+pub struct BankedMemory {
+    memory: VirtualMemory,
+    windows: Vec<BankWindow>,
+}
+
+impl BankedMemory {
+    pub fn new(memory: VirtualMemory) -> Self {
+        return BankedMemory {
+            memory,
+            windows: Vec::new(),
+        };
+    }
+
+    /// The fixed memory outside the windows, load the parts of the image that never move with load_rom.
+    pub fn memory(&self) -> &VirtualMemory {
+        return &self.memory;
+    }
+
+    pub fn memory_mut(&mut self) -> &mut VirtualMemory {
+        return &mut self.memory;
+    }
+
+    /// Splits an image of any size into size byte ROM banks and shows them one at a time at start.
+    /// Returns the window's index, bank 0 is selected to begin with.
+    pub fn rom_window(
+        &mut self,
+        start: u16,
+        size: u16,
+        control_address: u16,
+        image: Vec<u8>,
+    ) -> Result<usize, EmulationError> {
+        if image.is_empty() {
+            return Err(EmulationError::LoadFailure {
+                address: start as u32,
+                reason: "Can't bank switch an empty image",
+            });
+        }
+        let banks = image
+            .chunks(size.max(1) as usize)
+            .map(|chunk| {
+                let mut bank = chunk.to_vec();
+                bank.resize(size as usize, ERASED_BYTE);
+                bank
+            })
+            .collect();
+        return self.add_window(start, size, control_address, banks, false);
+    }
+
+    /// Adds bank_count zeroed RAM banks of size bytes shown one at a time at start.
+    pub fn ram_window(
+        &mut self,
+        start: u16,
+        size: u16,
+        control_address: u16,
+        bank_count: usize,
+    ) -> Result<usize, EmulationError> {
+        if bank_count == 0 {
+            return Err(EmulationError::LoadFailure {
+                address: start as u32,
+                reason: "A RAM window needs at least one bank",
+            });
+        }
+        let banks = vec![vec![0; size as usize]; bank_count];
+        return self.add_window(start, size, control_address, banks, true);
+    }
+
+    pub fn bank_count(&self, window: usize) -> usize {
+        return self.windows[window].banks.len();
+    }
+
+    pub fn selected_bank(&self, window: usize) -> usize {
+        return self.windows[window].selected;
+    }
+
+    /// Selects a bank the same way a write to the control register does, bank numbers wrap around the bank count.
+    pub fn select_bank(&mut self, window: usize, bank: usize) {
+        let window = &mut self.windows[window];
+        window.selected = bank % window.banks.len();
+    }
+
+    fn add_window(
+        &mut self,
+        start: u16,
+        size: u16,
+        control_address: u16,
+        banks: Vec<Vec<u8>>,
+        writable: bool,
+    ) -> Result<usize, EmulationError> {
+        if size == 0 || start as u32 + size as u32 > 0x10000 {
+            return Err(EmulationError::LoadFailure {
+                address: start as u32,
+                reason: "Bank window must fit inside the 64 KiB address space",
+            });
+        }
+        let end = start + (size - 1);
+        let overlaps = self
+            .windows
+            .iter()
+            .any(|window| start <= window.start + (window.size - 1) && window.start <= end);
+        if overlaps {
+            return Err(EmulationError::LoadFailure {
+                address: start as u32,
+                reason: "Bank windows overlap",
+            });
+        }
+        self.windows.push(BankWindow {
+            start,
+            size,
+            control_address,
+            banks,
+            writable,
+            selected: 0,
+        });
+        return Ok(self.windows.len() - 1);
+    }
+}
+
+impl BankedMemory {
+    // The window a bank 0 address falls in, the windows only cover the 64 KiB address space so a 65C816's accesses
+    // to the banks above pass straight through to the memory underneath
+    fn window_at(&self, address: u32) -> Option<&BankWindow> {
+        let bank_address = u16::try_from(address).ok()?;
+        return self
+            .windows
+            .iter()
+            .find(|window| window.contains(bank_address));
+    }
+}
+
+impl Bus for BankedMemory {
+    fn read(&mut self, address: u32) -> u8 {
+        if let Some(window) = self.window_at(address) {
+            return window.banks[window.selected][(address as u16 - window.start) as usize];
+        }
+        return self.memory.read(address);
+    }
+
+    fn peek(&mut self, address: u32) -> u8 {
+        if let Some(window) = self.window_at(address) {
+            return window.banks[window.selected][(address as u16 - window.start) as usize];
+        }
+        return self.memory.peek(address);
+    }
+
+    fn write(&mut self, address: u32, data: u8) {
+        let bank_address = match u16::try_from(address) {
+            Ok(bank_address) => bank_address,
+            Err(_) => return self.memory.write(address, data),
+        };
+        let mut handled = false;
+        for window in self.windows.iter_mut() {
+            if window.control_address == bank_address {
+                window.selected = data as usize % window.banks.len();
+                handled = true;
+            } else if window.contains(bank_address) {
+                if window.writable {
+                    window.banks[window.selected][(bank_address - window.start) as usize] = data;
+                }
+                handled = true;
+            }
+        }
+        if !handled {
+            self.memory.write(address, data);
+        }
+    }
+}
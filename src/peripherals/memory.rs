@@ -1,49 +1,85 @@
 use std::{
-    error::Error,
-    fmt::Display,
     ops::{Index, IndexMut},
     vec::Vec,
 };
 
+use crate::{
+    common::PowerOnState,
+    core::{bus::Bus, error::EmulationError},
+};
+
 pub fn test() {
     println!("Hello from memory");
 }
 
-// TODO: Maybe a safe mode that tracks what addresses are using what. (ROM, RAM, IO, Stack)
+// The 6502's 64 KiB and the 65C816's 16 MiB address spaces
+pub const ADDRESS_SPACE: usize = 0x10000;
+pub const LONG_ADDRESS_SPACE: usize = 0x1000000;
+
 #[derive(Debug)]
 pub struct VirtualMemory {
-    buffer: [u8; 0xFFFF],
+    buffer: Vec<u8>,
 }
 
 impl VirtualMemory {
     pub fn new() -> Self {
-        let arr: [u8; 0xFFFF] = [0; 0xFFFF];
-        return VirtualMemory { buffer: arr };
+        return VirtualMemory {
+            buffer: vec![0; ADDRESS_SPACE],
+        };
+    }
+
+    /// Memory covering the 65C816's full 24 bit address space.
+    pub fn new_extended() -> Self {
+        return VirtualMemory {
+            buffer: vec![0; LONG_ADDRESS_SPACE],
+        };
     }
 
+    pub fn len(&self) -> usize {
+        return self.buffer.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.buffer.is_empty();
+    }
+
+    /// Copies rom_data in starting at a 24 bit address, it has to fit inside the buffer.
     pub fn load_rom(
         &mut self,
         rom_data: Vec<u8>,
-        starting_address: u16,
-    ) -> Result<(), MemoryError> {
-        if 0xFFFF - starting_address < rom_data.len() as u16 {
-            return Err(MemoryError::new(
-                "Not enough space to fit ROM at this memory address",
-            ));
-        }
-        let mut rom_idx = 0;
-        for i in starting_address..0xFFFF {
-            if rom_idx >= rom_data.len() {
-                break;
+        starting_address: u32,
+    ) -> Result<(), EmulationError> {
+        let start = starting_address as usize;
+        let end = match start.checked_add(rom_data.len()) {
+            Some(end) if end <= self.buffer.len() => end,
+            _ => {
+                return Err(EmulationError::LoadFailure {
+                    address: starting_address,
+                    reason: "Not enough space to fit ROM at this memory address",
+                })
             }
-            self.buffer[i as usize] = rom_data[(i - starting_address) as usize];
-            rom_idx += 1;
-        }
+        };
+        self.buffer[start..end].copy_from_slice(&rom_data);
         return Ok(());
     }
 
     pub fn reinitialize(&mut self) {
-        self.buffer = [0; 0xFFFF];
+        self.buffer.fill(0);
+    }
+
+    /// Fills the whole buffer the way RAM comes up at power on, load any ROM images afterwards.
+    pub fn power_on(&mut self, state: PowerOnState) {
+        state.fill(&mut self.buffer);
+    }
+
+    /// Reads a byte from a 24 bit address, banks past the end of the buffer mirror the ones below.
+    pub fn read_long(&self, address: u32) -> u8 {
+        return self.buffer[address as usize % self.buffer.len()];
+    }
+
+    pub fn write_long(&mut self, address: u32, data: u8) {
+        let length = self.buffer.len();
+        self.buffer[address as usize % length] = data;
     }
 
     /// Reads a word in little endian order returns 0xHHLL, where 0xLL is the low byte and 0xHH is the highbyte
@@ -54,6 +90,22 @@ impl VirtualMemory {
     }
 }
 
+impl Bus for VirtualMemory {
+    fn read(&mut self, address: u32) -> u8 {
+        return self.read_long(address);
+    }
+
+    fn write(&mut self, address: u32, data: u8) {
+        self.write_long(address, data);
+    }
+}
+
+impl Default for VirtualMemory {
+    fn default() -> Self {
+        return VirtualMemory::new();
+    }
+}
+
 impl Index<u16> for VirtualMemory {
     type Output = u8;
     fn index(&self, index: u16) -> &Self::Output {
@@ -66,26 +118,3 @@ impl IndexMut<u16> for VirtualMemory {
         return &mut self.buffer[index as usize];
     }
 }
-
-#[derive(Debug)]
-pub struct MemoryError {
-    error_msg: String, // TODO: change to an error code enum once standardized
-}
-
-impl MemoryError {
-    pub fn new(err_str: &str) -> Self {
-        return MemoryError {
-            error_msg: String::from(err_str),
-        };
-    }
-
-    pub fn get_message(&self) -> &String {
-        return &self.error_msg;
-    }
-}
-impl Display for MemoryError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "An error has occured, error: {}", self.error_msg)
-    }
-}
-impl Error for MemoryError {}
@@ -0,0 +1,289 @@
+use std::{cell::RefCell, fmt::Debug, ops::RangeInclusive, rc::Rc};
+
+use crate::{
+    common::PowerOnState,
+    core::{bus::Bus, error::EmulationError},
+};
+
+/// What happens to a write that lands in ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RomWritePolicy {
+    #[default]
+    Ignore, // The write is dropped like on real hardware
+    Trap, // The write is dropped, recorded in MemoryMap::trapped_writes and stops the CPU with a bus fault
+}
+
+enum RegionKind {
+    Ram(Vec<u8>),
+    Rom(Vec<u8>),
+    Mirror(u32), // Start of the range this one mirrors
+    Device(Rc<RefCell<dyn Bus>>),
+}
+
+impl Debug for RegionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            RegionKind::Ram(data) => write!(f, "RAM ({} bytes)", data.len()),
+            RegionKind::Rom(data) => write!(f, "ROM ({} bytes)", data.len()),
+            RegionKind::Mirror(source) => write!(f, "Mirror of ${:04X}", source),
+            RegionKind::Device(_) => write!(f, "Device"),
+        };
+    }
+}
+
+#[derive(Debug)]
+struct Region {
+    start: u32,
+    end: u32,
+    // Address lines the glue logic actually decodes inside the region when it is partially decoded, the offset from
+    // the start is masked with it. Anything narrower than the region makes the contents repeat through it.
+    mask: Option<u32>,
+    kind: RegionKind,
+}
+
+impl Region {
+    fn contains(&self, address: u32) -> bool {
+        return self.start <= address && address <= self.end;
+    }
+
+    fn offset(&self, address: u32) -> u32 {
+        return match self.mask {
+            Some(mask) => (address - self.start) & mask,
+            None => address - self.start,
+        };
+    }
+}
+
+/// Describes how a board decodes its address space, one region at a time.
+///
+/// ```
+/// # use w65xx_emulator::peripherals::memory_map::MemoryMapBuilder;
+/// let map = MemoryMapBuilder::new()
+///     .ram(0x0000..=0x3FFF)
+///     .rom(0x8000, vec![0xEA; 0x8000])
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryMapBuilder {
+    regions: Vec<Region>,
+    rom_writes: RomWritePolicy,
+}
+
+impl MemoryMapBuilder {
+    pub fn new() -> Self {
+        return MemoryMapBuilder::default();
+    }
+
+    /// Zeroed RAM over the whole range.
+    pub fn ram(self, range: RangeInclusive<u32>) -> Self {
+        // A reversed range gets no memory, build turns it down
+        let size = range
+            .end()
+            .checked_sub(*range.start())
+            .map_or(0, |last| last as usize + 1);
+        return self.region(range, RegionKind::Ram(vec![0; size]));
+    }
+
+    /// ROM holding data, starting at start and running for as long as the image is.
+    pub fn rom(self, start: u32, data: Vec<u8>) -> Self {
+        // An empty image still claims its start address so build can report where it is
+        let end = start.saturating_add((data.len() as u32).saturating_sub(1));
+        return self.region(start..=end, RegionKind::Rom(data));
+    }
+
+    /// Makes the range an alias of the one starting at source, accesses are passed on to whatever is mapped there.
+    pub fn mirror(self, range: RangeInclusive<u32>, source: u32) -> Self {
+        return self.region(range, RegionKind::Mirror(source));
+    }
+
+    /// Hands accesses in the range to a device, it sees the offset from the start of the range as the address.
+    pub fn device(self, range: RangeInclusive<u32>, device: Rc<RefCell<dyn Bus>>) -> Self {
+        return self.region(range, RegionKind::Device(device));
+    }
+
+    /// Partial address decoding for the region added last, only the address lines in mask are wired up.
+    /// A 6522 VIA only looks at A0-A3, so `.device(0x6000..=0x6FFF, via).decoded(0x000F)` repeats its 16 registers
+    /// all through $6000-$6FFF. RAM and ROM shrink to the decoded size.
+    pub fn decoded(mut self, mask: u32) -> Self {
+        if let Some(region) = self.regions.last_mut() {
+            region.mask = Some(mask);
+            match &mut region.kind {
+                RegionKind::Ram(data) | RegionKind::Rom(data) => {
+                    data.resize(mask as usize + 1, 0);
+                }
+                RegionKind::Mirror(_) | RegionKind::Device(_) => {}
+            }
+        }
+        return self;
+    }
+
+    pub fn rom_writes(mut self, policy: RomWritePolicy) -> Self {
+        self.rom_writes = policy;
+        return self;
+    }
+
+    /// Checks that every region has something in it and no two regions claim the same address.
+    pub fn build(self) -> Result<MemoryMap, EmulationError> {
+        for (index, region) in self.regions.iter().enumerate() {
+            if region.end < region.start {
+                return Err(EmulationError::LoadFailure {
+                    address: region.start,
+                    reason: "Region ends before it starts",
+                });
+            }
+            if let RegionKind::Rom(data) = &region.kind {
+                if data.is_empty() {
+                    return Err(EmulationError::LoadFailure {
+                        address: region.start,
+                        reason: "ROM image is empty",
+                    });
+                }
+            }
+            let overlap = self.regions[index + 1..]
+                .iter()
+                .find(|other| region.start <= other.end && other.start <= region.end);
+            if let Some(other) = overlap {
+                return Err(EmulationError::LoadFailure {
+                    address: other.start,
+                    reason: "Memory regions overlap",
+                });
+            }
+        }
+        return Ok(MemoryMap {
+            regions: self.regions,
+            rom_writes: self.rom_writes,
+            trapped_writes: Vec::new(),
+            fault: None,
+            open_bus: 0,
+        });
+    }
+
+    fn region(mut self, range: RangeInclusive<u32>, kind: RegionKind) -> Self {
+        let (start, end) = range.into_inner();
+        self.regions.push(Region {
+            start,
+            end,
+            mask: None,
+            kind,
+        });
+        return self;
+    }
+}
+
+/// A bus built from a MemoryMapBuilder. Addresses nothing is mapped to read back as open bus, the last value that was
+/// on the data lines, and writes to them go nowhere.
+#[derive(Debug)]
+pub struct MemoryMap {
+    regions: Vec<Region>,
+    rom_writes: RomWritePolicy,
+    trapped_writes: Vec<(u32, u8)>,
+    fault: Option<u32>, // The first trapped write the CPU hasn't been told about yet
+    open_bus: u8,
+}
+
+impl MemoryMap {
+    /// Writes to ROM that were dropped, in order, as (address, data). Only recorded with RomWritePolicy::Trap.
+    pub fn trapped_writes(&self) -> &[(u32, u8)] {
+        return &self.trapped_writes;
+    }
+
+    pub fn clear_trapped_writes(&mut self) {
+        self.trapped_writes.clear();
+    }
+
+    /// Fills the RAM regions the way they come up at power on, ROM and devices are left alone.
+    pub fn power_on(&mut self, state: PowerOnState) {
+        let mut values = state.values();
+        for region in self.regions.iter_mut() {
+            if let RegionKind::Ram(data) = &mut region.kind {
+                for (byte, value) in data.iter_mut().zip(&mut values) {
+                    *byte = value;
+                }
+            }
+        }
+    }
+
+    // Finds the region that ends up handling the address, following a mirror once. A mirror bigger than what it
+    // mirrors repeats it, the source is only wired up to the address lines it needs.
+    fn resolve(&self, address: u32) -> Option<(usize, u32)> {
+        let index = self
+            .regions
+            .iter()
+            .position(|region| region.contains(address))?;
+        let region = &self.regions[index];
+        if let RegionKind::Mirror(source) = region.kind {
+            let target_index = self
+                .regions
+                .iter()
+                .position(|region| region.contains(source))?;
+            let size = self.regions[target_index].end - source + 1;
+            let target = source + region.offset(address) % size;
+            if let RegionKind::Mirror(_) = self.regions[target_index].kind {
+                return None;
+            }
+            return Some((target_index, target));
+        }
+        return Some((index, address));
+    }
+}
+
+impl Bus for MemoryMap {
+    fn read(&mut self, address: u32) -> u8 {
+        let data = match self.resolve(address) {
+            Some((index, address)) => {
+                let region = &self.regions[index];
+                let offset = region.offset(address);
+                match &region.kind {
+                    RegionKind::Ram(data) | RegionKind::Rom(data) => data[offset as usize],
+                    RegionKind::Device(device) => device.borrow_mut().read(offset),
+                    RegionKind::Mirror(_) => self.open_bus,
+                }
+            }
+            None => self.open_bus,
+        };
+        self.open_bus = data;
+        return data;
+    }
+
+    // Devices and mirrors of them aren't read, they give the open bus value like unmapped addresses
+    fn peek(&mut self, address: u32) -> u8 {
+        return match self.resolve(address) {
+            Some((index, address)) => {
+                let region = &self.regions[index];
+                match &region.kind {
+                    RegionKind::Ram(data) | RegionKind::Rom(data) => {
+                        data[region.offset(address) as usize]
+                    }
+                    RegionKind::Device(_) | RegionKind::Mirror(_) => self.open_bus,
+                }
+            }
+            None => self.open_bus,
+        };
+    }
+
+    fn write(&mut self, address: u32, data: u8) {
+        self.open_bus = data;
+        let (index, address) = match self.resolve(address) {
+            Some(resolved) => resolved,
+            None => return,
+        };
+        let region = &mut self.regions[index];
+        let offset = region.offset(address);
+        match &mut region.kind {
+            RegionKind::Ram(memory) => memory[offset as usize] = data,
+            RegionKind::Rom(_) => {
+                if self.rom_writes == RomWritePolicy::Trap {
+                    self.trapped_writes.push((address, data));
+                    self.fault.get_or_insert(address);
+                }
+            }
+            RegionKind::Device(device) => device.borrow_mut().write(offset, data),
+            RegionKind::Mirror(_) => {}
+        }
+    }
+
+    fn take_fault(&mut self) -> Option<u32> {
+        return self.fault.take();
+    }
+}
@@ -1 +1,3 @@
+pub mod bank_switch;
 pub mod memory;
+pub mod memory_map;
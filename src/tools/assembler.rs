@@ -0,0 +1,757 @@
+use std::{collections::BTreeMap, error::Error, fmt::Display};
+
+use strum::IntoEnumIterator;
+
+use crate::core::{
+    instructions::{
+        decode::{decode, Mnemonic, OpcodeMetadata, OPCODE_TABLE},
+        utils::AddressingModes,
+    },
+    variant::CpuVariant,
+};
+
+/// A program assembled from source, image is loaded at origin with `load_rom`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    pub origin: u16,
+    pub image: Vec<u8>, // Everything from the lowest to the highest address written, gaps are zero
+    pub symbols: BTreeMap<String, i64>, // Labels and constants
+}
+
+/// Why the source didn't assemble, line counts from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+
+impl Error for AssemblyError {}
+
+// The shape of an operand, the expressions in it are still text
+#[derive(Debug, Clone, Copy)]
+enum Operand<'a> {
+    None,
+    Accumulator,
+    Immediate(&'a str),
+    Direct(&'a str),
+    XIndex(&'a str),
+    YIndex(&'a str),
+    StackRelative(&'a str),
+    Pair(&'a str, &'a str),
+    Indirect(&'a str),
+    IndirectX(&'a str),
+    IndirectY(&'a str),
+    StackIndirectY(&'a str),
+    IndirectLong(&'a str),
+    IndirectLongY(&'a str),
+}
+
+impl<'a> Operand<'a> {
+    fn parse(text: &'a str) -> Operand<'a> {
+        let text = text.trim();
+        let upper = text.to_ascii_uppercase();
+        if text.is_empty() {
+            return Operand::None;
+        }
+        if upper == "A" {
+            return Operand::Accumulator;
+        }
+        if let Some(value) = text.strip_prefix('#') {
+            return Operand::Immediate(value.trim());
+        }
+        if text.starts_with('[') {
+            if let Some(inner) = text.strip_suffix(']') {
+                return Operand::IndirectLong(inner[1..].trim());
+            }
+            if upper.ends_with("],Y") {
+                return Operand::IndirectLongY(text[1..text.len() - 3].trim());
+            }
+        }
+        if text.starts_with('(') {
+            if upper.ends_with(",S),Y") {
+                return Operand::StackIndirectY(text[1..text.len() - 5].trim());
+            }
+            if upper.ends_with("),Y") {
+                return Operand::IndirectY(text[1..text.len() - 3].trim());
+            }
+            if upper.ends_with(",X)") {
+                return Operand::IndirectX(text[1..text.len() - 3].trim());
+            }
+            if let Some(inner) = text.strip_suffix(')') {
+                return Operand::Indirect(inner[1..].trim());
+            }
+        }
+        let parts = split_list(text);
+        if parts.len() == 2 {
+            return match parts[1].to_ascii_uppercase().as_str() {
+                "X" => Operand::XIndex(parts[0]),
+                "Y" => Operand::YIndex(parts[0]),
+                "S" => Operand::StackRelative(parts[0]),
+                _ => Operand::Pair(parts[0], parts[1]),
+            };
+        }
+        return Operand::Direct(text);
+    }
+
+    // The modes the operand could be written in, narrowest address first
+    fn candidates(&self) -> &'static [AddressingModes] {
+        return match self {
+            Operand::None => &[AddressingModes::Implied, AddressingModes::Accumulator],
+            Operand::Accumulator => &[AddressingModes::Accumulator],
+            Operand::Immediate(_) => &[AddressingModes::Immediate],
+            Operand::Direct(_) => &[
+                AddressingModes::Relative,
+                AddressingModes::RelativeLong,
+                AddressingModes::ZeroPage,
+                AddressingModes::Absolute,
+                AddressingModes::AbsoluteLong,
+            ],
+            Operand::XIndex(_) => &[
+                AddressingModes::ZeroPageXIndex,
+                AddressingModes::AbsoluteXIndex,
+                AddressingModes::AbsoluteLongXIndex,
+            ],
+            Operand::YIndex(_) => &[
+                AddressingModes::ZeroPageYIndex,
+                AddressingModes::AbsoluteYIndex,
+            ],
+            Operand::StackRelative(_) => &[AddressingModes::StackRelative],
+            Operand::Pair(_, _) => &[
+                AddressingModes::ZeroPageRelative,
+                AddressingModes::BlockMove,
+            ],
+            Operand::Indirect(_) => &[AddressingModes::ZeroPageIndirect, AddressingModes::Indirect],
+            Operand::IndirectX(_) => &[
+                AddressingModes::PreIndexIndirect,
+                AddressingModes::AbsoluteIndexIndirect,
+            ],
+            Operand::IndirectY(_) => &[AddressingModes::PostIndexIndirect],
+            Operand::StackIndirectY(_) => &[AddressingModes::StackRelativeIndirectYIndex],
+            Operand::IndirectLong(_) => &[
+                AddressingModes::ZeroPageIndirectLong,
+                AddressingModes::AbsoluteIndirectLong,
+            ],
+            Operand::IndirectLongY(_) => &[AddressingModes::ZeroPageIndirectLongYIndex],
+        };
+    }
+
+    // The expression that picks between a zero page and an absolute address
+    fn address(&self) -> Option<&'a str> {
+        return match *self {
+            Operand::Direct(text)
+            | Operand::XIndex(text)
+            | Operand::YIndex(text)
+            | Operand::Indirect(text)
+            | Operand::IndirectX(text)
+            | Operand::IndirectLong(text) => Some(text),
+            _ => None,
+        };
+    }
+}
+
+// Whether an address operand can be written in mode, the other modes take any operand
+fn fits(value: i64, mode: AddressingModes) -> bool {
+    return match mode {
+        AddressingModes::ZeroPage
+        | AddressingModes::ZeroPageXIndex
+        | AddressingModes::ZeroPageYIndex
+        | AddressingModes::ZeroPageIndirect
+        | AddressingModes::PreIndexIndirect
+        | AddressingModes::ZeroPageIndirectLong => (0..=0xFF).contains(&value),
+        AddressingModes::Absolute
+        | AddressingModes::AbsoluteXIndex
+        | AddressingModes::AbsoluteYIndex
+        | AddressingModes::Indirect
+        | AddressingModes::AbsoluteIndexIndirect
+        | AddressingModes::AbsoluteIndirectLong => (0..=0xFFFF).contains(&value),
+        AddressingModes::AbsoluteLong | AddressingModes::AbsoluteLongXIndex => {
+            (0..=0xFFFFFF).contains(&value)
+        }
+        _ => true,
+    };
+}
+
+// Splits a comma separated list, commas inside quotes don't count
+fn split_list(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (index, character) in text.char_indices() {
+        match (quote, character) {
+            (None, '"') | (None, '\'') => quote = Some(character),
+            (Some(open), _) if open == character => quote = None,
+            (None, ',') => {
+                parts.push(text[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    return parts;
+}
+
+// The source line without its comment
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    for (index, character) in text.char_indices() {
+        match (quote, character) {
+            (None, '"') | (None, '\'') => quote = Some(character),
+            (Some(open), _) if open == character => quote = None,
+            (None, ';') => return &text[..index],
+            _ => {}
+        }
+    }
+    return text;
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut characters = text.chars();
+    return match characters.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+            characters.all(|character| character.is_ascii_alphanumeric() || character == '_')
+        }
+        _ => false,
+    };
+}
+
+// RMB, SMB, BBR and BBS carry the bit they work on in the mnemonic, RMB3 or BBS7
+fn parse_mnemonic(text: &str) -> Option<(Mnemonic, Option<u8>)> {
+    let upper = text.to_ascii_uppercase();
+    if let Some(mnemonic) = Mnemonic::iter().find(|mnemonic| mnemonic.to_string() == upper) {
+        return Some((mnemonic, None));
+    }
+    // Split off the last character rather than byte, the word can be anything the source has in it
+    let (index, _) = upper.char_indices().last()?;
+    let (name, bit) = upper.split_at(index);
+    let bit = bit.parse::<u8>().ok().filter(|bit| *bit < 8)?;
+    return match name {
+        "RMB" => Some((Mnemonic::RMB, Some(bit))),
+        "SMB" => Some((Mnemonic::SMB, Some(bit))),
+        "BBR" => Some((Mnemonic::BBR, Some(bit))),
+        "BBS" => Some((Mnemonic::BBS, Some(bit))),
+        _ => None,
+    };
+}
+
+// The documented opcode for the instruction if there is one, NOP and SBC have undocumented duplicates.
+// Option::is_none_or would need Rust 1.82.
+#[allow(clippy::unnecessary_map_or)]
+fn find_opcode(
+    mnemonic: Mnemonic,
+    bit: Option<u8>,
+    mode: AddressingModes,
+    variant: CpuVariant,
+) -> Option<&'static OpcodeMetadata> {
+    return OPCODE_TABLE
+        .iter()
+        .filter(|metadata| {
+            metadata.mnemonic == mnemonic
+                && metadata.addressing_mode == mode
+                && metadata.variants.contains(&variant)
+                && bit.map_or(true, |bit| (metadata.opcode >> 4) & 0x07 == bit)
+        })
+        .min_by_key(|metadata| metadata.undocumented);
+}
+
+struct Assembler {
+    variant: CpuVariant,
+    symbols: BTreeMap<String, i64>,
+    modes: BTreeMap<usize, AddressingModes>, // Picked for each instruction line in the first pass
+    output: BTreeMap<u32, u8>,
+    pc: u32,
+    start: u32, // Where the statement on the line starts, for *
+    line: usize,
+    final_pass: bool,
+    // The 65C816 register widths set by .a16 and .i16, they decide how long immediate operands are
+    accumulator_wide: bool,
+    index_wide: bool,
+}
+
+impl Assembler {
+    fn error(&self, message: String) -> AssemblyError {
+        return AssemblyError {
+            line: self.line,
+            message,
+        };
+    }
+
+    // The value of an expression, None in the first pass while it refers to a label further on
+    fn evaluate(&self, text: &str) -> Result<Option<i64>, AssemblyError> {
+        let text = text.trim();
+        // Low and high byte take the whole expression after them, #<message+1
+        if let Some(rest) = text.strip_prefix('<') {
+            return Ok(self.evaluate(rest)?.map(|value| value & 0xFF));
+        }
+        if let Some(rest) = text.strip_prefix('>') {
+            return Ok(self.evaluate(rest)?.map(|value| (value >> 8) & 0xFF));
+        }
+        let mut expression = Expression {
+            assembler: self,
+            text: text.as_bytes(),
+            position: 0,
+        };
+        let value = expression.sum()?;
+        expression.skip_space();
+        if expression.position != text.len() {
+            return Err(self.error(format!("can't make sense of {}", text)));
+        }
+        return Ok(value);
+    }
+
+    // An expression that has to be known by now, the first pass needs these to know where things go
+    fn resolve(&self, text: &str) -> Result<i64, AssemblyError> {
+        return match self.evaluate(text)? {
+            Some(value) => Ok(value),
+            None => Err(self.error(format!("{} can't refer to a later label", text))),
+        };
+    }
+
+    fn emit(&mut self, byte: u8) -> Result<(), AssemblyError> {
+        if self.pc > 0xFFFF {
+            return Err(self.error(String::from("program runs past $FFFF")));
+        }
+        if self.final_pass && self.output.insert(self.pc, byte).is_some() {
+            return Err(self.error(format!("overwrites ${:04X}", self.pc)));
+        }
+        self.pc += 1;
+        return Ok(());
+    }
+
+    // Writes the value little endian in bytes, in range for either a signed or an unsigned number that wide
+    fn emit_value(&mut self, text: &str, bytes: u32) -> Result<(), AssemblyError> {
+        let value = match self.final_pass {
+            true => self.resolve(text)?,
+            false => 0,
+        };
+        let limit = 1i64 << (8 * bytes);
+        if value < -(limit / 2) || value >= limit {
+            return Err(self.error(format!("{} doesn't fit in {} bytes", text, bytes)));
+        }
+        for index in 0..bytes {
+            self.emit((value >> (8 * index)) as u8)?;
+        }
+        return Ok(());
+    }
+
+    // Writes the distance to a branch target from the end of the instruction
+    fn emit_offset(&mut self, text: &str, end: u32, bytes: u32) -> Result<(), AssemblyError> {
+        let offset = match self.final_pass {
+            true => match self.resolve(text)?.checked_sub(end as i64) {
+                Some(offset) => offset,
+                None => return Err(self.error(format!("branch to {} is out of range", text))),
+            },
+            false => 0,
+        };
+        let limit = 1i64 << (8 * bytes - 1);
+        if offset < -limit || offset >= limit {
+            return Err(self.error(format!("branch to {} is out of range", text)));
+        }
+        for index in 0..bytes {
+            self.emit((offset >> (8 * index)) as u8)?;
+        }
+        return Ok(());
+    }
+
+    fn define(&mut self, name: &str, value: i64) -> Result<(), AssemblyError> {
+        if !is_identifier(name) || parse_mnemonic(name).is_some() {
+            return Err(self.error(format!("{} can't be a label", name)));
+        }
+        // The second pass meets the same definitions again
+        if self.final_pass {
+            return Ok(());
+        }
+        if self.symbols.insert(name.to_string(), value).is_some() {
+            return Err(self.error(format!("{} is defined twice", name)));
+        }
+        return Ok(());
+    }
+
+    fn line(&mut self, text: &str) -> Result<(), AssemblyError> {
+        let mut text = strip_comment(text).trim();
+        self.start = self.pc;
+        if let Some((name, value)) = text.split_once('=') {
+            if is_identifier(name.trim()) {
+                let value = self.resolve(value)?;
+                return self.define(name.trim(), value);
+            }
+        }
+        if let Some((label, rest)) = text.split_once(':') {
+            if is_identifier(label.trim()) {
+                self.define(label.trim(), self.pc as i64)?;
+                text = rest.trim();
+            }
+        }
+        if text.is_empty() {
+            return Ok(());
+        }
+        let (word, operand) = match text.split_once(char::is_whitespace) {
+            Some((word, operand)) => (word, operand.trim()),
+            None => (text, ""),
+        };
+        if word.starts_with('.') {
+            return self.directive(word, operand);
+        }
+        return self.instruction(word, operand);
+    }
+
+    fn directive(&mut self, word: &str, operand: &str) -> Result<(), AssemblyError> {
+        match word.to_ascii_lowercase().as_str() {
+            ".org" => {
+                let address = self.resolve(operand)?;
+                if !(0..=0xFFFF).contains(&address) {
+                    return Err(self.error(format!("${:X} is past $FFFF", address)));
+                }
+                self.pc = address as u32;
+            }
+            ".byte" => {
+                for item in split_list(operand) {
+                    match item
+                        .strip_prefix('"')
+                        .and_then(|item| item.strip_suffix('"'))
+                    {
+                        Some(string) => {
+                            for byte in string.bytes() {
+                                self.emit(byte)?;
+                            }
+                        }
+                        None => self.emit_value(item, 1)?,
+                    }
+                }
+            }
+            ".word" => {
+                for item in split_list(operand) {
+                    self.emit_value(item, 2)?;
+                }
+            }
+            ".res" => {
+                let parts = split_list(operand);
+                let count = self.resolve(parts[0])?;
+                if !(0..=0x10000).contains(&count) {
+                    return Err(self.error(format!("can't reserve {} bytes", count)));
+                }
+                let fill = match (self.final_pass, parts.get(1)) {
+                    (true, Some(fill)) => self.resolve(fill)?,
+                    _ => 0,
+                };
+                if !(-0x80..=0xFF).contains(&fill) {
+                    return Err(self.error(format!("{} doesn't fit in a byte", fill)));
+                }
+                for _ in 0..count {
+                    self.emit(fill as u8)?;
+                }
+            }
+            ".a8" | ".a16" | ".i8" | ".i16" => {
+                if !self.variant.has_native_mode() {
+                    return Err(self.error(format!(
+                        "{} is for the 65C816's 16 bit registers, the {:?} doesn't have them",
+                        word, self.variant
+                    )));
+                }
+                match word.to_ascii_lowercase().as_str() {
+                    ".a8" => self.accumulator_wide = false,
+                    ".a16" => self.accumulator_wide = true,
+                    ".i8" => self.index_wide = false,
+                    _ => self.index_wide = true,
+                }
+            }
+            _ => return Err(self.error(format!("unknown directive {}", word))),
+        }
+        return Ok(());
+    }
+
+    // The first pass picks the mode, zero page when the address is known and small enough, and sticks to it
+    fn pick_mode(
+        &self,
+        mnemonic: Mnemonic,
+        bit: Option<u8>,
+        operand: &Operand,
+    ) -> Result<AddressingModes, AssemblyError> {
+        let available: Vec<AddressingModes> = operand
+            .candidates()
+            .iter()
+            .copied()
+            .filter(|mode| find_opcode(mnemonic, bit, *mode, self.variant).is_some())
+            .collect();
+        let value = match operand.address() {
+            Some(text) => self.evaluate(text)?,
+            None => None,
+        };
+        let mode = match value {
+            Some(value) => available.iter().find(|mode| fits(value, **mode)),
+            // Later labels get an absolute address unless zero page is all there is
+            None => available
+                .iter()
+                .find(|mode| fits(0x100, **mode))
+                .or(available.first()),
+        };
+        return match mode {
+            Some(mode) => Ok(*mode),
+            None if available.is_empty() => Err(self.error(format!(
+                "{} can't take that operand on the {:?}",
+                mnemonic, self.variant
+            ))),
+            None => Err(self.error(format!("{} operand is out of range", mnemonic))),
+        };
+    }
+
+    fn instruction(&mut self, word: &str, operand: &str) -> Result<(), AssemblyError> {
+        let (mnemonic, bit) = match parse_mnemonic(word) {
+            Some(parsed) => parsed,
+            None => return Err(self.error(format!("unknown instruction {}", word))),
+        };
+        if bit.is_none()
+            && matches!(
+                mnemonic,
+                Mnemonic::RMB | Mnemonic::SMB | Mnemonic::BBR | Mnemonic::BBS
+            )
+        {
+            return Err(self.error(format!(
+                "{} needs the bit number, {}0 to {}7",
+                mnemonic, mnemonic, mnemonic
+            )));
+        }
+        let operand = Operand::parse(operand);
+        let mode = match self.modes.get(&self.line) {
+            Some(mode) if self.final_pass => *mode,
+            _ => self.pick_mode(mnemonic, bit, &operand)?,
+        };
+        self.modes.insert(self.line, mode);
+        let metadata = find_opcode(mnemonic, bit, mode, self.variant).unwrap();
+        // An immediate is a word when the register the instruction works on is 16 bits wide
+        let operand_mode = match decode(metadata.opcode, self.variant) {
+            Some(mut instruction) => {
+                instruction.widen_immediate(self.accumulator_wide, self.index_wide);
+                instruction.addressing_mode
+            }
+            None => mode,
+        };
+        let end = self.pc + 1 + operand_mode.parameter_bytes() as u32;
+        self.emit(metadata.opcode)?;
+        return match (mode, operand) {
+            (AddressingModes::Implied, _) | (AddressingModes::Accumulator, _) => Ok(()),
+            (AddressingModes::Relative, Operand::Direct(target)) => {
+                self.emit_offset(target, end, 1)
+            }
+            (AddressingModes::RelativeLong, Operand::Direct(target)) => {
+                self.emit_offset(target, end, 2)
+            }
+            (AddressingModes::ZeroPageRelative, Operand::Pair(address, target)) => {
+                self.emit_value(address, 1)?;
+                self.emit_offset(target, end, 1)
+            }
+            // Written source bank first, the operand bytes are the destination bank first
+            (AddressingModes::BlockMove, Operand::Pair(source, destination)) => {
+                self.emit_value(destination, 1)?;
+                self.emit_value(source, 1)
+            }
+            (
+                _,
+                Operand::Immediate(text)
+                | Operand::Direct(text)
+                | Operand::XIndex(text)
+                | Operand::YIndex(text)
+                | Operand::StackRelative(text)
+                | Operand::Indirect(text)
+                | Operand::IndirectX(text)
+                | Operand::IndirectY(text)
+                | Operand::StackIndirectY(text)
+                | Operand::IndirectLong(text)
+                | Operand::IndirectLongY(text),
+            ) => self.emit_value(text, operand_mode.parameter_bytes() as u32),
+            _ => Err(self.error(format!("{} can't take that operand", mnemonic))),
+        };
+    }
+}
+
+// Numbers, symbols and * for the address of the line, with + - * / and unary minus. No parentheses, those are
+// the indirect addressing modes
+struct Expression<'a> {
+    assembler: &'a Assembler,
+    text: &'a [u8],
+    position: usize,
+}
+
+impl Expression<'_> {
+    fn skip_space(&mut self) {
+        while self.text.get(self.position) == Some(&b' ')
+            || self.text.get(self.position) == Some(&b'\t')
+        {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_space();
+        return self.text.get(self.position).copied();
+    }
+
+    // The result of an operator, None when it overflowed
+    fn checked(&self, result: Option<i64>) -> Result<i64, AssemblyError> {
+        return match result {
+            Some(value) => Ok(value),
+            None => Err(self.assembler.error(format!(
+                "{} overflows",
+                std::str::from_utf8(self.text).unwrap()
+            ))),
+        };
+    }
+
+    fn sum(&mut self) -> Result<Option<i64>, AssemblyError> {
+        let mut value = self.product()?;
+        while let Some(operator @ (b'+' | b'-')) = self.peek() {
+            self.position += 1;
+            let right = self.product()?;
+            value = match (value, right) {
+                (Some(left), Some(right)) if operator == b'+' => {
+                    Some(self.checked(left.checked_add(right))?)
+                }
+                (Some(left), Some(right)) => Some(self.checked(left.checked_sub(right))?),
+                _ => None,
+            };
+        }
+        return Ok(value);
+    }
+
+    fn product(&mut self) -> Result<Option<i64>, AssemblyError> {
+        let mut value = self.unary()?;
+        while let Some(operator @ (b'*' | b'/')) = self.peek() {
+            self.position += 1;
+            let right = self.unary()?;
+            value = match (value, right) {
+                (Some(_), Some(0)) if operator == b'/' => {
+                    return Err(self.assembler.error(String::from("division by zero")))
+                }
+                (Some(left), Some(right)) if operator == b'*' => {
+                    Some(self.checked(left.checked_mul(right))?)
+                }
+                (Some(left), Some(right)) => Some(self.checked(left.checked_div(right))?),
+                _ => None,
+            };
+        }
+        return Ok(value);
+    }
+
+    fn unary(&mut self) -> Result<Option<i64>, AssemblyError> {
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+            return match self.unary()? {
+                Some(value) => Ok(Some(self.checked(value.checked_neg())?)),
+                None => Ok(None),
+            };
+        }
+        return self.term();
+    }
+
+    fn term(&mut self) -> Result<Option<i64>, AssemblyError> {
+        let start = self.position;
+        let radix = match self.peek() {
+            Some(b'*') => {
+                self.position += 1;
+                return Ok(Some(self.assembler.start as i64));
+            }
+            Some(b'\'') if self.text.get(self.position + 2) == Some(&b'\'') => {
+                self.position += 3;
+                return Ok(Some(self.text[self.position - 2] as i64));
+            }
+            Some(b'$') => 16,
+            Some(b'%') => 2,
+            Some(character) if character.is_ascii_digit() => 10,
+            _ => 0,
+        };
+        if radix != 10 && radix != 0 {
+            self.position += 1;
+        }
+        let digits = self.position;
+        while self
+            .text
+            .get(self.position)
+            .is_some_and(|character| character.is_ascii_alphanumeric() || *character == b'_')
+        {
+            self.position += 1;
+        }
+        let word = std::str::from_utf8(&self.text[digits..self.position]).unwrap();
+        if radix != 0 {
+            return match i64::from_str_radix(word, radix) {
+                Ok(value) if value <= 0xFFFFFF => Ok(Some(value)),
+                _ => Err(self.assembler.error(format!(
+                    "bad number {}",
+                    std::str::from_utf8(&self.text[start..self.position]).unwrap()
+                ))),
+            };
+        }
+        if !is_identifier(word) {
+            return Err(self.assembler.error(format!(
+                "expected a number or a label in {}",
+                std::str::from_utf8(self.text).unwrap()
+            )));
+        }
+        return match self.assembler.symbols.get(word) {
+            Some(value) => Ok(Some(*value)),
+            None if self.assembler.final_pass => {
+                Err(self.assembler.error(format!("{} isn't defined", word)))
+            }
+            None => Ok(None),
+        };
+    }
+}
+
+/// Assembles source for the variant in two passes, the first finds where every label is and the second writes the
+/// bytes. One statement a line with an optional `label:` before it and `;` comments after, or `name = value`.
+///
+/// Instructions are written the way the disassembler prints them, `LDA ($20),Y`, `BBR0 $12,target` or
+/// `MVN $01,$02`. Addresses known by then get the zero page mode where there is one, later labels get absolute.
+/// Immediate operands are a byte unless the 65C816's registers have been made 16 bits wide.
+///
+/// Directives are `.org address`, `.byte` and `.word` lists (`.byte` takes "strings" too) and `.res count[, fill]`.
+/// On the 65C816 `.a16` and `.i16` say the accumulator or the index registers are 16 bits wide from there on, the
+/// way REP would leave them, which makes their immediates a word. `.a8` and `.i8` go back to bytes. The assembler
+/// doesn't follow REP and SEP itself.
+/// Expressions are `$hex`, `%binary`, decimal and `'c'` numbers, labels, `*` for the address of the line, `+ - * /`
+/// and a `<` or `>` in front for the low or high byte of the rest.
+pub fn assemble(source: &str, variant: CpuVariant) -> Result<Assembly, AssemblyError> {
+    let mut assembler = Assembler {
+        variant,
+        symbols: BTreeMap::new(),
+        modes: BTreeMap::new(),
+        output: BTreeMap::new(),
+        pc: 0,
+        start: 0,
+        line: 0,
+        final_pass: false,
+        accumulator_wide: false,
+        index_wide: false,
+    };
+    for final_pass in [false, true] {
+        assembler.final_pass = final_pass;
+        assembler.pc = 0;
+        assembler.accumulator_wide = false;
+        assembler.index_wide = false;
+        for (index, text) in source.lines().enumerate() {
+            assembler.line = index + 1;
+            assembler.line(text)?;
+        }
+    }
+    let (origin, end) = match (
+        assembler.output.keys().next(),
+        assembler.output.keys().next_back(),
+    ) {
+        (Some(&first), Some(&last)) => (first, last + 1),
+        _ => (0, 0),
+    };
+    let mut image = vec![0; (end - origin) as usize];
+    for (address, byte) in assembler.output {
+        image[(address - origin) as usize] = byte;
+    }
+    return Ok(Assembly {
+        origin: origin as u16,
+        image,
+        symbols: assembler.symbols,
+    });
+}
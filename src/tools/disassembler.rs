@@ -0,0 +1,241 @@
+use std::{fmt::Display, ops::RangeInclusive};
+
+use crate::core::{
+    bus::Bus,
+    cpu::CPU,
+    instructions::{
+        decode::{decode, Instruction, Mnemonic},
+        utils::AddressingModes,
+    },
+    register::{INDEX_SELECT_MASK, MEMORY_SELECT_MASK},
+    variant::CpuVariant,
+};
+
+/// What decoding depends on besides the opcode, the variant and on a 65C816 how wide the registers are. A variant on
+/// its own stands for 8 bit registers, like a 65C816 in emulation mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeContext {
+    pub variant: CpuVariant,
+    pub native: bool,           // 65C816 native mode, REP and SEP change the widths
+    pub accumulator_wide: bool, // M is clear, the accumulator's immediates are a word
+    pub index_wide: bool,       // X is clear, the index registers' immediates are a word
+}
+
+impl DecodeContext {
+    /// Decodes the way the CPU would run the next instruction, with its variant and register widths.
+    pub fn of<B: Bus>(cpu: &CPU<B>) -> Self {
+        return DecodeContext {
+            variant: cpu.variant,
+            native: cpu.is_native(),
+            accumulator_wide: cpu.accumulator_is_wide(),
+            index_wide: cpu.index_is_wide(),
+        };
+    }
+
+    // Follows REP and SEP, the widths they set hold for the instructions after them
+    fn follow(&mut self, line: &Disassembly) {
+        let instruction = match &line.instruction {
+            Some(instruction) if self.native => instruction,
+            _ => return,
+        };
+        let mask = line.operand() as u8;
+        let wide = match instruction.mnemonic {
+            Mnemonic::REP => true,
+            Mnemonic::SEP => false,
+            _ => return,
+        };
+        if mask & MEMORY_SELECT_MASK != 0 {
+            self.accumulator_wide = wide;
+        }
+        if mask & INDEX_SELECT_MASK != 0 {
+            self.index_wide = wide;
+        }
+    }
+}
+
+impl From<CpuVariant> for DecodeContext {
+    fn from(variant: CpuVariant) -> Self {
+        return DecodeContext {
+            variant,
+            native: false,
+            accumulator_wide: false,
+            index_wide: false,
+        };
+    }
+}
+
+/// One instruction read back from memory. Opcodes the variant doesn't assign come out as a single `.byte`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub address: u32,
+    pub instruction: Option<Instruction>,
+    pub bytes: Vec<u8>, // Opcode and operand as they are in memory
+}
+
+impl Disassembly {
+    /// Length in bytes, the next instruction starts at address + size.
+    pub fn size(&self) -> u16 {
+        return self.bytes.len() as u16;
+    }
+
+    /// A line of a listing, the address and bytes followed by the instruction: `$8000  B1 20     LDA ($20),Y`
+    pub fn listing(&self) -> String {
+        let bytes: Vec<String> = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        return format!(
+            "{}  {:<11} {}",
+            address(self.address),
+            bytes.join(" "),
+            self
+        );
+    }
+
+    // The operand as a little endian number, without the opcode
+    fn operand(&self) -> u32 {
+        return self.bytes[1..]
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| (value << 8) | byte as u32);
+    }
+
+    // Where a branch goes, relative to the next instruction and in the same bank
+    fn branch_target(&self, offset: i32) -> u32 {
+        let next = self.address as u16 as i32 + self.size() as i32;
+        return (self.address & 0xFF0000) | (next + offset) as u16 as u32;
+    }
+
+    fn operand_text(&self, instruction: &Instruction) -> String {
+        let operand = self.operand();
+        let byte = format!("${:02X}", operand & 0xFF);
+        let word = format!("${:04X}", operand & 0xFFFF);
+        return match instruction.addressing_mode {
+            AddressingModes::Implied => String::new(),
+            AddressingModes::Accumulator => String::from("A"),
+            AddressingModes::Immediate => format!("#{}", byte),
+            AddressingModes::ImmediateWord => format!("#{}", word),
+            AddressingModes::ZeroPage => byte,
+            AddressingModes::ZeroPageXIndex => format!("{},X", byte),
+            AddressingModes::ZeroPageYIndex => format!("{},Y", byte),
+            AddressingModes::ZeroPageIndirect => format!("({})", byte),
+            AddressingModes::PreIndexIndirect => format!("({},X)", byte),
+            AddressingModes::PostIndexIndirect => format!("({}),Y", byte),
+            AddressingModes::Absolute => word,
+            AddressingModes::AbsoluteXIndex => format!("{},X", word),
+            AddressingModes::AbsoluteYIndex => format!("{},Y", word),
+            AddressingModes::Indirect => format!("({})", word),
+            AddressingModes::AbsoluteIndexIndirect => format!("({},X)", word),
+            AddressingModes::Relative => address(self.branch_target(operand as u8 as i8 as i32)),
+            AddressingModes::RelativeLong => {
+                address(self.branch_target(operand as u16 as i16 as i32))
+            }
+            AddressingModes::ZeroPageRelative => format!(
+                "{},{}",
+                byte,
+                address(self.branch_target((operand >> 8) as u8 as i8 as i32))
+            ),
+            AddressingModes::AbsoluteLong => format!("${:06X}", operand),
+            AddressingModes::AbsoluteLongXIndex => format!("${:06X},X", operand),
+            AddressingModes::AbsoluteIndirectLong => format!("[{}]", word),
+            AddressingModes::ZeroPageIndirectLong => format!("[{}]", byte),
+            AddressingModes::ZeroPageIndirectLongYIndex => format!("[{}],Y", byte),
+            AddressingModes::StackRelative => format!("{},S", byte),
+            AddressingModes::StackRelativeIndirectYIndex => format!("({},S),Y", byte),
+            // The operand bytes are the destination bank then the source, the assembler syntax is the other way round
+            AddressingModes::BlockMove => format!("${:02X},{}", operand >> 8, byte),
+        };
+    }
+}
+
+impl Display for Disassembly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let instruction = match &self.instruction {
+            Some(instruction) => instruction,
+            None => return write!(f, ".byte ${:02X}", self.bytes[0]),
+        };
+        let mnemonic = match instruction.mnemonic {
+            // The bit number is part of the mnemonic, RMB3 or BBS7
+            Mnemonic::RMB | Mnemonic::SMB | Mnemonic::BBR | Mnemonic::BBS => {
+                format!("{}{}", instruction.mnemonic, instruction.bit_index())
+            }
+            mnemonic => mnemonic.to_string(),
+        };
+        let operand = self.operand_text(instruction);
+        if operand.is_empty() {
+            return write!(f, "{}", mnemonic);
+        }
+        return write!(f, "{} {}", mnemonic, operand);
+    }
+}
+
+// Addresses past the first bank only come up on the 65C816
+fn address(address: u32) -> String {
+    if address > 0xFFFF {
+        return format!("${:06X}", address);
+    }
+    return format!("${:04X}", address);
+}
+
+/// Decodes the instruction at address as the default variant with 8 bit registers, returning it with its length in
+/// bytes. The NMOS 6502 assigns every opcode so there always is one. disassemble_with takes the variant and register
+/// widths and gives the text of the instruction.
+pub fn disassemble<B: Bus>(bus: &mut B, address: u32) -> (Instruction, u16) {
+    let line = disassemble_with(bus, address, CpuVariant::default());
+    let len = line.size();
+    let instruction = line
+        .instruction
+        .expect("the NMOS 6502 assigns every opcode");
+    return (instruction, len);
+}
+
+/// Decodes the instruction at address the way the variant would run it, pass `DecodeContext::of(&cpu)` to size a
+/// 65C816's immediate operands by its register widths. The bus is only peeked at, devices don't see any reads.
+pub fn disassemble_with<B: Bus>(
+    bus: &mut B,
+    address: u32,
+    context: impl Into<DecodeContext>,
+) -> Disassembly {
+    let context = context.into();
+    let opcode = bus.peek(address);
+    let mut instruction = decode(opcode, context.variant);
+    if let Some(instruction) = &mut instruction {
+        instruction.widen_immediate(context.accumulator_wide, context.index_wide);
+    }
+    let len = match &instruction {
+        Some(instruction) => instruction.size(),
+        None => 1,
+    };
+    let mut bytes = vec![opcode];
+    for offset in 1..len as u32 {
+        // Operands wrap around inside the bank like the program counter does
+        let operand_address =
+            (address & 0xFF0000) | (address as u16).wrapping_add(offset as u16) as u32;
+        bytes.push(bus.peek(operand_address));
+    }
+    return Disassembly {
+        address,
+        instruction,
+        bytes,
+    };
+}
+
+/// Disassembles every instruction that starts inside range, one after the other from its start. In native mode the
+/// register widths follow the REP and SEP instructions along the way.
+pub fn disassemble_range<B: Bus>(
+    bus: &mut B,
+    range: RangeInclusive<u32>,
+    context: impl Into<DecodeContext>,
+) -> Vec<Disassembly> {
+    let mut context = context.into();
+    let mut lines = Vec::new();
+    let mut address = *range.start();
+    while address <= *range.end() {
+        let line = disassemble_with(bus, address, context);
+        context.follow(&line);
+        address += line.size() as u32;
+        lines.push(line);
+    }
+    return lines;
+}
@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::instructions::alu::DecimalMode;
 use w65xx_emulator::core::instructions::utils::AddressingModes;
 use w65xx_emulator::core::register::StatusFlags;
 use w65xx_emulator::peripherals::memory::VirtualMemory;
@@ -14,7 +15,7 @@ fn alu_test_setup() -> CPU {
         let paddng: Vec<u8> = vec![0xEA; 0xFF00];
         let working_data: Vec<u8> = (0..0xFC).collect();
         let reset_vector: Vec<u8> = vec![0x00, 0xFF];
-        let test_rom = vec![paddng, working_data, reset_vector].concat();
+        let test_rom = [paddng, working_data, reset_vector].concat();
         memory.load_rom(test_rom, 0x00).unwrap();
     }
     let mut cpu = CPU::new(memory_rc.clone());
@@ -35,7 +36,8 @@ fn adc_test() {
     cpu.processor_status_flags.clear_flag(StatusFlags::Carry);
 
     // Execute
-    cpu.sum_with_carry(&AddressingModes::Immediate, false);
+    cpu.sum_with_carry(&AddressingModes::Immediate, false)
+        .unwrap();
 
     // Verify
     assert_eq!(cpu.accumulator_cell.borrow().value, 3);
@@ -54,7 +56,8 @@ fn sbc_test() {
     cpu.processor_status_flags.set_flag(StatusFlags::Carry);
 
     // Execute
-    cpu.sum_with_carry(&AddressingModes::Immediate, true);
+    cpu.sum_with_carry(&AddressingModes::Immediate, true)
+        .unwrap();
 
     // Verify
     assert_eq!(cpu.accumulator_cell.borrow().value, 0x60);
@@ -73,7 +76,8 @@ fn sbc_borrow_test() {
     cpu.processor_status_flags.set_flag(StatusFlags::Carry);
 
     // Execute
-    cpu.sum_with_carry(&AddressingModes::Immediate, true);
+    cpu.sum_with_carry(&AddressingModes::Immediate, true)
+        .unwrap();
 
     // Verify
     assert_eq!(cpu.accumulator_cell.borrow().value, 0x60);
@@ -89,7 +93,7 @@ fn bitwise_and_test() {
     cpu.accumulator_cell.borrow_mut().value = 3;
 
     // Execute
-    cpu.bitwise_and(&AddressingModes::Immediate);
+    cpu.bitwise_and(&AddressingModes::Immediate).unwrap();
 
     // Verify
     assert_eq!(cpu.accumulator_cell.borrow().value, 1);
@@ -102,7 +106,7 @@ fn bitwise_or_test() {
     cpu.accumulator_cell.borrow_mut().value = 2;
 
     // Execute
-    cpu.bitwise_or(&AddressingModes::Immediate);
+    cpu.bitwise_or(&AddressingModes::Immediate).unwrap();
 
     // Verify
     assert_eq!(cpu.accumulator_cell.borrow().value, 3);
@@ -115,7 +119,8 @@ fn bitwise_exclusive_or_test() {
     cpu.accumulator_cell.borrow_mut().value = 0;
 
     // Execute
-    cpu.bitwise_exclusive_or(&AddressingModes::Immediate);
+    cpu.bitwise_exclusive_or(&AddressingModes::Immediate)
+        .unwrap();
 
     // Verify
     assert_eq!(cpu.accumulator_cell.borrow().value, 1);
@@ -203,7 +208,7 @@ fn bit_test() {
     cpu.accumulator_cell.borrow_mut().value = 0xff;
 
     // Execute
-    cpu.bit_instruction(&AddressingModes::ZeroPage);
+    cpu.bit_instruction(&AddressingModes::ZeroPage).unwrap();
 
     // Verify
     let status_register = &cpu.processor_status_flags;
@@ -228,3 +233,145 @@ fn bit_test() {
 //         true
 //     );
 // }
+
+// Decimal mode
+
+fn decimal_test_setup(accumulator: u8, operand: u8, carry: bool) -> CPU {
+    let mut cpu = alu_test_setup();
+    cpu.memory_rc.borrow_mut()[0xFF01] = operand;
+    cpu.accumulator_cell.borrow_mut().value = accumulator;
+    cpu.processor_status_flags.set_flag(StatusFlags::Decimal);
+    if carry {
+        cpu.processor_status_flags.set_flag(StatusFlags::Carry);
+    }
+    return cpu;
+}
+
+fn to_bcd(value: u8) -> u8 {
+    return ((value / 10) << 4) | (value % 10);
+}
+
+#[test]
+fn decimal_adc_test() {
+    // Every pair of valid BCD operands, with and without carry
+    for decimal_mode in [DecimalMode::Nmos, DecimalMode::Cmos] {
+        for a in 0..100_u8 {
+            for b in 0..100_u8 {
+                for carry in [false, true] {
+                    // Setup
+                    let mut cpu = decimal_test_setup(to_bcd(a), to_bcd(b), carry);
+                    cpu.decimal_mode = decimal_mode;
+
+                    // Execute
+                    cpu.sum_with_carry(&AddressingModes::Immediate, false)
+                        .unwrap();
+
+                    // Verify
+                    let sum = a as u16 + b as u16 + carry as u16;
+                    assert_eq!(
+                        cpu.accumulator_cell.borrow().value,
+                        to_bcd((sum % 100) as u8)
+                    );
+                    assert_eq!(
+                        cpu.processor_status_flags.check_flag(StatusFlags::Carry),
+                        sum >= 100
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn decimal_sbc_test() {
+    // Every pair of valid BCD operands, with and without borrow
+    for decimal_mode in [DecimalMode::Nmos, DecimalMode::Cmos] {
+        for a in 0..100_i16 {
+            for b in 0..100_i16 {
+                for carry in [false, true] {
+                    // Setup
+                    let mut cpu = decimal_test_setup(to_bcd(a as u8), to_bcd(b as u8), carry);
+                    cpu.decimal_mode = decimal_mode;
+
+                    // Execute
+                    cpu.sum_with_carry(&AddressingModes::Immediate, true)
+                        .unwrap();
+
+                    // Verify
+                    let difference = a - b - (!carry as i16);
+                    let expected = to_bcd(difference.rem_euclid(100) as u8);
+                    assert_eq!(cpu.accumulator_cell.borrow().value, expected);
+                    assert_eq!(
+                        cpu.processor_status_flags.check_flag(StatusFlags::Carry),
+                        difference >= 0
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn decimal_nmos_flags_test() {
+    // Setup, 0x99 + 0x01 = 0x00 with carry
+    let mut cpu = decimal_test_setup(0x99, 0x01, false);
+    let start = cpu.cycles;
+
+    // Execute
+    cpu.sum_with_carry(&AddressingModes::Immediate, false)
+        .unwrap();
+
+    // Verify, Z follows the binary sum (0x9A) and N the unadjusted high nibble (0xA0)
+    let flags = &cpu.processor_status_flags;
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x00);
+    assert!(flags.check_flag(StatusFlags::Carry));
+    assert!(!flags.check_flag(StatusFlags::Zero));
+    assert!(flags.check_flag(StatusFlags::Negative));
+    assert_eq!(cpu.cycles, start);
+}
+
+#[test]
+fn decimal_cmos_flags_test() {
+    // Setup, 0x99 + 0x01 = 0x00 with carry
+    let mut cpu = decimal_test_setup(0x99, 0x01, false);
+    cpu.decimal_mode = DecimalMode::Cmos;
+    let start = cpu.cycles;
+
+    // Execute
+    cpu.sum_with_carry(&AddressingModes::Immediate, false)
+        .unwrap();
+
+    // Verify, the CMOS parts set N and Z from the result and take an extra cycle
+    let flags = &cpu.processor_status_flags;
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x00);
+    assert!(flags.check_flag(StatusFlags::Carry));
+    assert!(flags.check_flag(StatusFlags::Zero));
+    assert!(!flags.check_flag(StatusFlags::Negative));
+    assert_eq!(cpu.cycles, start + 1);
+}
+
+#[test]
+fn decimal_invalid_bcd_test() {
+    // Setup, known NMOS results for operands that are not valid BCD
+    // (A, operand, carry, result, N, V, Z, C)
+    let cases = [
+        (0x0F, 0x01, false, 0x16, false, false, false, false),
+        (0x7F, 0x01, false, 0x86, true, true, false, false),
+        (0xFF, 0xFF, true, 0x55, true, false, false, true),
+    ];
+
+    for (a, operand, carry, result, n, v, z, c) in cases {
+        // Execute
+        let mut cpu = decimal_test_setup(a, operand, carry);
+        cpu.sum_with_carry(&AddressingModes::Immediate, false)
+            .unwrap();
+
+        // Verify
+        let flags = &cpu.processor_status_flags;
+        assert_eq!(cpu.accumulator_cell.borrow().value, result);
+        assert_eq!(flags.check_flag(StatusFlags::Negative), n);
+        assert_eq!(flags.check_flag(StatusFlags::Overflow), v);
+        assert_eq!(flags.check_flag(StatusFlags::Zero), z);
+        assert_eq!(flags.check_flag(StatusFlags::Carry), c);
+    }
+}
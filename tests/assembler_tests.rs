@@ -0,0 +1,304 @@
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::cpu::{StopReason, CPU};
+use w65xx_emulator::core::instructions::decode::OPCODE_TABLE;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+use w65xx_emulator::tools::assembler::assemble;
+use w65xx_emulator::tools::disassembler::disassemble_with;
+
+#[test]
+fn addressing_mode_test() {
+    // Setup
+    let source = "
+        LDA #$42
+        LDA $20
+        LDA $1234,X
+        LDX $20,Y
+        LDA ($20,X)
+        LDA ($20),Y
+        JMP ($FFFC)
+        ASL A
+        ASL
+        NOP
+    ";
+
+    // Execute
+    let assembly = assemble(source, CpuVariant::Nmos6502).unwrap();
+
+    // Verify
+    assert_eq!(
+        assembly.image,
+        vec![
+            0xA9, 0x42, 0xA5, 0x20, 0xBD, 0x34, 0x12, 0xB6, 0x20, 0xA1, 0x20, 0xB1, 0x20, 0x6C,
+            0xFC, 0xFF, 0x0A, 0x0A, 0xEA
+        ]
+    );
+}
+
+#[test]
+fn labels_test() {
+    // Setup, zero page is picked for addresses known by then, later labels get absolute
+    let source = "
+        .org $C000
+        counter = $10
+start:  LDX #$05
+loop:   DEC counter
+        BNE loop
+        BEQ done
+        LDA later
+done:   RTS
+later:  .byte 1
+    ";
+
+    // Execute
+    let assembly = assemble(source, CpuVariant::Nmos6502).unwrap();
+
+    // Verify
+    assert_eq!(assembly.origin, 0xC000);
+    assert_eq!(
+        assembly.image,
+        vec![0xA2, 0x05, 0xC6, 0x10, 0xD0, 0xFC, 0xF0, 0x03, 0xAD, 0x0C, 0xC0, 0x60, 0x01]
+    );
+    assert_eq!(assembly.symbols["start"], 0xC000);
+    assert_eq!(assembly.symbols["loop"], 0xC002);
+    assert_eq!(assembly.symbols["counter"], 0x10);
+}
+
+#[test]
+fn directives_test() {
+    // Setup
+    let source = "
+        .org $0200
+        .byte $01, 'A', \"hi, you\", -1
+        .word $1234, table
+        .res 3
+        .res 2, $EA
+table:  .org $0220
+        .byte <table, >table
+    ";
+
+    // Execute
+    let assembly = assemble(source, CpuVariant::Nmos6502).unwrap();
+
+    // Verify, the gap before the second .org is filled with zeros
+    let mut expected = vec![0x01, 0x41];
+    expected.extend_from_slice(b"hi, you");
+    expected.extend_from_slice(&[0xFF, 0x34, 0x12, 0x13, 0x02, 0, 0, 0, 0xEA, 0xEA]);
+    expected.extend_from_slice(&[0; 13]);
+    expected.extend_from_slice(&[0x13, 0x02]);
+    assert_eq!(assembly.origin, 0x0200);
+    assert_eq!(assembly.image, expected);
+}
+
+#[test]
+fn expression_test() {
+    // Setup
+    let source = "
+        .org $8000
+        base = $10
+        size = 4 * 2 + 1
+        LDA base + size - %11
+        LDA #<message + 1
+        LDX #>message
+        LDY #size / 2
+        JMP *
+message: .byte 0
+    ";
+
+    // Execute
+    let assembly = assemble(source, CpuVariant::Nmos6502).unwrap();
+
+    // Verify
+    assert_eq!(
+        assembly.image,
+        vec![0xA5, 0x16, 0xA9, 0x0C, 0xA2, 0x80, 0xA0, 0x04, 0x4C, 0x08, 0x80, 0x00]
+    );
+}
+
+#[test]
+fn variant_test() {
+    // Setup
+    let source = "
+        .org $8000
+        BRA skip
+        STZ $10
+        RMB3 $10
+skip:   BBS7 $10,skip
+        MVN $01,$02
+    ";
+
+    // Execute
+    let nmos = assemble(source, CpuVariant::Nmos6502);
+    let cmos = assemble(
+        ".org $8000\nBRA skip\nSTZ $10\nRMB3 $10\nskip: BBS7 $10,skip\n",
+        CpuVariant::Rockwell65C02,
+    );
+    let native = assemble(".org $8000\nMVN $01,$02\nBRL *\n", CpuVariant::Wdc65C816);
+
+    // Verify
+    assert_eq!(nmos.unwrap_err().line, 3);
+    assert_eq!(
+        cmos.unwrap().image,
+        vec![0x80, 0x04, 0x64, 0x10, 0x37, 0x10, 0xFF, 0x10, 0xFD]
+    );
+    assert_eq!(
+        native.unwrap().image,
+        vec![0x54, 0x02, 0x01, 0x82, 0xFD, 0xFF]
+    );
+}
+
+#[test]
+fn register_width_test() {
+    // Setup, switch to native mode with a 16 bit accumulator and index registers, then back to an 8 bit accumulator
+    let source = "
+        .org $8000
+        CLC
+        XCE
+        REP #$30
+        .a16
+        .i16
+        LDA #$1234
+        LDX #$10
+        SEP #$20
+        .a8
+        LDA #$56
+        LDY #next
+next:   RTS
+    ";
+
+    // Execute
+    let native = assemble(source, CpuVariant::Wdc65C816);
+    let cmos = assemble(".a16\nLDA #$1234\n", CpuVariant::Wdc65C02);
+
+    // Verify
+    assert_eq!(
+        native.unwrap().image,
+        vec![
+            0x18, 0xFB, 0xC2, 0x30, 0xA9, 0x34, 0x12, 0xA2, 0x10, 0x00, 0xE2, 0x20, 0xA9, 0x56,
+            0xA0, 0x11, 0x80, 0x60
+        ]
+    );
+    assert_eq!(cmos.unwrap_err().line, 1);
+}
+
+#[test]
+fn error_test() {
+    let cases = [
+        ("NOP\nLDA missing\n", 2),
+        ("loop: NOP\nloop: NOP\n", 2),
+        ("FOO #1\n", 1),
+        ("\n.org $8000\nBNE far\n.res 200\nfar: RTS\n", 3),
+        ("LDA #$100\n", 1),
+        (".org $FFFF\nNOP\nNOP\n", 3),
+        (".fill 3\n", 1),
+        ("LDA #$FFFFFF*$FFFFFF*$FFFFFF\n", 1),
+        ("LDA #-$7FFFFF*$FFFFFF*$FFFFFF-1\n", 1),
+        ("min = -$8000*$800000*$800000*4\nBNE min\n", 2),
+        ("min = -$8000*$800000*$800000*4\nLDA #-min\n", 2),
+        ("min = -$8000*$800000*$800000*4\nLDA #min/-1\n", 2),
+        ("min = -$8000*$800000*$800000*4\nLDA #min-1\n", 2),
+    ];
+    for (source, line) in cases {
+        // Execute
+        let error = assemble(source, CpuVariant::Nmos6502).unwrap_err();
+
+        // Verify
+        assert_eq!(error.line, line, "{}", error);
+    }
+}
+
+#[test]
+fn missing_bit_number_test() {
+    for source in ["rmb $12\n", "NOP\nSMB $12\n", "BBR $12,*\n", "BBS $12,*\n"] {
+        // Execute
+        let error = assemble(source, CpuVariant::Rockwell65C02).unwrap_err();
+
+        // Verify
+        assert_eq!(error.line, source.lines().count(), "{}", error);
+        assert!(error.message.contains("needs the bit number"), "{}", error);
+    }
+}
+
+#[test]
+fn non_ascii_source_test() {
+    let cases = [
+        ("  LDé #1\n", 1),
+        ("NOP\nRMBé $10\n", 2),
+        ("lé: NOP\n", 1),
+        ("NOP\nLDA #'é'\n", 2),
+    ];
+    for (source, line) in cases {
+        // Execute
+        let error = assemble(source, CpuVariant::Wdc65C02).unwrap_err();
+
+        // Verify
+        assert_eq!(error.line, line, "{}", error);
+    }
+}
+
+#[test]
+fn disassembler_round_trip_test() {
+    for variant in [
+        CpuVariant::Nmos6502,
+        CpuVariant::Wdc65C02,
+        CpuVariant::Rockwell65C02,
+        CpuVariant::Wdc65C816,
+    ] {
+        // The undocumented duplicates assemble to their documented opcode
+        for metadata in OPCODE_TABLE
+            .iter()
+            .filter(|metadata| metadata.variants.contains(&variant) && !metadata.undocumented)
+        {
+            // Setup, operands with a high byte so absolute addresses don't shrink to zero page
+            let mut program = vec![metadata.opcode, 0x34, 0x12, 0x01];
+            program.truncate(metadata.bytes as usize);
+            let mut memory = VirtualMemory::new();
+            memory.load_rom(program.clone(), 0x8000).unwrap();
+            let line = disassemble_with(&mut memory, 0x8000, variant);
+
+            // Execute
+            let assembly = assemble(&format!(".org $8000\n{}\n", line), variant).unwrap();
+
+            // Verify
+            assert_eq!(assembly.image, program, "{:?} {}", variant, line);
+        }
+    }
+}
+
+#[test]
+fn run_assembled_program_test() {
+    // Setup, multiply 3 by 5 with a loop
+    let assembly = assemble(
+        "
+        .org $8000
+        result = $10
+reset:  LDA #0
+        LDX #5
+loop:   CLC
+        ADC #3
+        DEX
+        BNE loop
+        STA result
+        JMP *
+
+        .org $FFFC
+        .word reset
+        ",
+        CpuVariant::Nmos6502,
+    )
+    .unwrap();
+    let memory_rc = Rc::new(RefCell::new(VirtualMemory::new()));
+    memory_rc
+        .borrow_mut()
+        .load_rom(assembly.image, assembly.origin as u32)
+        .unwrap();
+    let mut cpu = CPU::with_variant(memory_rc, CpuVariant::Nmos6502);
+    cpu.boot_cycle();
+
+    // Execute
+    let reason = cpu.run_until(|_| false).unwrap();
+
+    // Verify
+    assert_eq!(reason, StopReason::SelfJump(0x800C));
+    assert_eq!(cpu.memory_rc.borrow()[0x10], 15);
+}
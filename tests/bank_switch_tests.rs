@@ -0,0 +1,157 @@
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::bus::Bus;
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::bank_switch::BankedMemory;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+use w65xx_emulator::tools::assembler::assemble;
+
+const BANK_SIZE: usize = 0x4000;
+
+// A 128 KiB image where every byte holds the number of the bank it is in
+fn banked_image() -> Vec<u8> {
+    return (0..8)
+        .flat_map(|bank| vec![bank as u8; BANK_SIZE])
+        .collect();
+}
+
+#[test]
+fn load_large_image_test() {
+    // Setup
+    let mut memory = BankedMemory::new(VirtualMemory::new());
+
+    // Execute
+    let window = memory
+        .rom_window(0x8000, BANK_SIZE as u16, 0x7FFF, banked_image())
+        .unwrap();
+
+    // Verify
+    assert_eq!(memory.bank_count(window), 8);
+    assert_eq!(memory.selected_bank(window), 0);
+    assert_eq!(memory.read(0x8000), 0);
+    assert_eq!(memory.read(0xBFFF), 0);
+}
+
+#[test]
+fn control_register_test() {
+    // Setup
+    let mut memory = BankedMemory::new(VirtualMemory::new());
+    let window = memory
+        .rom_window(0x8000, BANK_SIZE as u16, 0x7FFF, banked_image())
+        .unwrap();
+
+    // Execute
+    memory.write(0x7FFF, 5);
+    let selected = memory.read(0x9000);
+    memory.write(0x9000, 0xAA);
+
+    // Verify, the ROM bank ignores the write and the control register never reaches RAM
+    assert_eq!(selected, 5);
+    assert_eq!(memory.selected_bank(window), 5);
+    assert_eq!(memory.read(0x9000), 5);
+    assert_eq!(memory.memory()[0x7FFF], 0);
+
+    // Bank numbers wrap around the bank count
+    memory.write(0x7FFF, 9);
+    assert_eq!(memory.selected_bank(window), 1);
+}
+
+#[test]
+fn ram_window_test() {
+    // Setup, four 8 KiB RAM banks behind $C000
+    let mut memory = BankedMemory::new(VirtualMemory::new());
+    memory.ram_window(0xC000, 0x2000, 0x7FFE, 4).unwrap();
+
+    // Execute
+    memory.write(0xC010, 0x11);
+    memory.write(0x7FFE, 2);
+    memory.write(0xC010, 0x22);
+    let bank_two = memory.read(0xC010);
+    memory.write(0x7FFE, 0);
+
+    // Verify
+    assert_eq!(bank_two, 0x22);
+    assert_eq!(memory.read(0xC010), 0x11);
+}
+
+#[test]
+fn short_last_bank_test() {
+    // Setup, a bank and a half
+    let mut memory = BankedMemory::new(VirtualMemory::new());
+    let window = memory
+        .rom_window(0x8000, 0x1000, 0x7FFF, vec![0x01; 0x1800])
+        .unwrap();
+
+    // Execute
+    memory.select_bank(window, 1);
+
+    // Verify, the rest of the last bank reads as erased
+    assert_eq!(memory.bank_count(window), 2);
+    assert_eq!(memory.read(0x87FF), 0x01);
+    assert_eq!(memory.read(0x8800), 0xFF);
+}
+
+#[test]
+fn bad_window_test() {
+    let mut memory = BankedMemory::new(VirtualMemory::new());
+    assert!(memory
+        .rom_window(0xC000, 0x8000, 0x7FFF, vec![0; 16])
+        .is_err());
+    assert!(memory.ram_window(0x8000, 0x2000, 0x7FFF, 0).is_err());
+    memory.ram_window(0x8000, 0x2000, 0x7FFF, 2).unwrap();
+    assert!(memory.ram_window(0x9000, 0x2000, 0x7FFE, 2).is_err());
+}
+
+#[test]
+fn extended_address_test() {
+    // Setup, a 65C816's 16 MiB of memory with a window in bank 0
+    let mut memory = BankedMemory::new(VirtualMemory::new_extended());
+    let window = memory
+        .rom_window(0x8000, BANK_SIZE as u16, 0x7FFF, banked_image())
+        .unwrap();
+
+    // Execute, the same offsets in bank 1
+    memory.write(0x017FFF, 3);
+    memory.write(0x018000, 0xAA);
+
+    // Verify, neither write touched the window
+    assert_eq!(memory.selected_bank(window), 0);
+    assert_eq!(memory.read(0x008000), 0);
+    assert_eq!(memory.read(0x017FFF), 3);
+    assert_eq!(memory.read(0x018000), 0xAA);
+    assert_eq!(memory.peek(0x018000), 0xAA);
+    assert_eq!(memory.memory().read_long(0x018000), 0xAA);
+}
+
+#[test]
+fn cpu_bank_switch_test() {
+    // Setup, the fixed code at $C000 selects bank 3 and reads from the window
+    let source = "
+        .org $C000
+reset:  LDA #$03
+        STA $7FFF
+        LDX $8000
+
+        .org $FFFC
+        .word reset
+    ";
+    let assembly = assemble(source, CpuVariant::Nmos6502).unwrap();
+    let mut memory = BankedMemory::new(VirtualMemory::new());
+    memory
+        .rom_window(0x8000, BANK_SIZE as u16, 0x7FFF, banked_image())
+        .unwrap();
+    memory
+        .memory_mut()
+        .load_rom(assembly.image, assembly.origin as u32)
+        .unwrap();
+    let mut cpu = CPU::new(Rc::new(RefCell::new(memory)));
+    cpu.boot_cycle();
+
+    // Execute
+    for _ in 0..3 {
+        cpu.step().unwrap().unwrap();
+    }
+
+    // Verify
+    assert_eq!(cpu.x_cell.borrow().value, 3);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use common::{assemble_program, cpu_setup, ORIGIN};
+use w65xx_emulator::core::bus::Bus;
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+
+const STATUS_PORT: u32 = 0xD000;
+const OUTPUT_PORT: u32 = 0xD001;
+
+// RAM with a couple of memory-mapped registers in it, reading the status port acknowledges it
+struct DeviceBus {
+    ram: VirtualMemory,
+    status: u8,
+    status_reads: usize,
+    output: Vec<u8>,
+}
+
+impl DeviceBus {
+    fn new() -> Self {
+        return DeviceBus {
+            ram: VirtualMemory::new(),
+            status: 0,
+            status_reads: 0,
+            output: Vec::new(),
+        };
+    }
+}
+
+impl Bus for DeviceBus {
+    fn read(&mut self, address: u32) -> u8 {
+        if address == STATUS_PORT {
+            self.status_reads += 1;
+            let status = self.status;
+            self.status = 0;
+            return status;
+        }
+        return self.ram.read(address);
+    }
+
+    fn write(&mut self, address: u32, data: u8) {
+        if address == OUTPUT_PORT {
+            self.output.push(data);
+            return;
+        }
+        self.ram.write(address, data);
+    }
+}
+
+fn program_setup(source: &str) -> CPU<DeviceBus> {
+    let program = assemble_program(source, CpuVariant::Nmos6502);
+    return cpu_setup(DeviceBus::new(), CpuVariant::Nmos6502, ORIGIN, program);
+}
+
+#[test]
+fn read_side_effect_test() {
+    // Setup
+    let source = "
+        LDA $D000
+        LDA $D000
+    ";
+    let mut cpu = program_setup(source);
+    cpu.memory_rc.borrow_mut().status = 0x80;
+
+    // Execute
+    cpu.step().unwrap().unwrap();
+    let first = cpu.accumulator_cell.borrow().value;
+    cpu.step().unwrap().unwrap();
+    let second = cpu.accumulator_cell.borrow().value;
+
+    // Verify, the first read cleared the status
+    assert_eq!(first, 0x80);
+    assert_eq!(second, 0x00);
+    assert_eq!(cpu.memory_rc.borrow().status_reads, 2);
+}
+
+#[test]
+fn device_write_test() {
+    // Setup
+    let source = "
+        LDA #$41
+        STA $D001
+        PHA
+    ";
+    let mut cpu = program_setup(source);
+
+    // Execute
+    for _ in 0..3 {
+        cpu.step().unwrap().unwrap();
+    }
+
+    // Verify, the store went to the device and the push went through the bus to RAM
+    let bus = cpu.memory_rc.borrow();
+    assert_eq!(bus.output, vec![0x41]);
+    assert_eq!(bus.ram[0xD001], 0x00);
+    assert_eq!(bus.ram[0x01FF], 0x41);
+}
+
+#[test]
+fn last_address_test() {
+    // Setup
+    let source = "
+        LDA #$5A
+        STA $FFFF
+        LDX $FFFF
+    ";
+    let mut cpu = program_setup(source);
+
+    // Execute
+    for _ in 0..3 {
+        cpu.step().unwrap().unwrap();
+    }
+
+    // Verify
+    assert_eq!(cpu.memory_rc.borrow().ram[0xFFFF], 0x5A);
+    assert_eq!(cpu.x_cell.borrow().value, 0x5A);
+}
+
+#[test]
+fn read_modify_write_side_effect_test() {
+    // Setup
+    let source = "
+        INC $D001
+        ASL $D000
+    ";
+    let mut cpu = program_setup(source);
+    cpu.memory_rc.borrow_mut().status = 0x01;
+
+    // Execute
+    cpu.step().unwrap().unwrap();
+    cpu.step().unwrap().unwrap();
+
+    // Verify, the output port sees the unmodified value first and the status port is read exactly once
+    let bus = cpu.memory_rc.borrow();
+    assert_eq!(bus.output, vec![0x00, 0x01]);
+    assert_eq!(bus.status_reads, 1);
+}
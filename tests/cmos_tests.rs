@@ -0,0 +1,352 @@
+mod common;
+
+use common::{program_setup, run};
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::cpu::{RunState, CPU};
+use w65xx_emulator::core::error::EmulationError;
+use w65xx_emulator::core::instructions::decode::{decode, Mnemonic};
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+
+#[test]
+fn cmos_only_opcodes_test() {
+    // Verify
+    assert_eq!(
+        decode(0x80, CpuVariant::Nmos6502).unwrap().mnemonic,
+        Mnemonic::NOP
+    );
+    assert_eq!(
+        decode(0x80, CpuVariant::Wdc65C02).unwrap().mnemonic,
+        Mnemonic::BRA
+    );
+    assert_eq!(
+        decode(0xCB, CpuVariant::Wdc65C02).unwrap().mnemonic,
+        Mnemonic::WAI
+    );
+    assert!(decode(0xCB, CpuVariant::Rockwell65C02).is_none());
+    assert!(decode(0xDB, CpuVariant::Rockwell65C02).is_none());
+}
+
+#[test]
+fn branch_always_test() {
+    // Setup
+    let source = "
+        BRA skip
+        LDA #$01
+skip:   LDA #$02
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+
+    // Execute
+    let cycles = run(&mut cpu, 2);
+
+    // Verify
+    assert_eq!(cycles, 3 + 2);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x02);
+}
+
+#[test]
+fn index_register_stack_test() {
+    // Setup
+    let source = "
+        LDX #$12
+        LDY #$34
+        PHX
+        PHY
+        PLX
+        PLY
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+
+    // Execute
+    let cycles = run(&mut cpu, 6);
+
+    // Verify, X and Y have been swapped through the stack
+    assert_eq!(cycles, 2 + 2 + 3 + 3 + 4 + 4);
+    assert_eq!(cpu.x_cell.borrow().value, 0x34);
+    assert_eq!(cpu.y_cell.borrow().value, 0x12);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xFF);
+}
+
+#[test]
+fn store_zero_test() {
+    // Setup
+    let source = "
+        LDX #$01
+        STZ $10
+        STZ $10,X
+        STZ $0200
+        STZ $0200,X
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        for address in [0x0010, 0x0011, 0x0200, 0x0201] {
+            memory[address] = 0xFF;
+        }
+    }
+
+    // Execute
+    let cycles = run(&mut cpu, 5);
+
+    // Verify
+    assert_eq!(cycles, 2 + 3 + 4 + 4 + 5);
+    let memory = cpu.memory_rc.borrow();
+    for address in [0x0010, 0x0011, 0x0200, 0x0201] {
+        assert_eq!(memory[address], 0x00);
+    }
+}
+
+#[test]
+fn test_and_modify_bits_test() {
+    // Setup
+    let source = "
+        LDA #$0F
+        TSB $10
+        TRB $0200
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        memory[0x0010] = 0xF0;
+        memory[0x0200] = 0xFF;
+    }
+
+    // Execute & Verify (TSB)
+    let cycles = run(&mut cpu, 2);
+    assert_eq!(cycles, 2 + 5);
+    assert_eq!(cpu.memory_rc.borrow()[0x0010], 0xFF);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Zero));
+
+    // Execute & Verify (TRB)
+    let cycles = run(&mut cpu, 1);
+    assert_eq!(cycles, 6);
+    assert_eq!(cpu.memory_rc.borrow()[0x0200], 0xF0);
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Zero));
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x0F);
+}
+
+#[test]
+fn accumulator_increment_test() {
+    // Setup
+    let source = "
+        LDA #$FF
+        INC A
+        DEC A
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+
+    // Execute & Verify
+    run(&mut cpu, 2);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x00);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Zero));
+    run(&mut cpu, 1);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0xFF);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Negative));
+}
+
+#[test]
+fn bit_immediate_test() {
+    // Setup
+    let source = "
+        LDA #$01
+        BIT #$C0
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+
+    // Execute
+    run(&mut cpu, 2);
+
+    // Verify, only Z is affected
+    let flags = &cpu.processor_status_flags;
+    assert!(flags.check_flag(StatusFlags::Zero));
+    assert!(!flags.check_flag(StatusFlags::Negative));
+    assert!(!flags.check_flag(StatusFlags::Overflow));
+}
+
+#[test]
+fn zero_page_indirect_test() {
+    // Setup
+    let source = "
+        LDA ($FF)
+        STA ($20)
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        // The pointer at $FF wraps around to $00 for its high byte
+        memory[0x00FF] = 0x34;
+        memory[0x0000] = 0x12;
+        memory[0x1234] = 0x56;
+        memory[0x0020] = 0x00;
+        memory[0x0021] = 0x03;
+    }
+
+    // Execute
+    let cycles = run(&mut cpu, 2);
+
+    // Verify
+    assert_eq!(cycles, 5 + 5);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x56);
+    assert_eq!(cpu.memory_rc.borrow()[0x0300], 0x56);
+}
+
+#[test]
+fn absolute_index_indirect_jump_test() {
+    // Setup
+    let source = "
+        LDX #$02
+        JMP ($3000,X)
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        memory[0x3002] = 0xCD;
+        memory[0x3003] = 0xAB;
+    }
+
+    // Execute
+    let cycles = run(&mut cpu, 2);
+
+    // Verify
+    assert_eq!(cycles, 2 + 6);
+    assert_eq!(cpu.program_counter.value, 0xABCD);
+}
+
+#[test]
+fn wait_for_interrupt_test() {
+    // Setup
+    let source = "
+        SEI
+        WAI
+        LDA #$01
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+
+    // Execute & Verify, nothing runs until an interrupt line is asserted
+    run(&mut cpu, 2);
+    assert_eq!(cpu.run_state, RunState::Waiting);
+    assert!(cpu.step().unwrap().is_none());
+    assert_eq!(cpu.program_counter.value, 0x8002);
+
+    // A masked IRQ wakes the CPU up without being serviced
+    cpu.pins.irq = true;
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::LDA);
+    assert_eq!(cpu.run_state, RunState::Running);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xFF);
+}
+
+#[test]
+fn stop_test() {
+    // Setup
+    let source = "
+        STP
+        NOP
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+
+    // Execute & Verify, interrupts can't wake it up
+    run(&mut cpu, 1);
+    assert_eq!(cpu.run_state, RunState::Stopped);
+    cpu.pins.irq = true;
+    cpu.pins.set_nmi(true);
+    assert_eq!(
+        cpu.step(),
+        Err(EmulationError::Halted {
+            state: RunState::Stopped,
+            address: 0x8001
+        })
+    );
+
+    // Only a reset can
+    cpu.pins.irq = false;
+    cpu.pins.reset = true;
+    assert!(cpu.step().unwrap().is_none());
+    cpu.pins.reset = false;
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::STP);
+    assert_eq!(cpu.program_counter.value, 0x8001);
+}
+
+#[test]
+fn bit_instruction_decode_test() {
+    // Verify
+    for variant in [CpuVariant::Wdc65C02, CpuVariant::Rockwell65C02] {
+        let instruction = decode(0xB7, variant).unwrap();
+        assert_eq!(instruction.mnemonic, Mnemonic::SMB);
+        assert_eq!(instruction.bit_index(), 3);
+        let instruction = decode(0x4F, variant).unwrap();
+        assert_eq!(instruction.mnemonic, Mnemonic::BBR);
+        assert_eq!(instruction.bit_index(), 4);
+        assert_eq!(instruction.size(), 3);
+    }
+}
+
+#[test]
+fn reset_set_memory_bit_test() {
+    // Setup
+    let source = "
+        RMB0 $10
+        SMB7 $10
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Rockwell65C02);
+    cpu.memory_rc.borrow_mut()[0x0010] = 0x0F;
+
+    // Execute & Verify
+    let cycles = run(&mut cpu, 1);
+    assert_eq!(cycles, 5);
+    assert_eq!(cpu.memory_rc.borrow()[0x0010], 0x0E);
+    run(&mut cpu, 1);
+    assert_eq!(cpu.memory_rc.borrow()[0x0010], 0x8E);
+    assert_eq!(cpu.program_counter.value, 0x8004);
+}
+
+#[test]
+fn branch_on_bit_test() {
+    // Setup, the BBR1 isn't taken
+    let source = "
+        BBS1 $10,set
+        .res 3
+set:    BBR1 $10,set
+        BBR0 $10,clear
+        .res 1
+clear:
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+    cpu.memory_rc.borrow_mut()[0x0010] = 0x02;
+
+    // Execute & Verify, taken
+    let cycles = run(&mut cpu, 1);
+    assert_eq!(cycles, 5 + 1);
+    assert_eq!(cpu.program_counter.value, 0x8006);
+
+    // Not taken
+    let cycles = run(&mut cpu, 1);
+    assert_eq!(cycles, 5);
+    assert_eq!(cpu.program_counter.value, 0x8009);
+
+    // Taken
+    run(&mut cpu, 1);
+    assert_eq!(cpu.program_counter.value, 0x800D);
+}
+
+#[test]
+fn branch_on_bit_wrap_test() {
+    // Setup, BBR0 $10, +5 at $FFFE with the offset wrapping round to $0000, and more memory past $FFFF to catch a
+    // read that doesn't wrap
+    let memory_rc = Rc::new(RefCell::new(VirtualMemory::new_extended()));
+    {
+        let mut memory = memory_rc.borrow_mut();
+        memory.load_rom(vec![0x0F, 0x10], 0xFFFE).unwrap();
+        memory.load_rom(vec![0x05], 0x0000).unwrap();
+        memory.load_rom(vec![0x20], 0x010000).unwrap();
+    }
+    let mut cpu = CPU::with_variant(memory_rc, CpuVariant::Rockwell65C02);
+    cpu.program_counter.value = 0xFFFE;
+
+    // Execute
+    run(&mut cpu, 1);
+
+    // Verify
+    assert_eq!(cpu.program_counter.value, 0x0006);
+}
@@ -0,0 +1,64 @@
+// Setup shared by the test suites, each one pulls it in with `mod common;` and uses what it needs
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::bus::Bus;
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+use w65xx_emulator::tools::assembler::assemble;
+
+// Where program_setup loads the program, and where BRK and IRQ go
+pub const ORIGIN: u16 = 0x8000;
+pub const IRQ_HANDLER: u16 = 0x9000;
+
+/// Writes program into the bus at origin with the reset vector pointing at it and the IRQ/BRK vector at
+/// IRQ_HANDLER, then boots the variant so the program counter starts on the program.
+pub fn cpu_setup<B: Bus>(bus: B, variant: CpuVariant, origin: u16, program: Vec<u8>) -> CPU<B> {
+    let bus_rc = Rc::new(RefCell::new(bus));
+    {
+        let mut bus = bus_rc.borrow_mut();
+        for (offset, byte) in program.into_iter().enumerate() {
+            bus.write(origin as u32 + offset as u32, byte);
+        }
+        let vectors = [origin.to_le_bytes(), IRQ_HANDLER.to_le_bytes()].concat();
+        for (offset, byte) in vectors.into_iter().enumerate() {
+            bus.write(0xFFFC + offset as u32, byte);
+        }
+    }
+    let mut cpu = CPU::with_variant(bus_rc, variant);
+    cpu.boot_cycle();
+    return cpu;
+}
+
+/// Assembles source for the variant into an image that starts at ORIGIN, a source that moves on with .org before
+/// its first byte is padded with zeros. Error lines count the .org put in front of it.
+pub fn assemble_program(source: &str, variant: CpuVariant) -> Vec<u8> {
+    let source = format!(".org ${:04X}\n{}", ORIGIN, source);
+    let assembly = match assemble(&source, variant) {
+        Ok(assembly) => assembly,
+        Err(error) => panic!("{}", error),
+    };
+    let mut image = vec![0; (assembly.origin - ORIGIN) as usize];
+    image.extend(assembly.image);
+    return image;
+}
+
+/// The program assembled from source at ORIGIN in 64 KiB of RAM.
+#[allow(dead_code)]
+pub fn program_setup(source: &str, variant: CpuVariant) -> CPU {
+    return cpu_setup(
+        VirtualMemory::new(),
+        variant,
+        ORIGIN,
+        assemble_program(source, variant),
+    );
+}
+
+/// Steps through instructions, none of which may fail, and returns the cycles they took.
+#[allow(dead_code)]
+pub fn run<B: Bus>(cpu: &mut CPU<B>, instructions: usize) -> u64 {
+    let start = cpu.cycles;
+    for _ in 0..instructions {
+        cpu.step().unwrap().unwrap();
+    }
+    return cpu.cycles - start;
+}
@@ -1,7 +1,7 @@
 use std::{cell::RefCell, rc::Rc};
 use w65xx_emulator::core::cpu::CPU;
 use w65xx_emulator::core::instructions::utils::{AddressingModes, BranchMode};
-use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::register::{StatusFlags, BREAK_MASK};
 use w65xx_emulator::peripherals::memory::VirtualMemory;
 
 fn test_setup() -> CPU {
@@ -12,7 +12,7 @@ fn test_setup() -> CPU {
         let paddng: Vec<u8> = vec![0xEA; 0xFF00];
         let working_data: Vec<u8> = (0..0xFC).collect();
         let reset_vector: Vec<u8> = vec![0x00, 0xFF];
-        let test_rom = vec![paddng, working_data, reset_vector].concat();
+        let test_rom = [paddng, working_data, reset_vector].concat();
         memory.load_rom(test_rom, 0x00).unwrap();
     }
     let mut cpu = CPU::new(memory_rc.clone());
@@ -28,7 +28,8 @@ fn compare_gt_test() {
     cpu.accumulator_cell.borrow_mut().value = value;
 
     // Execute
-    cpu.compare(&AddressingModes::Immediate, cpu.accumulator_cell.clone());
+    cpu.compare(&AddressingModes::Immediate, cpu.accumulator_cell.clone())
+        .unwrap();
 
     // Verify
     let sfr = &cpu.processor_status_flags;
@@ -51,7 +52,8 @@ fn compare_lt_test() {
     cpu.compare(
         &AddressingModes::AbsoluteXIndex,
         cpu.accumulator_cell.clone(),
-    );
+    )
+    .unwrap();
 
     // Verify
     let sfr = &cpu.processor_status_flags;
@@ -69,7 +71,8 @@ fn compare_eq_test() {
 
     // Execute
     cpu.accumulator_cell.borrow_mut().value = 1;
-    cpu.compare(&AddressingModes::Immediate, cpu.accumulator_cell.clone());
+    cpu.compare(&AddressingModes::Immediate, cpu.accumulator_cell.clone())
+        .unwrap();
 
     // Verify
     let sfr = &cpu.processor_status_flags;
@@ -84,33 +87,31 @@ fn compare_eq_test() {
 fn jump_test() {
     // Setup
     let mut cpu = test_setup();
-    cpu.memory_rc.borrow_mut()[0x0201] = !2 + 1;
 
-    // Execute
-    cpu.jump(&AddressingModes::Absolute, false, false);
+    // Execute (operand bytes at 0xff01 are 0x01, 0x02)
+    cpu.jump(&AddressingModes::Absolute, false).unwrap();
 
     // Verify
-    assert_eq!(cpu.program_counter.value, 0xfefe);
+    assert_eq!(cpu.program_counter.value, 0x0201);
 }
 
 #[test]
 fn subroutine_test() {
     // Setup
     let mut cpu = test_setup();
-    cpu.memory_rc.borrow_mut()[0x0201] = 20;
-    let next_pc = cpu.program_counter.value + 1;
+    let next_pc = cpu.program_counter.value + 3;
 
     // Execute (JSR)
-    cpu.jump(&AddressingModes::Absolute, true, false);
+    cpu.jump(&AddressingModes::Absolute, true).unwrap();
 
-    // Verify (JSR)
-    assert_eq!(cpu.program_counter.value, 0xff14);
+    // Verify (JSR), the pushed address is the last byte of the JSR instruction
+    assert_eq!(cpu.program_counter.value, 0x0201);
     {
         let sp = 0x0100 | cpu.stack_pointer.get_pointer() as u16;
         let memory = cpu.memory_rc.borrow();
         let pcl = memory[sp + 1];
         let pch = memory[sp + 2];
-        assert_eq!(pcl, 0x00);
+        assert_eq!(pcl, 0x02);
         assert_eq!(pch, 0xff);
     }
 
@@ -131,9 +132,9 @@ fn branching_test() {
     cpu.memory_rc.borrow_mut()[0xff01] = offset;
 
     // Execute
-    let expected_value = cpu.program_counter.value + (offset as u16);
+    let expected_value = cpu.program_counter.value + 2 + (offset as u16);
     cpu.processor_status_flags.set_flag(StatusFlags::Carry);
-    cpu.branch_exec(BranchMode::BCS);
+    cpu.branch_exec(BranchMode::BCS).unwrap();
 
     // Verify
     assert_eq!(cpu.program_counter.value, expected_value);
@@ -158,7 +159,7 @@ fn subtractive_branching_test() {
         (!0x20_u16 + 1)
     );
     cpu.processor_status_flags.set_flag(StatusFlags::Carry);
-    cpu.branch_exec(BranchMode::BCS);
+    cpu.branch_exec(BranchMode::BCS).unwrap();
 
     // Verify
     println!(
@@ -167,3 +168,55 @@ fn subtractive_branching_test() {
     );
     //assert_eq!(expected_value, cpu.program_counter.value);
 }
+
+#[test]
+fn break_return_test() {
+    // Setup
+    let mut cpu = test_setup();
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        memory[0xFFFE] = 0x00;
+        memory[0xFFFF] = 0x90;
+    }
+    cpu.processor_status_flags.set_flag(StatusFlags::Carry);
+
+    // Execute (BRK)
+    cpu.break_instruction();
+
+    // Verify (BRK), return address skips the padding byte and the pushed flags have B and bit 5 set
+    assert_eq!(cpu.program_counter.value, 0x9000);
+    assert!(cpu
+        .processor_status_flags
+        .check_flag(StatusFlags::InterruptDisable));
+    assert_eq!(cpu.processor_status_flags.get_flags() & BREAK_MASK, 0);
+    {
+        let memory = cpu.memory_rc.borrow();
+        assert_eq!(memory[0x01FF], 0xFF);
+        assert_eq!(memory[0x01FE], 0x02);
+        assert_eq!(memory[0x01FD], 0b0011_0001);
+    }
+
+    // Execute (RTI)
+    cpu.interrupt_return();
+
+    // Verify (RTI), B is dropped when the flags are pulled
+    assert_eq!(cpu.program_counter.value, 0xFF02);
+    assert_eq!(cpu.processor_status_flags.get_flags(), 0b0010_0001);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xFF);
+}
+
+#[test]
+fn interrupt_return_ignores_break_test() {
+    // Setup, fake an interrupt frame returning to 0x1234 with every bit set
+    let mut cpu = test_setup();
+    cpu.push_byte(0x12);
+    cpu.push_byte(0x34);
+    cpu.push_byte(0xFF);
+
+    // Execute
+    cpu.interrupt_return();
+
+    // Verify
+    assert_eq!(cpu.program_counter.value, 0x1234);
+    assert_eq!(cpu.processor_status_flags.get_flags(), 0b1110_1111);
+}
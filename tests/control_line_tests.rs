@@ -0,0 +1,109 @@
+mod common;
+
+use common::program_setup;
+use w65xx_emulator::core::bus::Bus;
+use w65xx_emulator::core::instructions::decode::Mnemonic;
+use w65xx_emulator::core::io::BusOperation;
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
+
+#[test]
+fn rdy_stall_test() {
+    // Setup
+    let mut cpu = program_setup("INX", CpuVariant::Nmos6502);
+    cpu.pins.rdy = false;
+    let start = cpu.cycles;
+
+    // Execute
+    let stalled = cpu.step();
+    cpu.pins.rdy = true;
+    let ran = cpu.step().unwrap().unwrap();
+
+    // Verify
+    assert!(stalled.unwrap().is_none());
+    assert_eq!(ran.mnemonic, Mnemonic::INX);
+    assert_eq!(cpu.x_cell.borrow().value, 1);
+    assert_eq!(cpu.cycles - start, 1 + 2);
+}
+
+#[test]
+fn rdy_write_cycle_test() {
+    // Setup, cycle stepped
+    let source = "
+        STA $0200
+        INX
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+    cpu.cycle_stepped = true;
+    for _ in 0..3 {
+        cpu.step_cycle().unwrap();
+    }
+
+    // Execute, RDY goes low in front of the write
+    cpu.pins.rdy = false;
+    let write = cpu.step_cycle().unwrap();
+    let held = cpu.step_cycle().unwrap();
+    let held_again = cpu.step_cycle().unwrap();
+
+    // Verify, the NMOS chip finishes the write and then stops on the next opcode fetch
+    assert_eq!(write.operation, BusOperation::Write);
+    assert_eq!(held.address, 0x8003);
+    assert!(held.sync);
+    assert_eq!(held_again, held);
+    assert_eq!(cpu.x_cell.borrow().value, 0);
+}
+
+#[test]
+fn set_overflow_test() {
+    // Setup
+    let source = "
+        CLV
+        NOP
+        NOP
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute
+    cpu.pins.set_so(true);
+    cpu.step().unwrap().unwrap();
+    let cleared = cpu.processor_status_flags.check_flag(StatusFlags::Overflow);
+    cpu.step().unwrap().unwrap();
+    let held = cpu.processor_status_flags.check_flag(StatusFlags::Overflow);
+    cpu.pins.set_so(false);
+    cpu.pins.set_so(true);
+    cpu.step().unwrap().unwrap();
+
+    // Verify, only the edge sets V, holding the line doesn't set it again after CLV
+    assert!(!cleared);
+    assert!(!held);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Overflow));
+}
+
+#[test]
+fn dma_test() {
+    // Setup
+    let mut cpu = program_setup("NOP", CpuVariant::Nmos6502);
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        memory.load_rom(vec![1, 2, 3, 4], 0x0200).unwrap();
+    }
+    let start = cpu.cycles;
+
+    // Execute, copy a page fragment like a sprite DMA
+    let taken = cpu.dma(|bus| {
+        bus.idle();
+        for offset in 0..4 {
+            let data = bus.read(0x0200 + offset);
+            bus.write(0x0300 + offset, data);
+        }
+    });
+
+    // Verify
+    assert_eq!(taken, 9);
+    assert_eq!(cpu.cycles - start, 9);
+    assert_eq!(cpu.pins.address_buffer, 0x0303);
+    assert!(!cpu.pins.read);
+    let memory = cpu.memory_rc.borrow();
+    assert_eq!(memory[0x0300], 1);
+    assert_eq!(memory[0x0303], 4);
+}
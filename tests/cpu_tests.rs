@@ -1,6 +1,13 @@
+mod common;
+
+use common::program_setup;
 use std::{cell::RefCell, rc::Rc};
 use w65xx_emulator::core::cpu::*;
+use w65xx_emulator::core::error::EmulationError;
+use w65xx_emulator::core::instructions::decode::{decode, Mnemonic};
 use w65xx_emulator::core::instructions::utils::AddressingModes;
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
 use w65xx_emulator::peripherals::memory::VirtualMemory;
 
 #[test]
@@ -29,7 +36,7 @@ fn address_mode_setup() -> CPU {
     let padding: Vec<u8> = vec![0xEA; 0xFCFF];
     let test_rom: Vec<u8> = (0..0xFC).collect();
     let reset_vector: Vec<u8> = vec![0x00, 0xFF];
-    let rom = vec![
+    let rom = [
         zp_padding,
         stack_page_padding,
         padding,
@@ -183,7 +190,7 @@ fn addr_indirect_y_test() {
     // Verify
     assert_eq!(pc.value, 0xFF00);
     assert_eq!(pc.value, 0xFF00);
-    assert_eq!(address, 0x0C0B); // pc + 1 = 0x1, lookup address = 0xB (0x0001 has 0x1) then + 10 (y) gives 0xB
+    assert_eq!(address, 0x020B); // pc + 1 = 0x1, zero page word at 0x0001 is 0x0201, then + 10 (y) gives 0x020B
     assert_eq!(data, 0xea); // almost all padding is 0xEA (nop instruction)
 }
 
@@ -237,3 +244,166 @@ fn addr_zeropage_y_test() {
     assert_eq!(address, 0x000B);
     assert_eq!(data, 0xB);
 }
+
+#[test]
+fn decode_table_test() {
+    // Verify
+    let documented = (0..=0xFF)
+        .filter_map(|opcode| decode(opcode, CpuVariant::Nmos6502))
+        .filter(|instruction| !instruction.undocumented)
+        .count();
+    assert_eq!(documented, 151);
+
+    let instruction = decode(0xB1, CpuVariant::Nmos6502).unwrap();
+    assert_eq!(instruction.mnemonic, Mnemonic::LDA);
+    assert_eq!(
+        instruction.addressing_mode,
+        AddressingModes::PostIndexIndirect
+    );
+    assert_eq!(instruction.size(), 2);
+    assert!(decode(0x02, CpuVariant::Wdc65C02).is_none());
+}
+
+#[test]
+fn step_loop_test() {
+    // Setup
+    let source = "
+        LDX #$05
+        LDA #$00
+loop:   CLC
+        ADC #$03
+        DEX
+        BNE loop
+        STA $0200
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    let boot_cycles = cpu.cycles;
+
+    // Execute
+    while cpu.program_counter.value != 0x800D {
+        cpu.step().unwrap().unwrap();
+    }
+
+    // Verify
+    assert_eq!(boot_cycles, 7);
+    assert_eq!(cpu.cycles - boot_cycles, 52); // 2 + 2 + 5 * 8 + 4 taken branches + 4
+    assert_eq!(cpu.accumulator_cell.borrow().value, 15);
+    assert_eq!(cpu.x_cell.borrow().value, 0);
+    assert_eq!(cpu.memory_rc.borrow()[0x0200], 15);
+}
+
+#[test]
+fn step_subroutine_test() {
+    // Setup
+    let source = "
+        JSR sub
+        LDY #$01
+        NOP
+sub:    LDX #$02
+        RTS
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute
+    let ran: Vec<Mnemonic> = (0..4)
+        .map(|_| cpu.step().unwrap().unwrap().mnemonic)
+        .collect();
+
+    // Verify
+    assert_eq!(
+        ran,
+        vec![Mnemonic::JSR, Mnemonic::LDX, Mnemonic::RTS, Mnemonic::LDY]
+    );
+    assert_eq!(cpu.x_cell.borrow().value, 2);
+    assert_eq!(cpu.y_cell.borrow().value, 1);
+    assert_eq!(cpu.program_counter.value, 0x8005);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xFF);
+}
+
+#[test]
+fn step_unassigned_opcode_test() {
+    // Setup
+    let memory_rc = Rc::new(RefCell::new(VirtualMemory::new()));
+    memory_rc
+        .borrow_mut()
+        .load_rom(vec![0x00, 0x80], 0xFFFC)
+        .unwrap();
+    memory_rc.borrow_mut()[0x8000] = 0x02;
+    let mut cpu = CPU::with_variant(memory_rc, CpuVariant::Wdc65C02);
+    cpu.illegal_opcodes = IllegalOpcodePolicy::Error;
+    cpu.boot_cycle();
+
+    // Execute
+    let result = cpu.step();
+
+    // Verify
+    assert_eq!(
+        result,
+        Err(EmulationError::IllegalOpcode {
+            opcode: 0x02,
+            address: 0x8000
+        })
+    );
+    assert_eq!(cpu.program_counter.value, 0x8000);
+}
+
+#[test]
+fn page_crossing_cycles_test() {
+    // Setup
+    let source = "
+        LDA $80F0,X
+        LDA $80F0,X
+        STA $0200,X
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute & Verify
+    let start = cpu.cycles;
+    cpu.step().unwrap().unwrap();
+    assert_eq!(cpu.cycles - start, 4);
+
+    cpu.x_cell.borrow_mut().value = 0x20;
+    let start = cpu.cycles;
+    cpu.step().unwrap().unwrap();
+    assert_eq!(cpu.cycles - start, 5);
+
+    // Stores always take the extra cycle, it is part of their base count
+    cpu.x_cell.borrow_mut().value = 0x00;
+    let start = cpu.cycles;
+    cpu.step().unwrap().unwrap();
+    assert_eq!(cpu.cycles - start, 5);
+}
+
+#[test]
+fn branch_cycles_test() {
+    // Setup, a branch placed at the end of a page then another one where it goes
+    let source = "
+        .org $80FD
+        BCS next
+        .org $810F
+next:   BCS * + $12
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+    cpu.program_counter.value = 0x80FD;
+
+    // Execute & Verify (not taken)
+    let start = cpu.cycles;
+    cpu.step().unwrap().unwrap();
+    assert_eq!(cpu.cycles - start, 2);
+    assert_eq!(cpu.program_counter.value, 0x80FF);
+
+    // Execute & Verify (taken, crossing into the next page)
+    cpu.program_counter.value = 0x80FD;
+    cpu.processor_status_flags.set_flag(StatusFlags::Carry);
+    let start = cpu.cycles;
+    cpu.step().unwrap().unwrap();
+    assert_eq!(cpu.cycles - start, 4);
+    assert_eq!(cpu.program_counter.value, 0x810F);
+
+    // Execute & Verify (taken, same page)
+    let start = cpu.cycles;
+    cpu.step().unwrap().unwrap();
+    assert_eq!(cpu.cycles - start, 3);
+    assert_eq!(cpu.program_counter.value, 0x8121);
+}
@@ -0,0 +1,529 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::instructions::decode::{Mnemonic, OPCODE_TABLE};
+use w65xx_emulator::core::io::{BusCycle, BusOperation};
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+
+fn program_setup(source: &str) -> CPU {
+    return variant_setup(source, CpuVariant::Nmos6502);
+}
+
+fn variant_setup(source: &str, variant: CpuVariant) -> CPU {
+    let mut cpu = common::program_setup(source, variant);
+    cpu.cycle_stepped = true;
+    return cpu;
+}
+
+fn read(address: u32, data: u8) -> (u32, u8, BusOperation) {
+    return (address, data, BusOperation::Read);
+}
+
+fn write(address: u32, data: u8) -> (u32, u8, BusOperation) {
+    return (address, data, BusOperation::Write);
+}
+
+fn run_cycles(cpu: &mut CPU, count: usize) -> Vec<BusCycle> {
+    return (0..count).map(|_| cpu.step_cycle().unwrap()).collect();
+}
+
+fn transactions(cycles: &[BusCycle]) -> Vec<(u32, u8, BusOperation)> {
+    return cycles
+        .iter()
+        .map(|cycle| (cycle.address, cycle.data, cycle.operation))
+        .collect();
+}
+
+#[test]
+fn absolute_read_cycles_test() {
+    // Setup
+    let mut cpu = program_setup("LDA $1234");
+    cpu.memory_rc.borrow_mut()[0x1234] = 0x77;
+
+    // Execute
+    let cycles = run_cycles(&mut cpu, 4);
+
+    // Verify, only the opcode fetch raises SYNC
+    assert_eq!(
+        transactions(&cycles),
+        vec![
+            read(0x8000, 0xAD),
+            read(0x8001, 0x34),
+            read(0x8002, 0x12),
+            read(0x1234, 0x77)
+        ]
+    );
+    assert!(cycles[0].sync);
+    assert!(cycles[1..].iter().all(|cycle| !cycle.sync));
+    assert_eq!(cpu.cycles, 7 + 4);
+}
+
+#[test]
+fn pins_follow_cycles_test() {
+    // Setup
+    let mut cpu = program_setup("STA $0200");
+    cpu.accumulator_cell.borrow_mut().value = 0x42;
+
+    // Execute
+    cpu.step_cycle().unwrap();
+    let opcode_sync = cpu.pins.sync;
+    run_cycles(&mut cpu, 3);
+
+    // Verify
+    assert!(opcode_sync);
+    assert!(!cpu.pins.sync);
+    assert!(!cpu.pins.read);
+    assert_eq!(cpu.pins.address_buffer, 0x0200);
+    assert_eq!(cpu.pins.data_buffer, 0x42);
+}
+
+#[test]
+fn implied_dummy_read_test() {
+    // Setup
+    let source = "
+        INX
+        PHA
+    ";
+    let mut cpu = program_setup(source);
+    cpu.accumulator_cell.borrow_mut().value = 0x99;
+
+    // Execute
+    let cycles = run_cycles(&mut cpu, 5);
+
+    // Verify, both read the next byte before doing anything else
+    assert_eq!(
+        transactions(&cycles),
+        vec![
+            read(0x8000, 0xE8),
+            read(0x8001, 0x48),
+            read(0x8001, 0x48),
+            read(0x8002, 0x00),
+            write(0x01FF, 0x99)
+        ]
+    );
+}
+
+#[test]
+fn indirect_indexed_cycles_test() {
+    // Setup
+    let mut cpu = program_setup("LDA ($10),Y");
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        memory[0x0010] = 0x00;
+        memory[0x0011] = 0x30;
+        memory[0x3002] = 0x5A;
+    }
+    cpu.y_cell.borrow_mut().value = 0x02;
+
+    // Execute
+    let cycles = run_cycles(&mut cpu, 5);
+
+    // Verify, the pointer is only read once
+    assert_eq!(
+        transactions(&cycles),
+        vec![
+            read(0x8000, 0xB1),
+            read(0x8001, 0x10),
+            read(0x0010, 0x00),
+            read(0x0011, 0x30),
+            read(0x3002, 0x5A)
+        ]
+    );
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x5A);
+}
+
+#[test]
+fn instruction_boundary_test() {
+    // Setup
+    let mut cpu = program_setup("JMP $8000");
+
+    // Execute, two instructions' worth
+    let cycles = run_cycles(&mut cpu, 6);
+
+    // Verify, every instruction starts with an opcode fetch
+    assert_eq!(cycles.iter().filter(|cycle| cycle.sync).count(), 2);
+    assert!(cycles[3].sync);
+    assert_eq!(cpu.cycles, 7 + 6);
+}
+
+#[test]
+fn internal_cycles_test() {
+    // Setup, the BNE is taken and the stack pointer is at $FC for the pulls
+    let mut jsr = program_setup("JSR $9000");
+    let mut rts = program_setup("RTS");
+    let mut pla = program_setup("PLA");
+    let mut lda = program_setup("LDA $10,X");
+    let mut bne = program_setup("BNE * + 4");
+    for cpu in [&mut rts, &mut pla] {
+        cpu.stack_pointer.set_pointer(0xFC);
+        let mut memory = cpu.memory_rc.borrow_mut();
+        memory[0x01FD] = 0x02;
+        memory[0x01FE] = 0x90;
+    }
+    lda.x_cell.borrow_mut().value = 0x02;
+    bne.processor_status_flags.clear_flag(StatusFlags::Zero);
+
+    // Execute, one cycle past the instruction to see the next opcode fetch
+    let jsr_cycles = run_cycles(&mut jsr, 7);
+    let rts_cycles = run_cycles(&mut rts, 7);
+    let pla_cycles = run_cycles(&mut pla, 5);
+    let lda_cycles = run_cycles(&mut lda, 5);
+    let bne_cycles = run_cycles(&mut bne, 4);
+
+    // Verify, every cycle is on the bus in the order the hardware runs them. JSR pushes the return address
+    // before it reads the high byte of the target, RTS reads the pulled address while stepping over it.
+    assert_eq!(
+        transactions(&jsr_cycles[..6]),
+        vec![
+            read(0x8000, 0x20),
+            read(0x8001, 0x00),
+            read(0x01FF, 0x00),
+            write(0x01FF, 0x80),
+            write(0x01FE, 0x02),
+            read(0x8002, 0x90)
+        ]
+    );
+    assert_eq!(
+        transactions(&rts_cycles[..6]),
+        vec![
+            read(0x8000, 0x60),
+            read(0x8001, 0x00),
+            read(0x01FC, 0x00),
+            read(0x01FD, 0x02),
+            read(0x01FE, 0x90),
+            read(0x9002, 0x00)
+        ]
+    );
+    assert_eq!(
+        transactions(&pla_cycles[..4]),
+        vec![
+            read(0x8000, 0x68),
+            read(0x8001, 0x00),
+            read(0x01FC, 0x00),
+            read(0x01FD, 0x02)
+        ]
+    );
+    assert_eq!(
+        transactions(&lda_cycles[..4]),
+        vec![
+            read(0x8000, 0xB5),
+            read(0x8001, 0x10),
+            read(0x0010, 0x00),
+            read(0x0012, 0x00)
+        ]
+    );
+    assert_eq!(
+        transactions(&bne_cycles[..3]),
+        vec![read(0x8000, 0xD0), read(0x8001, 0x02), read(0x8002, 0x00)]
+    );
+    for (cycles, next_opcode) in [
+        (&jsr_cycles, 0x9000),
+        (&rts_cycles, 0x9003),
+        (&pla_cycles, 0x8001),
+        (&lda_cycles, 0x8002),
+        (&bne_cycles, 0x8004),
+    ] {
+        let next = cycles.last().unwrap();
+        assert!(next.sync);
+        assert_eq!(next.address, next_opcode);
+    }
+}
+
+#[test]
+fn bus_cycle_count_test() {
+    for variant in [
+        CpuVariant::Nmos6502,
+        CpuVariant::Ricoh2A03,
+        CpuVariant::Wdc65C02,
+        CpuVariant::Rockwell65C02,
+        CpuVariant::Wdc65C816,
+    ] {
+        for metadata in OPCODE_TABLE.iter().filter(|metadata| {
+            metadata.variants.contains(&variant)
+                && !matches!(
+                    metadata.mnemonic,
+                    Mnemonic::JAM | Mnemonic::WAI | Mnemonic::STP
+                )
+        }) {
+            for decimal in [false, true] {
+                // Setup, whichever opcode follows has to be assigned on every variant
+                let source = format!(".byte ${:02X}, $10, $12, $EA", metadata.opcode);
+                let mut cpu = variant_setup(&source, variant);
+                if decimal {
+                    cpu.processor_status_flags.set_flag(StatusFlags::Decimal);
+                }
+
+                // Execute
+                let start = cpu.cycles;
+                let mut cycles = vec![cpu.step_cycle().unwrap()];
+                let elapsed = (cpu.cycles - start) as usize;
+                cycles.extend(run_cycles(&mut cpu, elapsed));
+
+                // Verify, the next opcode is fetched right after the counted cycles. Only the 65C816 has cycles
+                // that aren't on the bus.
+                let name = format!("{:?} {:02X} {:?}", variant, metadata.opcode, cycles);
+                assert!(cycles[elapsed].sync, "{}", name);
+                assert!(
+                    cycles[1..elapsed].iter().all(|cycle| !cycle.sync),
+                    "{}",
+                    name
+                );
+                if variant != CpuVariant::Wdc65C816 {
+                    assert!(
+                        cycles
+                            .iter()
+                            .all(|cycle| cycle.operation != BusOperation::Internal),
+                        "{}",
+                        name
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn read_modify_write_test() {
+    // Setup
+    let mut nmos = program_setup("INC $10");
+    let mut cmos = variant_setup("INC $10", CpuVariant::Wdc65C02);
+    nmos.memory_rc.borrow_mut()[0x0010] = 0x41;
+    cmos.memory_rc.borrow_mut()[0x0010] = 0x41;
+
+    // Execute
+    let nmos_cycles = run_cycles(&mut nmos, 5);
+    let cmos_cycles = run_cycles(&mut cmos, 5);
+
+    // Verify, the NMOS chip writes the old value back first, the 65C02 reads it again
+    assert_eq!(
+        transactions(&nmos_cycles[2..]),
+        vec![read(0x0010, 0x41), write(0x0010, 0x41), write(0x0010, 0x42)]
+    );
+    assert_eq!(
+        transactions(&cmos_cycles[2..]),
+        vec![read(0x0010, 0x41), read(0x0010, 0x41), write(0x0010, 0x42)]
+    );
+}
+
+#[test]
+fn indexed_page_cross_test() {
+    // Setup, with X = 1
+    let mut nmos = program_setup("LDA $10FF,X");
+    let mut cmos = variant_setup("LDA $10FF,X", CpuVariant::Wdc65C02);
+    nmos.x_cell.borrow_mut().value = 0x01;
+    cmos.x_cell.borrow_mut().value = 0x01;
+
+    // Execute
+    let nmos_cycles = run_cycles(&mut nmos, 5);
+    let cmos_cycles = run_cycles(&mut cmos, 5);
+
+    // Verify, NMOS reads the address before the carry reached the high byte, the 65C02 the last operand byte
+    assert_eq!(
+        transactions(&nmos_cycles[3..]),
+        vec![read(0x1000, 0x00), read(0x1100, 0x00)]
+    );
+    assert_eq!(
+        transactions(&cmos_cycles[3..]),
+        vec![read(0x8002, 0x10), read(0x1100, 0x00)]
+    );
+}
+
+#[test]
+fn indexed_page_cross_program_bank_test() {
+    // Setup, with X = 1 running from bank 1 of a 65C816
+    let program = common::assemble_program("LDA $10FF,X", CpuVariant::Wdc65C816);
+    let memory_rc = Rc::new(RefCell::new(VirtualMemory::new_extended()));
+    memory_rc.borrow_mut().load_rom(program, 0x018000).unwrap();
+    let mut cpu = CPU::with_variant(memory_rc, CpuVariant::Wdc65C816);
+    cpu.cycle_stepped = true;
+    cpu.program_bank = 0x01;
+    cpu.program_counter.value = 0x8000;
+    cpu.x_cell.borrow_mut().value = 0x01;
+
+    // Execute
+    let cycles = run_cycles(&mut cpu, 5);
+
+    // Verify, the extra cycle is spent on the last operand byte in the program bank
+    assert_eq!(cycles[3].address, 0x018002);
+}
+
+#[test]
+fn indexed_store_test() {
+    // Setup, with X = 1 so it doesn't cross a page
+    let mut cpu = program_setup("STA $1000,X");
+    cpu.x_cell.borrow_mut().value = 0x01;
+    cpu.accumulator_cell.borrow_mut().value = 0x33;
+
+    // Execute
+    let cycles = run_cycles(&mut cpu, 5);
+
+    // Verify, stores always spend the cycle
+    assert_eq!(
+        transactions(&cycles[3..]),
+        vec![read(0x1001, 0x00), write(0x1001, 0x33)]
+    );
+}
+
+#[test]
+fn undocumented_read_modify_write_test() {
+    // Setup
+    let mut cpu = program_setup("SLO $10");
+    cpu.memory_rc.borrow_mut()[0x0010] = 0x21;
+
+    // Execute
+    let cycles = run_cycles(&mut cpu, 5);
+
+    // Verify, the ORA half uses the shifted value without reading it again
+    assert_eq!(
+        transactions(&cycles[2..]),
+        vec![read(0x0010, 0x21), write(0x0010, 0x21), write(0x0010, 0x42)]
+    );
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x42);
+}
+
+#[test]
+fn zero_page_indexed_dummy_read_test() {
+    // Setup, with X and Y = 2
+    for source in ["LDA $10,X", "LDX $10,Y", "LDA ($10,X)"] {
+        let mut nmos = program_setup(source);
+        let mut cmos = variant_setup(source, CpuVariant::Wdc65C02);
+        for cpu in [&mut nmos, &mut cmos] {
+            cpu.x_cell.borrow_mut().value = 0x02;
+            cpu.y_cell.borrow_mut().value = 0x02;
+        }
+
+        // Execute
+        let nmos_cycles = run_cycles(&mut nmos, 4);
+        let cmos_cycles = run_cycles(&mut cmos, 4);
+
+        // Verify, NMOS reads the unindexed address while adding the index, the 65C02 the operand again
+        assert_eq!(
+            transactions(&nmos_cycles[1..]),
+            vec![read(0x8001, 0x10), read(0x0010, 0x00), read(0x0012, 0x00)]
+        );
+        assert_eq!(
+            transactions(&cmos_cycles[1..]),
+            vec![read(0x8001, 0x10), read(0x8001, 0x10), read(0x0012, 0x00)]
+        );
+    }
+}
+
+#[test]
+fn stack_pull_dummy_read_test() {
+    for variant in [CpuVariant::Nmos6502, CpuVariant::Wdc65C02] {
+        // Setup, with the stack pointer at $FC
+        let mut pla = variant_setup("PLA", variant);
+        let mut plp = variant_setup("PLP", variant);
+        let mut rti = variant_setup("RTI", variant);
+        let mut rts = variant_setup("RTS", variant);
+        for cpu in [&mut pla, &mut plp, &mut rti, &mut rts] {
+            cpu.stack_pointer.set_pointer(0xFC);
+            let mut memory = cpu.memory_rc.borrow_mut();
+            memory[0x01FD] = 0x20;
+            memory[0x01FE] = 0x34;
+            memory[0x01FF] = 0x12;
+        }
+
+        // Execute
+        let pla_cycles = run_cycles(&mut pla, 4);
+        let plp_cycles = run_cycles(&mut plp, 4);
+        let rti_cycles = run_cycles(&mut rti, 6);
+        let rts_cycles = run_cycles(&mut rts, 5);
+
+        // Verify, the top of the stack is read before the stack pointer is incremented
+        for (opcode, cycles) in [(0x68, pla_cycles), (0x28, plp_cycles)] {
+            assert_eq!(
+                transactions(&cycles),
+                vec![
+                    read(0x8000, opcode),
+                    read(0x8001, 0x00),
+                    read(0x01FC, 0x00),
+                    read(0x01FD, 0x20)
+                ]
+            );
+        }
+        assert_eq!(
+            transactions(&rti_cycles),
+            vec![
+                read(0x8000, 0x40),
+                read(0x8001, 0x00),
+                read(0x01FC, 0x00),
+                read(0x01FD, 0x20),
+                read(0x01FE, 0x34),
+                read(0x01FF, 0x12)
+            ]
+        );
+        assert_eq!(
+            transactions(&rts_cycles),
+            vec![
+                read(0x8000, 0x60),
+                read(0x8001, 0x00),
+                read(0x01FC, 0x00),
+                read(0x01FD, 0x20),
+                read(0x01FE, 0x34)
+            ]
+        );
+        assert_eq!(rti.program_counter.value, 0x1234);
+    }
+}
+
+#[test]
+fn branch_dummy_read_test() {
+    // Setup, a branch and one at $80FD that crosses into the next page
+    let cross = "
+        .org $80FD
+        BNE * + 6
+    ";
+    let mut nmos = program_setup("BNE * + 4");
+    let mut cmos = variant_setup("BNE * + 4", CpuVariant::Wdc65C02);
+    let mut nmos_cross = program_setup(cross);
+    let mut cmos_cross = variant_setup(cross, CpuVariant::Wdc65C02);
+    for cpu in [&mut nmos_cross, &mut cmos_cross] {
+        cpu.program_counter.value = 0x80FD;
+    }
+    for cpu in [&mut nmos, &mut cmos, &mut nmos_cross, &mut cmos_cross] {
+        cpu.processor_status_flags.clear_flag(StatusFlags::Zero);
+    }
+
+    // Execute
+    let nmos_cycles = run_cycles(&mut nmos, 4);
+    let cmos_cycles = run_cycles(&mut cmos, 4);
+    let nmos_cross_cycles = run_cycles(&mut nmos_cross, 5);
+    let cmos_cross_cycles = run_cycles(&mut cmos_cross, 5);
+
+    // Verify, the next opcode is read while the offset is added. On a page crossing NMOS reads the target before
+    // its high byte is fixed, the 65C02 reads the next opcode again.
+    for cycles in [nmos_cycles, cmos_cycles] {
+        assert_eq!(
+            transactions(&cycles[..3]),
+            vec![read(0x8000, 0xD0), read(0x8001, 0x02), read(0x8002, 0x00)]
+        );
+        assert!(cycles[3].sync);
+        assert_eq!(cycles[3].address, 0x8004);
+    }
+    assert_eq!(
+        transactions(&nmos_cross_cycles[..4]),
+        vec![
+            read(0x80FD, 0xD0),
+            read(0x80FE, 0x04),
+            read(0x80FF, 0x00),
+            read(0x8003, 0x00)
+        ]
+    );
+    assert_eq!(
+        transactions(&cmos_cross_cycles[..4]),
+        vec![
+            read(0x80FD, 0xD0),
+            read(0x80FE, 0x04),
+            read(0x80FF, 0x00),
+            read(0x80FF, 0x00)
+        ]
+    );
+    for cycles in [nmos_cross_cycles, cmos_cross_cycles] {
+        assert!(cycles[4].sync);
+        assert_eq!(cycles[4].address, 0x8103);
+    }
+}
@@ -0,0 +1,260 @@
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::bus::Bus;
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::instructions::decode::Mnemonic;
+use w65xx_emulator::core::instructions::utils::AddressingModes;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::bank_switch::BankedMemory;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+use w65xx_emulator::peripherals::memory_map::MemoryMapBuilder;
+use w65xx_emulator::tools::disassembler::{
+    disassemble, disassemble_range, disassemble_with, DecodeContext,
+};
+
+// A register that counts how often it's read, like a status register that clears itself
+struct CountingRegister {
+    reads: usize,
+}
+
+impl Bus for CountingRegister {
+    fn read(&mut self, _address: u32) -> u8 {
+        self.reads += 1;
+        return 0xEA;
+    }
+
+    fn write(&mut self, _address: u32, _data: u8) {}
+}
+
+fn memory_setup(program: Vec<u8>, address: u16) -> VirtualMemory {
+    let mut memory = VirtualMemory::new();
+    memory.load_rom(program, address as u32).unwrap();
+    return memory;
+}
+
+#[test]
+fn addressing_mode_syntax_test() {
+    let cases: Vec<(Vec<u8>, &str)> = vec![
+        (vec![0xEA], "NOP"),
+        (vec![0x0A], "ASL A"),
+        (vec![0xA9, 0x42], "LDA #$42"),
+        (vec![0xA5, 0x20], "LDA $20"),
+        (vec![0xB5, 0x20], "LDA $20,X"),
+        (vec![0xB6, 0x20], "LDX $20,Y"),
+        (vec![0xAD, 0x34, 0x12], "LDA $1234"),
+        (vec![0xBD, 0x34, 0x12], "LDA $1234,X"),
+        (vec![0xB9, 0x34, 0x12], "LDA $1234,Y"),
+        (vec![0xA1, 0x20], "LDA ($20,X)"),
+        (vec![0xB1, 0x20], "LDA ($20),Y"),
+        (vec![0x6C, 0xFC, 0xFF], "JMP ($FFFC)"),
+    ];
+    for (program, expected) in cases {
+        // Setup
+        let size = program.len() as u16;
+        let mut memory = memory_setup(program, 0x8000);
+
+        // Execute
+        let line = disassemble_with(&mut memory, 0x8000, CpuVariant::Nmos6502);
+
+        // Verify
+        assert_eq!(line.to_string(), expected);
+        assert_eq!(line.size(), size);
+    }
+}
+
+#[test]
+fn instruction_and_length_test() {
+    // Setup, JMP ($FFFC) then every opcode on its own
+    let mut memory = memory_setup(vec![0x6C, 0xFC, 0xFF], 0x8000);
+
+    // Execute
+    let (instruction, len) = disassemble(&mut memory, 0x8000);
+
+    // Verify
+    assert_eq!(instruction.mnemonic, Mnemonic::JMP);
+    assert_eq!(instruction.addressing_mode, AddressingModes::Indirect);
+    assert_eq!(len, 3);
+    for opcode in 0..=255 {
+        let mut memory = memory_setup(vec![opcode], 0x8000);
+        let (instruction, len) = disassemble(&mut memory, 0x8000);
+        assert_eq!(instruction.opcode, opcode);
+        assert_eq!(len, instruction.size());
+    }
+}
+
+#[test]
+fn branch_target_test() {
+    // Setup, BNE back and forward
+    let mut memory = memory_setup(vec![0xD0, 0xFC, 0xD0, 0x10], 0xC014);
+
+    // Execute
+    let back = disassemble_with(&mut memory, 0xC014, CpuVariant::Nmos6502);
+    let forward = disassemble_with(&mut memory, 0xC016, CpuVariant::Nmos6502);
+
+    // Verify
+    assert_eq!(back.to_string(), "BNE $C012");
+    assert_eq!(forward.to_string(), "BNE $C028");
+}
+
+#[test]
+fn variant_test() {
+    // Setup, LDA ($20), BRA and BBR0 on the 65C02. The NMOS part sees a JAM and a JSR
+    let mut memory = memory_setup(vec![0xB2, 0x20, 0x80, 0xFE, 0x0F, 0x12, 0x02], 0x8000);
+
+    // Execute
+    let nmos = disassemble_range(&mut memory, 0x8000..=0x8003, CpuVariant::Nmos6502);
+    let cmos = disassemble_range(&mut memory, 0x8000..=0x8004, CpuVariant::Rockwell65C02);
+
+    // Verify
+    let nmos: Vec<String> = nmos.iter().map(|line| line.to_string()).collect();
+    let cmos: Vec<String> = cmos.iter().map(|line| line.to_string()).collect();
+    assert_eq!(nmos, vec!["JAM", "JSR $FE80"]);
+    assert_eq!(cmos, vec!["LDA ($20)", "BRA $8002", "BBR0 $12,$8009"]);
+}
+
+#[test]
+fn unassigned_opcode_test() {
+    // Setup
+    let mut memory = memory_setup(vec![0x03, 0xEA], 0x8000);
+
+    // Execute
+    let lines = disassemble_range(&mut memory, 0x8000..=0x8001, CpuVariant::Wdc65C02);
+
+    // Verify
+    assert!(lines[0].instruction.is_none());
+    assert_eq!(lines[0].to_string(), ".byte $03");
+    assert_eq!(lines[1].instruction.unwrap().mnemonic, Mnemonic::NOP);
+}
+
+#[test]
+fn native_mode_test() {
+    // Setup
+    let mut memory = memory_setup(
+        vec![
+            0xAF, 0x56, 0x34, 0x12, 0xB7, 0x10, 0xA3, 0x03, 0x54, 0x02, 0x01, 0x82, 0xFD, 0xFF,
+        ],
+        0x8000,
+    );
+
+    // Execute
+    let lines = disassemble_range(&mut memory, 0x8000..=0x800B, CpuVariant::Wdc65C816);
+
+    // Verify
+    let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "LDA $123456",
+            "LDA [$10],Y",
+            "LDA $03,S",
+            "MVN $01,$02",
+            "BRL $800B"
+        ]
+    );
+}
+
+#[test]
+fn register_width_test() {
+    // Setup, CLC, XCE, REP #$30 then LDA #$1234
+    let mut memory = memory_setup(vec![0x18, 0xFB, 0xC2, 0x30, 0xA9, 0x34, 0x12], 0x8000);
+    memory.load_rom(vec![0x00, 0x80], 0xFFFC).unwrap();
+    let mut cpu = CPU::with_variant(Rc::new(RefCell::new(memory)), CpuVariant::Wdc65C816);
+    cpu.boot_cycle();
+    for _ in 0..3 {
+        cpu.step().unwrap();
+    }
+
+    // Execute
+    let context = DecodeContext::of(&cpu);
+    let line = disassemble_with(
+        &mut *cpu.memory_rc.borrow_mut(),
+        cpu.program_address(),
+        context,
+    );
+
+    // Verify
+    assert!(context.accumulator_wide && context.index_wide);
+    assert_eq!(line.to_string(), "LDA #$1234");
+    assert_eq!(line.size(), 3);
+}
+
+#[test]
+fn rep_sep_test() {
+    // Setup, LDA #$1234, LDX #$10, SEP #$20, LDA #$56, REP #$10, LDY #$2000
+    let mut memory = memory_setup(
+        vec![
+            0xA9, 0x34, 0x12, 0xA2, 0x10, 0xE2, 0x20, 0xA9, 0x56, 0xC2, 0x10, 0xA0, 0x00, 0x20,
+        ],
+        0x8000,
+    );
+    let context = DecodeContext {
+        variant: CpuVariant::Wdc65C816,
+        native: true,
+        accumulator_wide: true,
+        index_wide: false,
+    };
+
+    // Execute
+    let lines = disassemble_range(&mut memory, 0x8000..=0x800B, context);
+
+    // Verify
+    let lines: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "LDA #$1234",
+            "LDX #$10",
+            "SEP #$20",
+            "LDA #$56",
+            "REP #$10",
+            "LDY #$2000"
+        ]
+    );
+}
+
+#[test]
+fn listing_test() {
+    // Setup
+    let mut memory = memory_setup(vec![0xB1, 0x20], 0x8000);
+
+    // Execute
+    let line = disassemble_with(&mut memory, 0x8000, CpuVariant::Nmos6502);
+
+    // Verify
+    assert_eq!(line.listing(), "$8000  B1 20       LDA ($20),Y");
+}
+
+#[test]
+fn device_peek_test() {
+    // Setup, LDA $6000 in ROM with the register at $6000
+    let register = Rc::new(RefCell::new(CountingRegister { reads: 0 }));
+    let mut map = MemoryMapBuilder::new()
+        .device(0x6000..=0x6000, register.clone())
+        .rom(0x8000, vec![0xAD, 0x00, 0x60])
+        .build()
+        .unwrap();
+
+    // Execute
+    let line = disassemble_with(&mut map, 0x8000, CpuVariant::Nmos6502);
+    let device = disassemble_with(&mut map, 0x6000, CpuVariant::Nmos6502);
+
+    // Verify, the register is never read and gives the open bus value instead
+    assert_eq!(line.to_string(), "LDA $6000");
+    assert_eq!(device.bytes, vec![0x00]);
+    assert_eq!(register.borrow().reads, 0);
+}
+
+#[test]
+fn banked_memory_peek_test() {
+    // Setup, a JMP $1234 in the second bank of a window
+    let mut memory = BankedMemory::new(VirtualMemory::new());
+    let mut image = vec![0xEA; 0x2000];
+    image[0x1000..0x1003].copy_from_slice(&[0x4C, 0x34, 0x12]);
+    let window = memory.rom_window(0x8000, 0x1000, 0x7FFF, image).unwrap();
+    memory.select_bank(window, 1);
+
+    // Execute
+    let line = disassemble_with(&mut memory, 0x8000, CpuVariant::Nmos6502);
+
+    // Verify
+    assert_eq!(line.to_string(), "JMP $1234");
+}
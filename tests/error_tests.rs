@@ -0,0 +1,179 @@
+mod common;
+
+use common::{assemble_program, program_setup};
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::cpu::{IllegalOpcodePolicy, RunState, CPU};
+use w65xx_emulator::core::error::EmulationError;
+use w65xx_emulator::core::instructions::decode::{Instruction, Mnemonic};
+use w65xx_emulator::core::instructions::utils::AddressingModes;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+use w65xx_emulator::peripherals::memory_map::{MemoryMapBuilder, RomWritePolicy};
+
+#[test]
+fn illegal_opcode_test() {
+    // Setup, $03 is unassigned on the 65C02
+    let source = "
+        NOP
+        .byte $03
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+    cpu.illegal_opcodes = IllegalOpcodePolicy::Error;
+    cpu.step().unwrap().unwrap();
+    let cycles = cpu.cycles;
+
+    // Execute
+    let result = cpu.step();
+
+    // Verify, nothing about the CPU changed
+    assert_eq!(
+        result,
+        Err(EmulationError::IllegalOpcode {
+            opcode: 0x03,
+            address: 0x8001
+        })
+    );
+    assert_eq!(cpu.program_counter.value, 0x8001);
+    assert_eq!(cpu.cycles, cycles);
+}
+
+#[test]
+fn invalid_addressing_mode_test() {
+    // Setup, a JMP with an addressing mode no JMP opcode has
+    let mut cpu = program_setup("NOP", CpuVariant::Nmos6502);
+    let instruction = Instruction {
+        opcode: 0x4C,
+        mnemonic: Mnemonic::JMP,
+        addressing_mode: AddressingModes::Immediate,
+        cycles: 3,
+        page_penalty: false,
+        undocumented: false,
+    };
+
+    // Execute
+    let result = cpu.execute(&instruction);
+
+    // Verify
+    assert_eq!(
+        result,
+        Err(EmulationError::InvalidAddressingMode {
+            addressing_mode: AddressingModes::Immediate,
+            address: 0x8000
+        })
+    );
+    assert_eq!(cpu.program_counter.value, 0x8000);
+}
+
+#[test]
+fn halted_test() {
+    // Setup
+    let mut cpu = program_setup("STP", CpuVariant::Wdc65C02);
+    cpu.step().unwrap().unwrap();
+
+    // Execute
+    let result = cpu.step();
+
+    // Verify, the error can be displayed and the CPU recovers with a reset
+    assert_eq!(
+        result,
+        Err(EmulationError::Halted {
+            state: RunState::Stopped,
+            address: 0x8001
+        })
+    );
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "CPU halted (Stopped) at $8001"
+    );
+    cpu.reset();
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::STP);
+}
+
+#[test]
+fn bus_fault_test() {
+    // Setup, the store is trapped since $8000 is ROM
+    let source = "
+        LDA #$42
+        STA $8000
+        .res $FFFC - *, $EA
+        .word $8000
+        .res 2, $EA
+    ";
+    let rom = assemble_program(source, CpuVariant::Nmos6502);
+    let map = MemoryMapBuilder::new()
+        .ram(0x0000..=0x7FFF)
+        .rom(0x8000, rom)
+        .rom_writes(RomWritePolicy::Trap)
+        .build()
+        .unwrap();
+    let mut cpu = CPU::new(Rc::new(RefCell::new(map)));
+    cpu.boot_cycle();
+    cpu.step().unwrap().unwrap();
+
+    // Execute
+    let result = cpu.step();
+
+    // Verify, the store still finishes and the next step carries on
+    assert_eq!(result, Err(EmulationError::BusFault { address: 0x8000 }));
+    assert_eq!(cpu.program_counter.value, 0x8005);
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::NOP);
+}
+
+#[test]
+fn load_failure_test() {
+    // Setup
+    let mut memory = VirtualMemory::new();
+
+    // Execute
+    let result = memory.load_rom(vec![0xEA; 4], 0xFFFE);
+
+    // Verify
+    assert_eq!(
+        result,
+        Err(EmulationError::LoadFailure {
+            address: 0xFFFE,
+            reason: "Not enough space to fit ROM at this memory address"
+        })
+    );
+}
+
+#[test]
+fn program_counter_wrap_test() {
+    // Setup, NOP in the last byte of memory
+    let mut cpu = program_setup("NOP", CpuVariant::Nmos6502);
+    cpu.memory_rc.borrow_mut()[0xFFFF] = 0xEA;
+    cpu.program_counter.value = 0xFFFF;
+
+    // Execute
+    cpu.step().unwrap().unwrap();
+
+    // Verify
+    assert_eq!(cpu.program_counter.value, 0x0000);
+}
+
+#[test]
+fn load_past_buffer_test() {
+    // Setup
+    let mut memory = VirtualMemory::new();
+    let mut extended = VirtualMemory::new_extended();
+
+    // Execute
+    let bank_one = memory.load_rom(vec![0xEA; 4], 0x012000);
+    let top_bank = extended.load_rom(vec![0xEA; 4], 0xFFFFFE);
+
+    // Verify
+    assert_eq!(
+        bank_one,
+        Err(EmulationError::LoadFailure {
+            address: 0x012000,
+            reason: "Not enough space to fit ROM at this memory address"
+        })
+    );
+    assert_eq!(
+        top_bank,
+        Err(EmulationError::LoadFailure {
+            address: 0xFFFFFE,
+            reason: "Not enough space to fit ROM at this memory address"
+        })
+    );
+}
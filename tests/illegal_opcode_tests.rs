@@ -0,0 +1,173 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::cpu::{IllegalOpcodePolicy, RunState, CPU};
+use w65xx_emulator::core::error::EmulationError;
+use w65xx_emulator::core::instructions::decode::Mnemonic;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+
+fn program_setup(source: &str, policy: IllegalOpcodePolicy) -> CPU {
+    let mut cpu = common::program_setup(source, CpuVariant::Wdc65C02);
+    cpu.illegal_opcodes = policy;
+    return cpu;
+}
+
+#[test]
+fn error_policy_test() {
+    // Setup, the opcodes the 65C02 doesn't assign can only be written as bytes
+    let mut cpu = program_setup(".byte $5C, $00, $00", IllegalOpcodePolicy::Error);
+
+    // Execute
+    let result = cpu.step();
+
+    // Verify
+    assert_eq!(
+        result,
+        Err(EmulationError::IllegalOpcode {
+            opcode: 0x5C,
+            address: 0x8000
+        })
+    );
+    assert_eq!(cpu.program_counter.value, 0x8000);
+}
+
+#[test]
+fn default_policy_test() {
+    for (variant, nop) in [
+        (CpuVariant::Nmos6502, false),
+        (CpuVariant::Ricoh2A03, false),
+        (CpuVariant::Wdc65C02, true),
+        (CpuVariant::Rockwell65C02, true),
+        (CpuVariant::Wdc65C816, false),
+    ] {
+        // Setup
+        let memory_rc = Rc::new(RefCell::new(VirtualMemory::new()));
+
+        // Execute
+        let cpu = CPU::with_variant(memory_rc, variant);
+
+        // Verify, the 65C02s run their unassigned opcodes as NOPs like the real chips
+        assert_eq!(
+            matches!(cpu.illegal_opcodes, IllegalOpcodePolicy::Nop),
+            nop,
+            "{:?}",
+            variant
+        );
+    }
+}
+
+#[test]
+fn nop_policy_test() {
+    // (opcode, bytes, cycles) of the real chip
+    let nops = [
+        (0x03, 1, 1),
+        (0xFB, 1, 1),
+        (0x02, 2, 2),
+        (0xE2, 2, 2),
+        (0x44, 2, 3),
+        (0xF4, 2, 4),
+        (0x5C, 3, 8),
+        (0xDC, 3, 4),
+    ];
+    for (opcode, bytes, cycles) in nops {
+        // Setup
+        let source = format!(".byte ${:02X}, $12, $34", opcode);
+        let mut cpu = program_setup(&source, IllegalOpcodePolicy::Nop);
+        let start = cpu.cycles;
+
+        // Execute
+        let instruction = cpu.step().unwrap().unwrap();
+
+        // Verify
+        assert_eq!(instruction.mnemonic, Mnemonic::NOP);
+        assert_eq!(cpu.program_counter.value, 0x8000 + bytes, "${:02X}", opcode);
+        assert_eq!(cpu.cycles - start, cycles, "${:02X}", opcode);
+    }
+}
+
+#[test]
+fn jam_policy_test() {
+    // Setup
+    let source = "
+        .byte $03
+        NOP
+    ";
+    let mut cpu = program_setup(source, IllegalOpcodePolicy::Jam);
+
+    // Execute
+    let instruction = cpu.step().unwrap().unwrap();
+
+    // Verify, stuck on the opcode until a reset
+    assert_eq!(instruction.mnemonic, Mnemonic::JAM);
+    assert_eq!(cpu.run_state, RunState::Jammed);
+    assert_eq!(
+        cpu.step(),
+        Err(EmulationError::Halted {
+            state: RunState::Jammed,
+            address: 0x8000
+        })
+    );
+    cpu.reset();
+    assert_eq!(cpu.run_state, RunState::Running);
+}
+
+#[test]
+fn handler_policy_test() {
+    // Setup, the handler records the opcode and skips over it
+    let source = "
+        .byte $0B
+        INX
+    ";
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let mut cpu = program_setup(
+        source,
+        IllegalOpcodePolicy::Handler(Box::new(move |cpu, opcode| {
+            handler_seen
+                .borrow_mut()
+                .push((opcode, cpu.program_counter.value));
+            cpu.program_counter.increment(0);
+            return Ok(None);
+        })),
+    );
+
+    // Execute
+    let first = cpu.step().unwrap();
+    let second = cpu.step().unwrap().unwrap();
+
+    // Verify, the handler stays in place for the next illegal opcode
+    assert!(first.is_none());
+    assert_eq!(second.mnemonic, Mnemonic::INX);
+    assert_eq!(*seen.borrow(), vec![(0x0B, 0x8000)]);
+    assert!(matches!(
+        cpu.illegal_opcodes,
+        IllegalOpcodePolicy::Handler(_)
+    ));
+}
+
+#[test]
+fn handler_error_test() {
+    // Setup
+    let mut cpu = program_setup(
+        ".byte $33",
+        IllegalOpcodePolicy::Handler(Box::new(|cpu, opcode| {
+            return Err(EmulationError::IllegalOpcode {
+                opcode,
+                address: cpu.program_counter.value as u32,
+            });
+        })),
+    );
+
+    // Execute
+    let result = cpu.step();
+
+    // Verify
+    assert_eq!(
+        result,
+        Err(EmulationError::IllegalOpcode {
+            opcode: 0x33,
+            address: 0x8000
+        })
+    );
+}
@@ -0,0 +1,132 @@
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::instructions::decode::Mnemonic;
+use w65xx_emulator::core::register::{StatusFlags, BREAK_MASK};
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+use w65xx_emulator::tools::assembler::assemble;
+
+fn test_setup() -> CPU {
+    // Main program is a run of NOPs at 0x8000, NMI handler at 0x9000 and IRQ handler at 0xA000
+    let source = "
+        .org $8000
+reset:  .res $10, $EA
+
+        .org $9000
+nmi:    INX
+        RTI
+
+        .org $A000
+irq:    INY
+        RTI
+
+        .org $FFFA
+        .word nmi, reset, irq
+    ";
+    let assembly = assemble(source, CpuVariant::Nmos6502).unwrap();
+    let memory_rc = Rc::new(RefCell::new(VirtualMemory::new()));
+    memory_rc
+        .borrow_mut()
+        .load_rom(assembly.image, assembly.origin as u32)
+        .unwrap();
+    let mut cpu = CPU::new(memory_rc.clone());
+    cpu.boot_cycle(); // PC starts at 0x8000
+    return cpu;
+}
+
+#[test]
+fn irq_test() {
+    // Setup
+    let mut cpu = test_setup();
+    cpu.step().unwrap().unwrap();
+    cpu.processor_status_flags.clear_flag(StatusFlags::Carry);
+    cpu.pins.irq = true;
+    let start = cpu.cycles;
+
+    // Execute
+    let ran = cpu.step().unwrap().unwrap();
+
+    // Verify
+    assert_eq!(ran.mnemonic, Mnemonic::INY);
+    assert_eq!(cpu.cycles - start, 7 + 2);
+    assert!(cpu
+        .processor_status_flags
+        .check_flag(StatusFlags::InterruptDisable));
+    {
+        let memory = cpu.memory_rc.borrow();
+        assert_eq!(cpu.stack_pointer.get_pointer(), 0xFC);
+        assert_eq!(memory[0x01FF], 0x80);
+        assert_eq!(memory[0x01FE], 0x01);
+        assert_eq!(memory[0x01FD] & BREAK_MASK, 0);
+        assert_eq!(memory[0x01FD] & StatusFlags::InterruptDisable.get_mask(), 0);
+    }
+
+    // The line is still asserted, but the handler runs with interrupts disabled
+    let ran = cpu.step().unwrap().unwrap();
+    assert_eq!(ran.mnemonic, Mnemonic::RTI);
+    assert_eq!(cpu.program_counter.value, 0x8001);
+    assert_eq!(cpu.y_cell.borrow().value, 1);
+}
+
+#[test]
+fn irq_masked_test() {
+    // Setup
+    let mut cpu = test_setup();
+    cpu.processor_status_flags
+        .set_flag(StatusFlags::InterruptDisable);
+    cpu.pins.irq = true;
+
+    // Execute
+    let ran = cpu.step().unwrap().unwrap();
+
+    // Verify
+    assert_eq!(ran.mnemonic, Mnemonic::NOP);
+    assert_eq!(cpu.program_counter.value, 0x8001);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xFF);
+}
+
+#[test]
+fn nmi_edge_test() {
+    // Setup
+    let mut cpu = test_setup();
+    cpu.processor_status_flags
+        .set_flag(StatusFlags::InterruptDisable);
+
+    // Execute, NMI ignores the interrupt disable flag
+    cpu.pins.set_nmi(true);
+    let ran = cpu.step().unwrap().unwrap();
+
+    // Verify
+    assert_eq!(ran.mnemonic, Mnemonic::INX);
+    assert_eq!(cpu.x_cell.borrow().value, 1);
+
+    // Holding the line low does not trigger another NMI
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::RTI);
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::NOP);
+    assert_eq!(cpu.x_cell.borrow().value, 1);
+
+    // A new falling edge does
+    cpu.pins.set_nmi(false);
+    cpu.pins.set_nmi(true);
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::INX);
+    assert_eq!(cpu.x_cell.borrow().value, 2);
+}
+
+#[test]
+fn reset_line_test() {
+    // Setup
+    let mut cpu = test_setup();
+    cpu.step().unwrap().unwrap();
+    cpu.step().unwrap().unwrap();
+
+    // Execute & Verify, nothing runs while reset is held
+    cpu.pins.reset = true;
+    assert!(cpu.step().unwrap().is_none());
+    assert!(cpu.step().unwrap().is_none());
+    assert_eq!(cpu.program_counter.value, 0x8002);
+
+    // Releasing reset restarts from the reset vector
+    cpu.pins.reset = false;
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::NOP);
+    assert_eq!(cpu.program_counter.value, 0x8001);
+}
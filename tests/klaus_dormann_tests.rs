@@ -0,0 +1,168 @@
+// Klaus Dormann's functional tests, https://github.com/Klaus2m5/6502_65C02_functional_tests
+// The binaries aren't part of the repository, copy them into tests/fixtures/klaus and run cargo test -- --ignored.
+// A test whose binary is missing fails. The success addresses are the ones of the binaries in the bin_files directory upstream,
+// assembling the tests with other options moves them. Upstream doesn't ship binaries of the decimal tests, so each one needs
+// a sidecar next to it, 6502_decimal_test.end holding the address of end_of_test (the DONE label) from the listing in hex.
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc};
+use w65xx_emulator::core::cpu::{IllegalOpcodePolicy, StopReason, CPU};
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+
+// The functional tests keep the number of the test they are on just past the zero page
+const TEST_CASE: u16 = 0x0200;
+// The decimal tests leave 0 here when they pass
+const DECIMAL_ERROR: u16 = 0x000B;
+// The functional test takes a little under 100 million cycles, anything well past that is stuck
+const CYCLE_BUDGET: u64 = 200_000_000;
+
+fn fixture_path(name: &str) -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("klaus")
+        .join(name);
+}
+
+fn read_fixture(name: &str) -> Vec<u8> {
+    return match fs::read(fixture_path(name)) {
+        Ok(contents) => contents,
+        Err(error) => panic!("{} isn't in tests/fixtures/klaus: {}", name, error),
+    };
+}
+
+// The address of end_of_test from the sidecar of a decimal test binary
+fn end_of_test(name: &str) -> u32 {
+    let sidecar = name.replace(".bin", ".end");
+    let contents = String::from_utf8_lossy(&read_fixture(&sidecar)).into_owned();
+    let address = contents.trim().trim_start_matches('$');
+    return match u32::from_str_radix(address, 16) {
+        Ok(address) => address,
+        Err(error) => panic!("{} doesn't hold a hex address: {}", sidecar, error),
+    };
+}
+
+// Loads the binary at load_address and runs it from start until it traps, or gets to end_of_test if there is one
+fn run_fixture(
+    name: &str,
+    variant: CpuVariant,
+    load_address: u16,
+    start: u16,
+    end_of_test: Option<u32>,
+) -> (CPU, StopReason) {
+    let image = read_fixture(name);
+    let memory_rc = Rc::new(RefCell::new(VirtualMemory::new()));
+    memory_rc
+        .borrow_mut()
+        .load_rom(image, load_address as u32)
+        .unwrap();
+    let mut cpu = CPU::with_variant(memory_rc, variant);
+    // The extended opcodes test runs the 65C02's unassigned opcodes, the real chip treats them as NOPs.
+    // The NMOS chips assign every opcode so it makes no difference to them.
+    cpu.illegal_opcodes = IllegalOpcodePolicy::Nop;
+    cpu.boot_cycle();
+    cpu.program_counter.value = start;
+    // The tests check BRK and RTI with real BRKs, they run as interrupts unless stop_on_break is set. A run that does
+    // stop on one carries on with what's left of the budget.
+    loop {
+        let result = match end_of_test {
+            Some(address) => {
+                cpu.run_until(|cpu| cpu.program_address() == address || cpu.cycles >= CYCLE_BUDGET)
+            }
+            None => cpu.run_for_cycles(CYCLE_BUDGET.saturating_sub(cpu.cycles)),
+        };
+        match result {
+            Ok(StopReason::Break(_)) => {}
+            Ok(reason) => return (cpu, reason),
+            Err(error) => panic!(
+                "{} failed in test ${:02X}: {}",
+                name,
+                test_case(&cpu),
+                error
+            ),
+        }
+    }
+}
+
+fn test_case(cpu: &CPU) -> u8 {
+    return cpu.memory_rc.borrow()[TEST_CASE];
+}
+
+// The functional tests trap in a JMP * when they are done, anywhere else is the check that failed
+fn assert_functional_pass(name: &str, cpu: &CPU, reason: StopReason, success: u32) {
+    match reason {
+        StopReason::SelfJump(address) if address == success => {}
+        StopReason::SelfJump(address) => panic!(
+            "{} failed in test ${:02X}, trapped at ${:04X}",
+            name,
+            test_case(cpu),
+            address
+        ),
+        reason => panic!(
+            "{} failed in test ${:02X}, stopped with {:?}",
+            name,
+            test_case(cpu),
+            reason
+        ),
+    }
+}
+
+// The decimal tests get to end_of_test whether they pass or not and leave the result in memory. The stock end_of_test is
+// an STP, which the NMOS parts don't have, so the run stops on its address before it is fetched.
+fn assert_decimal_pass(name: &str, cpu: &CPU, reason: StopReason, end_of_test: u32) {
+    assert_eq!(
+        reason,
+        StopReason::Breakpoint(end_of_test),
+        "{} never got to end_of_test at ${:04X}",
+        name,
+        end_of_test
+    );
+    let error = cpu.memory_rc.borrow()[DECIMAL_ERROR];
+    assert_eq!(
+        error, 0,
+        "{} failed, ERROR is ${:02X} at {:?}",
+        name, error, reason
+    );
+}
+
+#[test]
+#[ignore = "needs the binary in tests/fixtures/klaus"]
+fn functional_test() {
+    let name = "6502_functional_test.bin";
+    let (cpu, reason) = run_fixture(name, CpuVariant::Nmos6502, 0x0000, 0x0400, None);
+    assert_functional_pass(name, &cpu, reason, 0x3469);
+}
+
+#[test]
+#[ignore = "needs the binary in tests/fixtures/klaus"]
+fn cmos_functional_test() {
+    // The 65C02 runs everything the NMOS part does
+    let name = "6502_functional_test.bin";
+    let (cpu, reason) = run_fixture(name, CpuVariant::Wdc65C02, 0x0000, 0x0400, None);
+    assert_functional_pass(name, &cpu, reason, 0x3469);
+}
+
+#[test]
+#[ignore = "needs the binary in tests/fixtures/klaus"]
+fn extended_opcodes_test() {
+    let name = "65C02_extended_opcodes_test.bin";
+    let (cpu, reason) = run_fixture(name, CpuVariant::Wdc65C02, 0x0000, 0x0400, None);
+    assert_functional_pass(name, &cpu, reason, 0x24F1);
+}
+
+#[test]
+#[ignore = "needs the binary and its .end sidecar in tests/fixtures/klaus"]
+fn decimal_test() {
+    let name = "6502_decimal_test.bin";
+    let end = end_of_test(name);
+    let (cpu, reason) = run_fixture(name, CpuVariant::Nmos6502, 0x0200, 0x0200, Some(end));
+    assert_decimal_pass(name, &cpu, reason, end);
+}
+
+#[test]
+#[ignore = "needs the binary and its .end sidecar in tests/fixtures/klaus"]
+fn cmos_decimal_test() {
+    let name = "65C02_decimal_test.bin";
+    let end = end_of_test(name);
+    let (cpu, reason) = run_fixture(name, CpuVariant::Wdc65C02, 0x0200, 0x0200, Some(end));
+    assert_decimal_pass(name, &cpu, reason, end);
+}
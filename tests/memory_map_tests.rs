@@ -0,0 +1,226 @@
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::bus::Bus;
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::error::EmulationError;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory_map::{MemoryMap, MemoryMapBuilder, RomWritePolicy};
+use w65xx_emulator::tools::assembler::assemble;
+
+// Sixteen plain registers, enough to stand in for a 6522 VIA
+struct Registers {
+    values: [u8; 16],
+}
+
+impl Bus for Registers {
+    fn read(&mut self, address: u32) -> u8 {
+        return self.values[address as usize];
+    }
+
+    fn write(&mut self, address: u32, data: u8) {
+        self.values[address as usize] = data;
+    }
+}
+
+fn board(rom: Vec<u8>) -> (MemoryMap, Rc<RefCell<Registers>>) {
+    let via = Rc::new(RefCell::new(Registers { values: [0; 16] }));
+    let mut image = vec![0xEA; 0x8000];
+    image[..rom.len()].copy_from_slice(&rom);
+    image[0x7FFC] = 0x00; // Reset vector to $8000
+    image[0x7FFD] = 0x80;
+    let map = MemoryMapBuilder::new()
+        .ram(0x0000..=0x3FFF)
+        .device(0x6000..=0x6FFF, via.clone())
+        .decoded(0x000F)
+        .rom(0x8000, image)
+        .build()
+        .unwrap();
+    return (map, via);
+}
+
+#[test]
+fn ram_rom_test() {
+    // Setup
+    let (mut map, _) = board(vec![0x42]);
+
+    // Execute
+    map.write(0x1234, 0x99);
+    map.write(0x8000, 0x00);
+
+    // Verify, RAM takes the write and ROM ignores it
+    assert_eq!(map.read(0x1234), 0x99);
+    assert_eq!(map.read(0x8000), 0x42);
+    assert!(map.trapped_writes().is_empty());
+}
+
+#[test]
+fn partial_decoding_test() {
+    // Setup
+    let (mut map, via) = board(vec![]);
+
+    // Execute, $6013 and $6FF3 both decode to register 3
+    map.write(0x6013, 0x55);
+
+    // Verify
+    assert_eq!(via.borrow().values[3], 0x55);
+    assert_eq!(map.read(0x6FF3), 0x55);
+}
+
+#[test]
+fn mirror_test() {
+    // Setup, 2 KiB of RAM repeated through $0000-$1FFF and mirrored again at $4000
+    let mut map = MemoryMapBuilder::new()
+        .ram(0x0000..=0x1FFF)
+        .decoded(0x07FF)
+        .mirror(0x4000..=0x47FF, 0x0000)
+        .build()
+        .unwrap();
+
+    // Execute
+    map.write(0x0801, 0x12);
+
+    // Verify
+    assert_eq!(map.read(0x0001), 0x12);
+    assert_eq!(map.read(0x1801), 0x12);
+    assert_eq!(map.read(0x4001), 0x12);
+}
+
+#[test]
+fn uneven_ram_test() {
+    // Setup, 24 KiB isn't a power of two so no address lines are left out
+    let mut map = MemoryMapBuilder::new()
+        .ram(0x0000..=0x5FFF)
+        .build()
+        .unwrap();
+
+    // Execute
+    map.write(0x0000, 0x11);
+    map.write(0x2000, 0x22);
+    map.write(0x5FFF, 0x33);
+
+    // Verify
+    assert_eq!(map.read(0x0000), 0x11);
+    assert_eq!(map.read(0x2000), 0x22);
+    assert_eq!(map.read(0x5FFF), 0x33);
+    assert_eq!(map.read(0x1FFF), 0x00);
+}
+
+#[test]
+fn uneven_rom_test() {
+    // Setup, a 12 KiB image with each 4 KiB block filled with its number
+    let image: Vec<u8> = (0..0x3000)
+        .map(|offset| (offset / 0x1000) as u8 + 1)
+        .collect();
+    let mut map = MemoryMapBuilder::new().rom(0xD000, image).build().unwrap();
+
+    // Verify
+    assert_eq!(map.read(0xD000), 1);
+    assert_eq!(map.read(0xE000), 2);
+    assert_eq!(map.read(0xF000), 3);
+    assert_eq!(map.read(0xFFFF), 3);
+}
+
+#[test]
+fn large_mirror_test() {
+    // Setup, 2 KiB of RAM repeated three more times through $0800-$1FFF like the NES
+    let mut map = MemoryMapBuilder::new()
+        .ram(0x0000..=0x07FF)
+        .mirror(0x0800..=0x1FFF, 0x0000)
+        .build()
+        .unwrap();
+
+    // Execute
+    map.write(0x1234, 0x56);
+
+    // Verify
+    assert_eq!(map.read(0x0234), 0x56);
+    assert_eq!(map.read(0x0A34), 0x56);
+    assert_eq!(map.read(0x1A34), 0x56);
+}
+
+#[test]
+fn open_bus_test() {
+    // Setup
+    let (mut map, _) = board(vec![0xA5]);
+
+    // Execute, nothing is mapped at $5000
+    map.read(0x8000);
+    map.write(0x5000, 0x01);
+    let unmapped = map.read(0x5000);
+
+    // Verify, the last value driven on the bus is read back
+    assert_eq!(unmapped, 0x01);
+}
+
+#[test]
+fn trap_rom_writes_test() {
+    // Setup
+    let mut map = MemoryMapBuilder::new()
+        .rom(0xF000, vec![0xFF; 0x1000])
+        .rom_writes(RomWritePolicy::Trap)
+        .build()
+        .unwrap();
+
+    // Execute
+    map.write(0xF123, 0x01);
+
+    // Verify
+    assert_eq!(map.trapped_writes(), &[(0xF123, 0x01)]);
+    assert_eq!(map.read(0xF123), 0xFF);
+}
+
+#[test]
+fn overlapping_regions_test() {
+    let result = MemoryMapBuilder::new()
+        .ram(0x0000..=0x3FFF)
+        .ram(0x3000..=0x4FFF)
+        .build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn reversed_range_test() {
+    #[allow(clippy::reversed_empty_ranges)]
+    let result = MemoryMapBuilder::new().ram(0x0010..=0x0000).build();
+    assert_eq!(
+        result.unwrap_err(),
+        EmulationError::LoadFailure {
+            address: 0x0010,
+            reason: "Region ends before it starts"
+        }
+    );
+}
+
+#[test]
+fn empty_rom_test() {
+    let result = MemoryMapBuilder::new().rom(0x8000, vec![]).build();
+    assert_eq!(
+        result.unwrap_err(),
+        EmulationError::LoadFailure {
+            address: 0x8000,
+            reason: "ROM image is empty"
+        }
+    );
+}
+
+#[test]
+fn cpu_memory_map_test() {
+    // Setup
+    let source = "
+        .org $8000
+        LDA #$07
+        STA $6002 ; VIA DDRB
+        STA $0200
+    ";
+    let (map, via) = board(assemble(source, CpuVariant::Nmos6502).unwrap().image);
+    let mut cpu = CPU::new(Rc::new(RefCell::new(map)));
+    cpu.boot_cycle();
+
+    // Execute
+    for _ in 0..3 {
+        cpu.step().unwrap().unwrap();
+    }
+
+    // Verify
+    assert_eq!(via.borrow().values[2], 0x07);
+    assert_eq!(cpu.memory_rc.borrow_mut().read(0x0200), 0x07);
+}
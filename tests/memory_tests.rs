@@ -18,12 +18,12 @@ fn memory_rom_load_test() {
     let mut virtual_memory = VirtualMemory::new();
     let test_rom = (0x80..0xFF).collect::<Vec<u8>>();
 
-    let expected_output = vec![vec![0; 0xFFFF - test_rom.len()], test_rom.clone()].concat();
+    let expected_output = [vec![0; 0xFFFF - test_rom.len()], test_rom.clone()].concat();
 
     // Execute
-    let res = virtual_memory.load_rom(test_rom.clone(), 0xFFFF - (test_rom.len() as u16));
+    let res = virtual_memory.load_rom(test_rom.clone(), 0xFFFF - (test_rom.len() as u32));
     if let Err(e) = res {
-        panic!("{}", e.get_message());
+        panic!("{}", e);
     }
 
     // Validate
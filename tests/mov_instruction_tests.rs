@@ -11,7 +11,7 @@ fn test_setup() -> CPU {
         let paddng: Vec<u8> = vec![0xEA; 0xFF00];
         let working_data: Vec<u8> = (0..0xFC).collect();
         let reset_vector: Vec<u8> = vec![0x00, 0xFF];
-        let test_rom = vec![paddng, working_data, reset_vector].concat();
+        let test_rom = [paddng, working_data, reset_vector].concat();
         memory.load_rom(test_rom, 0x00).unwrap();
     }
     let mut cpu = CPU::new(memory_rc.clone());
@@ -25,7 +25,8 @@ fn load_test() {
     let mut cpu = test_setup();
 
     // Execute
-    cpu.load_instruction(AddressingModes::Immediate, cpu.accumulator_cell.clone());
+    cpu.load_instruction(AddressingModes::Immediate, cpu.accumulator_cell.clone())
+        .unwrap();
 
     // Verify
     assert_eq!(cpu.accumulator_cell.borrow().value, 0x1);
@@ -38,7 +39,8 @@ fn store_test() {
     cpu.accumulator_cell.borrow_mut().value = 0x5;
 
     // Execute
-    cpu.store_instruction(AddressingModes::Immediate, cpu.accumulator_cell.clone());
+    cpu.store_instruction(AddressingModes::Immediate, cpu.accumulator_cell.clone())
+        .unwrap();
 
     // Verify
     assert_eq!(cpu.memory_rc.borrow()[0xFF01], 0x5);
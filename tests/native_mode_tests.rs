@@ -0,0 +1,368 @@
+mod common;
+
+use common::{assemble_program, cpu_setup, run, ORIGIN};
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::instructions::decode::{decode, Mnemonic};
+use w65xx_emulator::core::instructions::utils::AddressingModes;
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+
+fn native_setup(source: &str) -> CPU {
+    let program = assemble_program(source, CpuVariant::Wdc65C816);
+    return cpu_setup(
+        VirtualMemory::new_extended(),
+        CpuVariant::Wdc65C816,
+        ORIGIN,
+        program,
+    );
+}
+
+#[test]
+fn native_decode_test() {
+    // Verify, every opcode is assigned on the 65C816 and the Rockwell columns hold the long modes
+    for opcode in 0..=0xFF {
+        assert!(
+            decode(opcode, CpuVariant::Wdc65C816).is_some(),
+            "{:#04X}",
+            opcode
+        );
+    }
+    let instruction = decode(0x07, CpuVariant::Wdc65C816).unwrap();
+    assert_eq!(instruction.mnemonic, Mnemonic::ORA);
+    assert_eq!(
+        instruction.addressing_mode,
+        AddressingModes::ZeroPageIndirectLong
+    );
+    assert_eq!(
+        decode(0x07, CpuVariant::Wdc65C02).unwrap().mnemonic,
+        Mnemonic::RMB
+    );
+    assert_eq!(decode(0xAF, CpuVariant::Wdc65C816).unwrap().size(), 4);
+}
+
+#[test]
+fn emulation_mode_test() {
+    // Setup
+    let source = "
+        LDA #$FF
+        LDX #$10
+    ";
+    let mut cpu = native_setup(source);
+
+    // Execute
+    run(&mut cpu, 2);
+
+    // Verify, after reset it runs like a 65C02
+    assert!(!cpu.is_native());
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0xFF);
+    assert_eq!(cpu.x_cell.borrow().get_word(), 0x0010);
+    assert_eq!(cpu.program_counter.value, 0x8004);
+}
+
+#[test]
+fn wide_registers_test() {
+    // Setup
+    let source = "
+        CLC
+        XCE
+        REP #$30
+        .a16
+        .i16
+        LDA #$1234
+        LDX #$ABCD
+        TXY
+        SEP #$10
+    ";
+    let mut cpu = native_setup(source);
+
+    // Execute & Verify
+    let cycles = run(&mut cpu, 6);
+    assert!(cpu.is_native());
+    assert!(cpu.accumulator_is_wide() && cpu.index_is_wide());
+    assert_eq!(cycles, 2 + 2 + 3 + 3 + 3 + 2);
+    assert_eq!(cpu.program_counter.value, 0x800B);
+    assert_eq!(cpu.accumulator_cell.borrow().get_word(), 0x1234);
+    assert_eq!(cpu.x_cell.borrow().get_word(), 0xABCD);
+    assert_eq!(cpu.y_cell.borrow().get_word(), 0xABCD);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Negative));
+
+    // 8 bit index registers lose their high byte
+    run(&mut cpu, 1);
+    assert!(!cpu.index_is_wide());
+    assert_eq!(cpu.x_cell.borrow().get_word(), 0x00CD);
+    assert_eq!(cpu.y_cell.borrow().get_word(), 0x00CD);
+    assert_eq!(cpu.accumulator_cell.borrow().get_word(), 0x1234);
+}
+
+#[test]
+fn wide_arithmetic_test() {
+    // Setup
+    let source = "
+        CLC
+        XCE
+        REP #$20
+        .a16
+        LDA #$12FF
+        CLC
+        ADC #$0001
+
+        SEC
+        SBC #$1301
+
+        SED
+        CLC
+        LDA #$1999
+        ADC #$0001
+    ";
+    let mut cpu = native_setup(source);
+
+    // Execute & Verify
+    run(&mut cpu, 6);
+    assert_eq!(cpu.accumulator_cell.borrow().get_word(), 0x1300);
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+
+    run(&mut cpu, 2);
+    assert_eq!(cpu.accumulator_cell.borrow().get_word(), 0xFFFF);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Negative));
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+
+    run(&mut cpu, 4);
+    assert_eq!(cpu.accumulator_cell.borrow().get_word(), 0x2000);
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+}
+
+#[test]
+fn direct_page_test() {
+    // Setup
+    let source = "
+        CLC
+        XCE
+        REP #$20
+        .a16
+        LDA #$0234
+        TCD
+        SEP #$20
+        .a8
+        LDA $10
+    ";
+    let mut cpu = native_setup(source);
+    cpu.memory_rc.borrow_mut()[0x0244] = 0x42;
+
+    // Execute
+    run(&mut cpu, 6);
+    let cycles = run(&mut cpu, 1);
+
+    // Verify, the direct page is off a page boundary so the load takes an extra cycle
+    assert_eq!(cpu.direct_page, 0x0234);
+    assert_eq!(cycles, 3 + 1);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x42);
+}
+
+#[test]
+fn long_addressing_test() {
+    // Setup
+    let source = "
+        LDA $123456
+        LDY #$05
+        STA [$10],Y
+        LDA #$02
+        PHA
+        PLB
+        LDA $1000
+    ";
+    let mut cpu = native_setup(source);
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        memory.write_long(0x123456, 0x42);
+        memory.write_long(0x021000, 0x99);
+        memory.load_rom(vec![0x00, 0x00, 0x02], 0x0010).unwrap();
+    }
+
+    // Execute & Verify, the long modes work in emulation mode too
+    run(&mut cpu, 3);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x42);
+    assert_eq!(cpu.memory_rc.borrow().read_long(0x020005), 0x42);
+
+    // Absolute addresses are in the data bank
+    run(&mut cpu, 4);
+    assert_eq!(cpu.data_bank, 0x02);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x99);
+}
+
+#[test]
+fn long_subroutine_test() {
+    // Setup, with RTL at $018000
+    let mut cpu = native_setup("JSL $018000");
+    cpu.memory_rc.borrow_mut().write_long(0x018000, 0x6B);
+
+    // Execute & Verify
+    let cycles = run(&mut cpu, 1);
+    assert_eq!(cycles, 8);
+    assert_eq!(cpu.program_address(), 0x018000);
+    assert_eq!(cpu.stack_pointer.get_word(), 0x01FC);
+    {
+        let memory = cpu.memory_rc.borrow();
+        assert_eq!(memory[0x01FF], 0x00);
+        assert_eq!(memory[0x01FE], 0x80);
+        assert_eq!(memory[0x01FD], 0x03);
+    }
+
+    run(&mut cpu, 1);
+    assert_eq!(cpu.program_address(), 0x008004);
+    assert_eq!(cpu.stack_pointer.get_word(), 0x01FF);
+}
+
+#[test]
+fn native_interrupt_test() {
+    // Setup, with the native IRQ handler at $9000
+    let source = "
+        CLC
+        XCE
+    ";
+    let mut cpu = native_setup(source);
+    {
+        let mut memory = cpu.memory_rc.borrow_mut();
+        memory.load_rom(vec![0x00, 0x90], 0xFFEE).unwrap();
+        memory.load_rom(vec![0x00, 0xA0], 0xFFFE).unwrap();
+        memory[0x9000] = 0xEA;
+    }
+    run(&mut cpu, 2);
+    cpu.program_bank = 0x03;
+    cpu.memory_rc.borrow_mut().write_long(0x038002, 0xEA);
+
+    // Execute
+    cpu.pins.irq = true;
+    let cycles = run(&mut cpu, 1);
+
+    // Verify, the program bank is pushed first and the flags go without a break bit
+    assert_eq!(cycles, 8 + 2);
+    assert_eq!(cpu.program_address(), 0x009001);
+    assert_eq!(cpu.stack_pointer.get_word(), 0x01FB);
+    let memory = cpu.memory_rc.borrow();
+    assert_eq!(memory[0x01FF], 0x03);
+    assert_eq!(memory[0x01FE], 0x80);
+    assert_eq!(memory[0x01FD], 0x02);
+    assert_eq!(
+        memory[0x01FC],
+        cpu.processor_status_flags.get_flags() & !0x04
+    );
+}
+
+#[test]
+fn block_move_test() {
+    // Setup
+    let source = "
+        CLC
+        XCE
+        REP #$30
+        .a16
+        .i16
+        LDA #$0003
+        LDX #$1000
+        LDY #$2000
+        MVN $00,$00
+    ";
+    let mut cpu = native_setup(source);
+    cpu.memory_rc
+        .borrow_mut()
+        .load_rom(vec![1, 2, 3, 4], 0x1000)
+        .unwrap();
+
+    // Execute, MVN runs once per byte
+    run(&mut cpu, 6);
+    let cycles = run(&mut cpu, 4);
+
+    // Verify
+    assert_eq!(cycles, 4 * 7);
+    assert_eq!(cpu.program_counter.value, 0x8010);
+    assert_eq!(cpu.accumulator_cell.borrow().get_word(), 0xFFFF);
+    assert_eq!(cpu.x_cell.borrow().get_word(), 0x1004);
+    assert_eq!(cpu.y_cell.borrow().get_word(), 0x2004);
+    let memory = cpu.memory_rc.borrow();
+    for offset in 0..4 {
+        assert_eq!(memory[0x2000 + offset], offset as u8 + 1);
+    }
+}
+
+#[test]
+fn native_stack_test() {
+    // Setup
+    let source = "
+        CLC
+        XCE
+        REP #$30
+        .a16
+        .i16
+        PEA $1234
+        PLX
+        LDA #$0300
+        TCS
+        PHA
+        PER $8020
+    ";
+    let mut cpu = native_setup(source);
+
+    // Execute & Verify
+    run(&mut cpu, 5);
+    assert_eq!(cpu.x_cell.borrow().get_word(), 0x1234);
+    assert_eq!(cpu.stack_pointer.get_word(), 0x01FF);
+
+    // The stack can be moved anywhere in bank 0
+    run(&mut cpu, 3);
+    assert_eq!(cpu.stack_pointer.get_word(), 0x02FE);
+    {
+        let memory = cpu.memory_rc.borrow();
+        assert_eq!(memory[0x0300], 0x03);
+        assert_eq!(memory[0x02FF], 0x00);
+    }
+
+    // PER pushes the address following it plus the offset
+    run(&mut cpu, 1);
+    assert_eq!(cpu.stack_pointer.get_word(), 0x02FC);
+    assert_eq!(cpu.memory_rc.borrow().read_word(0x02FD), 0x8020);
+}
+
+#[test]
+fn return_to_emulation_test() {
+    // Setup
+    let source = "
+        CLC
+        XCE
+        REP #$30
+        .a16
+        .i16
+        LDX #$1234
+        LDA #$0300
+        TCS
+        SEC
+        XCE
+    ";
+    let mut cpu = native_setup(source);
+
+    // Execute
+    run(&mut cpu, 8);
+
+    // Verify, the registers drop back to 8 bits and the stack to page 1
+    assert!(!cpu.is_native());
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+    assert_eq!(cpu.x_cell.borrow().get_word(), 0x0034);
+    assert_eq!(cpu.accumulator_cell.borrow().get_word(), 0x0300);
+    assert_eq!(cpu.stack_pointer.get_word(), 0x0100);
+}
+
+#[test]
+fn load_above_bank_zero_test() {
+    // Setup
+    let mut cpu = native_setup("LDA $012000");
+    cpu.memory_rc
+        .borrow_mut()
+        .load_rom(vec![0x5A], 0x012000)
+        .unwrap();
+
+    // Execute
+    run(&mut cpu, 1);
+
+    // Verify
+    assert_eq!(cpu.accumulator_cell.borrow().get_word() & 0xFF, 0x5A);
+}
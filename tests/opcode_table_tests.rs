@@ -0,0 +1,76 @@
+use w65xx_emulator::core::instructions::decode::{decode, opcode_metadata, Mnemonic};
+use w65xx_emulator::core::instructions::utils::AddressingModes;
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
+
+#[test]
+fn decode_matches_table_test() {
+    for variant in [
+        CpuVariant::Nmos6502,
+        CpuVariant::Wdc65C02,
+        CpuVariant::Rockwell65C02,
+        CpuVariant::Ricoh2A03,
+        CpuVariant::Wdc65C816,
+    ] {
+        for opcode in 0..=255 {
+            // Execute
+            let instruction = decode(opcode, variant);
+            let metadata = opcode_metadata(opcode, variant);
+
+            // Verify
+            assert_eq!(instruction.is_some(), metadata.is_some());
+            if let (Some(instruction), Some(metadata)) = (instruction, metadata) {
+                assert_eq!(instruction.mnemonic, metadata.mnemonic);
+                assert_eq!(instruction.addressing_mode, metadata.addressing_mode);
+                assert_eq!(instruction.cycles, metadata.cycles);
+                assert_eq!(instruction.size(), metadata.bytes as u16);
+                assert!(metadata.variants.contains(&variant));
+            }
+        }
+    }
+}
+
+#[test]
+fn variant_rows_test() {
+    // Execute
+    let nmos = opcode_metadata(0x6C, CpuVariant::Nmos6502).unwrap();
+    let cmos = opcode_metadata(0x6C, CpuVariant::Wdc65C02).unwrap();
+
+    // Verify, the 65C02 spends a cycle fixing the page wrap bug of JMP ($xxFF)
+    assert_eq!(nmos.mnemonic, Mnemonic::JMP);
+    assert_eq!(nmos.addressing_mode, AddressingModes::Indirect);
+    assert_eq!((nmos.cycles, cmos.cycles), (5, 6));
+    assert_eq!(
+        nmos.variants,
+        &[CpuVariant::Nmos6502, CpuVariant::Ricoh2A03]
+    );
+    assert!(opcode_metadata(0xCB, CpuVariant::Wdc65C02).is_some());
+    assert!(opcode_metadata(0xCB, CpuVariant::Rockwell65C02).is_none());
+}
+
+#[test]
+fn flags_test() {
+    // Setup
+    let nvzc = StatusFlags::Negative.get_mask()
+        | StatusFlags::Overflow.get_mask()
+        | StatusFlags::Zero.get_mask()
+        | StatusFlags::Carry.get_mask();
+    let dc = StatusFlags::Decimal.get_mask() | StatusFlags::Carry.get_mask();
+
+    // Execute
+    let adc = opcode_metadata(0x69, CpuVariant::Nmos6502).unwrap();
+    let bit_immediate = opcode_metadata(0x89, CpuVariant::Wdc65C02).unwrap();
+    let sta = opcode_metadata(0x8D, CpuVariant::Nmos6502).unwrap();
+
+    // Verify
+    assert_eq!((adc.flags_read, adc.flags_written), (dc, nvzc));
+    assert_eq!(bit_immediate.flags_written, StatusFlags::Zero.get_mask());
+    assert_eq!((sta.flags_read, sta.flags_written), (0, 0));
+}
+
+#[test]
+fn description_test() {
+    // Verify
+    assert_eq!(Mnemonic::LDA.description(), "load accumulator");
+    assert_eq!(Mnemonic::STZ.description(), "store zero");
+}
@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use strum::IntoEnumIterator;
+use w65xx_emulator::core::cpu::CPU;
 use w65xx_emulator::core::register::*;
 use w65xx_emulator::peripherals::memory::VirtualMemory;
 
@@ -14,7 +15,7 @@ fn register_read_write_test() {
     accumulator.value = 0x13;
 
     // Verify
-    assert_eq!(accumulator.value, 0x13 as u8);
+    assert_eq!(accumulator.value, 0x13_u8);
 }
 
 #[test]
@@ -96,14 +97,15 @@ fn program_counter_increment_test() {
 fn stack_push_test() {
     // Setup
     let memory = Rc::new(RefCell::new(VirtualMemory::new()));
-    let mut stack_ptr = StackPointerRegister::new(0x01, 0xFF, memory.clone());
+    let mut cpu = CPU::new(memory.clone());
 
     // Execute
-    stack_ptr.push(0x01);
-    stack_ptr.push(0x02);
-    stack_ptr.push(0x03);
+    cpu.push_byte(0x01);
+    cpu.push_byte(0x02);
+    cpu.push_byte(0x03);
 
     // Verify
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xFC);
     let mem_inner = memory.borrow();
     assert_eq!(mem_inner[0x01FF], 0x01);
     assert_eq!(mem_inner[0x01FE], 0x02);
@@ -113,17 +115,18 @@ fn stack_push_test() {
 #[test]
 fn stack_pull_test() {
     let memory = Rc::new(RefCell::new(VirtualMemory::new()));
-    let mut stack_ptr = StackPointerRegister::new(0x01, 0xFF, memory.clone());
+    let mut cpu = CPU::new(memory.clone());
 
     // Execute
-    stack_ptr.push(0x01);
-    stack_ptr.push(0x02);
-    stack_ptr.push(0x03);
+    cpu.push_byte(0x01);
+    cpu.push_byte(0x02);
+    cpu.push_byte(0x03);
 
     // Verify
-    assert_eq!(stack_ptr.pop(), 0x03);
-    assert_eq!(stack_ptr.pop(), 0x02);
-    assert_eq!(stack_ptr.pop(), 0x01);
+    assert_eq!(cpu.pop_byte(), 0x03);
+    assert_eq!(cpu.pop_byte(), 0x02);
+    assert_eq!(cpu.pop_byte(), 0x01);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xFF);
 }
 
 #[test]
@@ -138,7 +141,7 @@ fn status_flag_set_test() {
 
     // Verify
     for flag in StatusFlags::iter() {
-        assert_eq!(flag_register.check_flag(flag), true);
+        assert!(flag_register.check_flag(flag));
     }
 }
 
@@ -156,8 +159,8 @@ fn status_flag_check() {
 
     // Verify
     for (mut position, flag) in StatusFlags::iter().enumerate() {
-        if position >= 5 {
-            position += 1;
+        if position >= 4 {
+            position += 2; // skip the break and unused bits
         }
         let expected_flag = (expected_flags & (1 << position)) != 0;
         assert_eq!(flag_register.check_flag(flag), expected_flag);
@@ -190,3 +193,19 @@ fn clear_flag_mask_test() {
     // Verify
     assert!(flag_register.check_mask(expected_flag_state));
 }
+
+#[test]
+fn pushed_flags_test() {
+    // Set up
+    let mut flag_register = StatusRegister::new();
+    flag_register.set_flag(StatusFlags::Carry);
+
+    // Verify, the break bit only shows up in the pushed copy
+    assert_eq!(flag_register.get_flags(), 0b0010_0001);
+    assert_eq!(flag_register.get_pushed_flags(true), 0b0011_0001);
+    assert_eq!(flag_register.get_pushed_flags(false), 0b0010_0001);
+
+    // Pulling the flags back never stores the break bit
+    flag_register.set_mask(0xFF);
+    assert_eq!(flag_register.get_flags(), 0b1110_1111);
+}
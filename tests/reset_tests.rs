@@ -0,0 +1,150 @@
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::common::PowerOnState;
+use w65xx_emulator::core::bus::Bus;
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::io::BusOperation;
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+use w65xx_emulator::peripherals::memory_map::MemoryMapBuilder;
+
+fn cpu_setup(variant: CpuVariant) -> CPU {
+    let memory_rc = Rc::new(RefCell::new(VirtualMemory::new()));
+    memory_rc
+        .borrow_mut()
+        .load_rom(vec![0x00, 0x80], 0xFFFC)
+        .unwrap();
+    return CPU::with_variant(memory_rc, variant);
+}
+
+#[test]
+fn reset_sequence_test() {
+    // Setup
+    let mut cpu = cpu_setup(CpuVariant::Nmos6502);
+    cpu.accumulator_cell.borrow_mut().value = 0x12;
+    cpu.processor_status_flags.set_flag(StatusFlags::Decimal);
+
+    // Execute
+    cpu.reset();
+
+    // Verify, the suppressed pushes move the stack pointer and nothing else is touched
+    assert_eq!(cpu.program_counter.value, 0x8000);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xFC);
+    assert_eq!(cpu.cycles, 7);
+    assert!(cpu
+        .processor_status_flags
+        .check_flag(StatusFlags::InterruptDisable));
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Decimal));
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x12);
+    assert_eq!(cpu.memory_rc.borrow()[0x01FF], 0x00);
+}
+
+#[test]
+fn cmos_reset_test() {
+    // Setup
+    let mut cpu = cpu_setup(CpuVariant::Wdc65C02);
+    cpu.processor_status_flags.set_flag(StatusFlags::Decimal);
+
+    // Execute
+    cpu.reset();
+
+    // Verify
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Decimal));
+}
+
+#[test]
+fn reset_bus_cycles_test() {
+    // Setup
+    let mut cpu = cpu_setup(CpuVariant::Nmos6502);
+    cpu.cycle_stepped = true;
+    cpu.program_counter.value = 0x1234;
+    cpu.pins.reset = true;
+    cpu.step_cycle().unwrap();
+    cpu.pins.reset = false;
+
+    // Execute
+    let cycles: Vec<(u32, BusOperation)> = (0..7)
+        .map(|_| cpu.step_cycle().unwrap())
+        .map(|cycle| (cycle.address, cycle.operation))
+        .collect();
+
+    // Verify, the stack is read instead of written
+    let expected: Vec<(u32, BusOperation)> =
+        [0x1234, 0x1234, 0x01FF, 0x01FE, 0x01FD, 0xFFFC, 0xFFFD]
+            .iter()
+            .map(|address| (*address, BusOperation::Read))
+            .collect();
+    assert_eq!(cycles, expected);
+}
+
+#[test]
+fn power_on_registers_test() {
+    // Setup
+    let mut cpu = cpu_setup(CpuVariant::Nmos6502);
+
+    // Execute
+    cpu.power_on(PowerOnState::Pattern(0xA5));
+
+    // Verify
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0xA5);
+    assert_eq!(cpu.x_cell.borrow().value, 0xA5);
+    assert_eq!(cpu.y_cell.borrow().value, 0xA5);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0xA2);
+    assert_eq!(cpu.program_counter.value, 0x8000);
+    assert!(cpu
+        .processor_status_flags
+        .check_flag(StatusFlags::InterruptDisable));
+}
+
+#[test]
+fn power_on_random_test() {
+    // Setup
+    let mut first = VirtualMemory::new();
+    let mut second = VirtualMemory::new();
+    let mut other_seed = VirtualMemory::new();
+
+    // Execute
+    first.power_on(PowerOnState::Random(42));
+    second.power_on(PowerOnState::Random(42));
+    other_seed.power_on(PowerOnState::Random(7));
+
+    // Verify, the same seed gives the same contents
+    let first_page: Vec<u8> = (0..0x100).map(|address| first[address]).collect();
+    let second_page: Vec<u8> = (0..0x100).map(|address| second[address]).collect();
+    let other_page: Vec<u8> = (0..0x100).map(|address| other_seed[address]).collect();
+    assert_eq!(first_page, second_page);
+    assert_ne!(first_page, other_page);
+    assert!(first_page.iter().any(|byte| *byte != first_page[0]));
+}
+
+#[test]
+fn power_on_seed_test() {
+    for (seed, next_seed) in [(0, 1), (42, 43), (u64::MAX - 1, u64::MAX)] {
+        // Execute
+        let first: Vec<u8> = PowerOnState::Random(seed).values().take(0x100).collect();
+        let second: Vec<u8> = PowerOnState::Random(next_seed)
+            .values()
+            .take(0x100)
+            .collect();
+
+        // Verify, seeds that only differ in the lowest bit still give different contents
+        assert_ne!(first, second, "{} {}", seed, next_seed);
+    }
+}
+
+#[test]
+fn power_on_memory_map_test() {
+    // Setup
+    let mut map = MemoryMapBuilder::new()
+        .ram(0x0000..=0x00FF)
+        .rom(0x8000, vec![0xEA; 0x10])
+        .build()
+        .unwrap();
+
+    // Execute
+    map.power_on(PowerOnState::Pattern(0x55));
+
+    // Verify, only RAM is filled
+    assert_eq!(map.read(0x0010), 0x55);
+    assert_eq!(map.read(0x8000), 0xEA);
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use common::program_setup;
+use w65xx_emulator::core::cpu::StopReason;
+use w65xx_emulator::core::error::EmulationError;
+use w65xx_emulator::core::variant::CpuVariant;
+
+// A BRK, with the NOP it skips over, then INY, and a handler at the IRQ vector that does INX
+const BREAK_SOURCE: &str = "
+        BRK
+        NOP
+        INY
+        JMP *
+
+        .org $9000
+        INX
+        RTI
+    ";
+
+#[test]
+fn self_jump_test() {
+    // Setup, multiply 3 by 5 with a loop then JMP * to signal it's done
+    let source = "
+        LDA #$00
+        LDX #$05
+loop:   CLC
+        ADC #$03
+        DEX
+        BNE loop
+        STA $10
+        JMP *
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute
+    let reason = cpu.run_until(|_| false).unwrap();
+
+    // Verify
+    assert_eq!(reason, StopReason::SelfJump(0x800C));
+    assert_eq!(cpu.memory_rc.borrow()[0x10], 15);
+    assert_eq!(cpu.program_counter.value, 0x800C);
+}
+
+#[test]
+fn branch_to_self_test() {
+    // Setup
+    let source = "
+        NOP
+        BRA *
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+
+    // Execute
+    let reason = cpu.run_for_cycles(1000).unwrap();
+
+    // Verify
+    assert_eq!(reason, StopReason::SelfJump(0x8001));
+}
+
+#[test]
+fn run_until_pc_test() {
+    // Setup
+    let source = "
+        INX
+        INX
+        INX
+        NOP
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute
+    let reason = cpu.run_until_pc(0x8003).unwrap();
+
+    // Verify, stopped before the NOP runs
+    assert_eq!(reason, StopReason::Breakpoint(0x8003));
+    assert_eq!(cpu.x_cell.borrow().value, 3);
+    assert_eq!(cpu.run_until_pc(0x8003), Ok(StopReason::Breakpoint(0x8003)));
+}
+
+#[test]
+fn run_until_condition_test() {
+    // Setup
+    let source = "
+loop:   INX
+        JMP loop
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute
+    let reason = cpu
+        .run_until(|cpu| cpu.x_cell.borrow().value == 10)
+        .unwrap();
+
+    // Verify
+    assert_eq!(reason, StopReason::Breakpoint(0x8001));
+    assert_eq!(cpu.x_cell.borrow().value, 10);
+}
+
+#[test]
+fn cycle_budget_test() {
+    // Setup, NOPs all the way
+    let mut cpu = program_setup(".res $100, $EA", CpuVariant::Nmos6502);
+    let start = cpu.cycles;
+
+    // Execute, the last NOP runs over by a cycle
+    let reason = cpu.run_for_cycles(11).unwrap();
+
+    // Verify
+    assert_eq!(reason, StopReason::CycleBudget);
+    assert_eq!(cpu.cycles - start, 12);
+    assert_eq!(cpu.program_counter.value, 0x8006);
+}
+
+#[test]
+fn instruction_budget_test() {
+    // Setup, NOPs all the way
+    let mut cpu = program_setup(".res $100, $EA", CpuVariant::Nmos6502);
+
+    // Execute
+    let reason = cpu.run_for_instructions(5).unwrap();
+
+    // Verify
+    assert_eq!(reason, StopReason::InstructionBudget);
+    assert_eq!(cpu.program_counter.value, 0x8005);
+}
+
+#[test]
+fn break_test() {
+    // Setup
+    let source = "
+        LDA #$42
+        BRK
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+    cpu.stop_on_break = true;
+
+    // Execute
+    let reason = cpu.run_for_instructions(100).unwrap();
+
+    // Verify
+    assert_eq!(reason, StopReason::Break(0x8002));
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x42);
+}
+
+#[test]
+fn break_resume_test() {
+    // Setup
+    let mut cpu = program_setup(BREAK_SOURCE, CpuVariant::Nmos6502);
+    cpu.stop_on_break = true;
+
+    // Execute, the first run stops on the BRK and the second goes through the handler and back
+    let first = cpu.run_for_cycles(1000).unwrap();
+    let second = cpu.run_for_cycles(1000).unwrap();
+
+    // Verify
+    assert_eq!(first, StopReason::Break(0x8000));
+    assert_eq!(second, StopReason::SelfJump(0x8003));
+    assert_eq!(cpu.x_cell.borrow().value, 1);
+    assert_eq!(cpu.y_cell.borrow().value, 1);
+}
+
+#[test]
+fn break_as_interrupt_test() {
+    // Setup, the same program without stopping on BRK
+    let mut cpu = program_setup(BREAK_SOURCE, CpuVariant::Nmos6502);
+
+    // Execute
+    let reason = cpu.run_for_cycles(1000).unwrap();
+
+    // Verify
+    assert_eq!(reason, StopReason::SelfJump(0x8003));
+    assert_eq!(cpu.x_cell.borrow().value, 1);
+    assert_eq!(cpu.y_cell.borrow().value, 1);
+}
+
+#[test]
+fn stop_and_jam_test() {
+    // Setup
+    let mut cmos = program_setup("NOP\nSTP", CpuVariant::Wdc65C02);
+    let mut nmos = program_setup("NOP\nJAM", CpuVariant::Nmos6502);
+
+    // Execute & Verify, running a halted CPU is an error
+    assert_eq!(cmos.run_for_cycles(100), Ok(StopReason::Stopped(0x8001)));
+    assert_eq!(nmos.run_for_cycles(100), Ok(StopReason::Jammed(0x8001)));
+    assert!(matches!(
+        nmos.run_for_cycles(100),
+        Err(EmulationError::Halted { .. })
+    ));
+}
+
+#[test]
+fn waiting_test() {
+    // Setup
+    let source = "
+        SEI
+        WAI
+        INX
+        STP
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Wdc65C02);
+
+    // Execute & Verify, comes back while waiting and carries on once an interrupt is asserted
+    assert_eq!(cpu.run_for_cycles(100), Ok(StopReason::Waiting(0x8001)));
+    assert_eq!(cpu.run_for_cycles(100), Ok(StopReason::Waiting(0x8002)));
+    cpu.pins.irq = true;
+    assert_eq!(cpu.run_for_cycles(100), Ok(StopReason::Stopped(0x8003)));
+    assert_eq!(cpu.x_cell.borrow().value, 1);
+}
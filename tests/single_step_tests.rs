@@ -0,0 +1,240 @@
+// Runner for the SingleStepTests 65x02 suites, https://github.com/SingleStepTests/65x02
+// The JSON files aren't part of the repository, copy the ones of a variant (00.json to ff.json) into
+// tests/fixtures/single_step/<variant>, where variant is the name of the directory upstream, and run
+// cargo test -- --ignored. A variant without any files fails.
+use serde::Deserialize;
+use std::{cell::RefCell, collections::BTreeMap, fs, path::PathBuf, rc::Rc};
+use w65xx_emulator::core::cpu::{IllegalOpcodePolicy, CPU};
+use w65xx_emulator::core::io::{BusCycle, BusOperation};
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+
+// The break and unused bits don't exist in the register, the suites don't agree on what to show for them
+const PHANTOM_FLAGS: u8 = 0x30;
+
+#[derive(Deserialize)]
+struct TestCase {
+    name: String,
+    initial: State,
+    #[serde(rename = "final")]
+    expected: State,
+    cycles: Vec<(u16, u8, String)>,
+}
+
+#[derive(Deserialize)]
+struct State {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+fn fixture_files(directory: &str) -> Vec<PathBuf> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("single_step")
+        .join(directory);
+    let mut files: Vec<PathBuf> = match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    return files;
+}
+
+fn cpu_setup(variant: CpuVariant) -> CPU {
+    let memory_rc = Rc::new(RefCell::new(VirtualMemory::new()));
+    let mut cpu = CPU::with_variant(memory_rc, variant);
+    // The 65C02 suites cover the unassigned opcodes too, as the NOPs the real chips run them as
+    cpu.illegal_opcodes = IllegalOpcodePolicy::Nop;
+    cpu.cycle_stepped = true;
+    return cpu;
+}
+
+fn load_state(cpu: &mut CPU, state: &State) {
+    cpu.program_counter.value = state.pc;
+    cpu.stack_pointer.set_pointer(state.s);
+    cpu.accumulator_cell.borrow_mut().value = state.a;
+    cpu.x_cell.borrow_mut().value = state.x;
+    cpu.y_cell.borrow_mut().value = state.y;
+    cpu.processor_status_flags.set_mask(state.p);
+    let mut memory = cpu.memory_rc.borrow_mut();
+    for &(address, data) in state.ram.iter() {
+        memory[address] = data;
+    }
+}
+
+// Runs one instruction a cycle at a time, returning the bus cycles it ran
+fn run_instruction(cpu: &mut CPU) -> Result<Vec<BusCycle>, String> {
+    let start = cpu.cycles;
+    let mut cycles = Vec::new();
+    loop {
+        cycles.push(cpu.step_cycle().map_err(|error| error.to_string())?);
+        if cycles.len() as u64 >= cpu.cycles - start {
+            return Ok(cycles);
+        }
+    }
+}
+
+// Describes everything that doesn't match, an empty list is a pass
+fn diff(cpu: &CPU, case: &TestCase, cycles: &[BusCycle]) -> Vec<String> {
+    let expected = &case.expected;
+    let mut differences = Vec::new();
+    let mut compare = |name: &str, actual: u16, expected: u16| {
+        if actual != expected {
+            differences.push(format!(
+                "{} is ${:02X}, expected ${:02X}",
+                name, actual, expected
+            ));
+        }
+    };
+    compare("PC", cpu.program_counter.value, expected.pc);
+    compare(
+        "S",
+        cpu.stack_pointer.get_pointer() as u16,
+        expected.s as u16,
+    );
+    compare(
+        "A",
+        cpu.accumulator_cell.borrow().value as u16,
+        expected.a as u16,
+    );
+    compare("X", cpu.x_cell.borrow().value as u16, expected.x as u16);
+    compare("Y", cpu.y_cell.borrow().value as u16, expected.y as u16);
+    compare(
+        "P",
+        (cpu.processor_status_flags.get_flags() | PHANTOM_FLAGS) as u16,
+        (expected.p | PHANTOM_FLAGS) as u16,
+    );
+    {
+        let memory = cpu.memory_rc.borrow();
+        for &(address, data) in expected.ram.iter() {
+            compare(
+                &format!("${:04X}", address),
+                memory[address] as u16,
+                data as u16,
+            );
+        }
+    }
+
+    if cycles.len() != case.cycles.len() {
+        differences.push(format!(
+            "ran {} cycles, expected {}",
+            cycles.len(),
+            case.cycles.len()
+        ));
+    }
+    // The suites record the address bus of every cycle, an internal cycle has to be a read of the same address
+    for (index, (cycle, (address, data, operation))) in
+        cycles.iter().zip(case.cycles.iter()).enumerate()
+    {
+        let matches = match cycle.operation {
+            BusOperation::Internal => operation == "read" && cycle.address == *address as u32,
+            BusOperation::Read => {
+                operation == "read" && cycle.address == *address as u32 && cycle.data == *data
+            }
+            BusOperation::Write => {
+                operation == "write" && cycle.address == *address as u32 && cycle.data == *data
+            }
+        };
+        if !matches {
+            differences.push(format!(
+                "cycle {} was {:?} ${:04X} ${:02X}, expected {} ${:04X} ${:02X}",
+                index + 1,
+                cycle.operation,
+                cycle.address,
+                cycle.data,
+                operation,
+                address,
+                data
+            ));
+        }
+    }
+    return differences;
+}
+
+// Runs every case of the variant, returning the failures per opcode as (count, first failure)
+fn run_suite(directory: &str, variant: CpuVariant) -> BTreeMap<u8, (usize, String)> {
+    let files = fixture_files(directory);
+    assert!(
+        !files.is_empty(),
+        "There are no {} tests in tests/fixtures/single_step/{}",
+        variant,
+        directory
+    );
+    let mut failures: BTreeMap<u8, (usize, String)> = BTreeMap::new();
+    for file in files {
+        let json = fs::read_to_string(&file).unwrap();
+        let cases: Vec<TestCase> = serde_json::from_str(&json)
+            .unwrap_or_else(|error| panic!("Can't parse {}: {}", file.display(), error));
+        for case in cases {
+            // A fresh CPU and memory for every case, so nothing a case leaves behind reaches the next one
+            let mut cpu = cpu_setup(variant);
+            load_state(&mut cpu, &case.initial);
+            let opcode = cpu.memory_rc.borrow()[case.initial.pc];
+            let differences = match run_instruction(&mut cpu) {
+                Ok(cycles) => diff(&cpu, &case, &cycles),
+                Err(error) => vec![error],
+            };
+            if !differences.is_empty() {
+                let failure = failures
+                    .entry(opcode)
+                    .or_insert_with(|| (0, format!("{}: {}", case.name, differences.join(", "))));
+                failure.0 += 1;
+            }
+        }
+    }
+    return failures;
+}
+
+fn assert_suite_passes(directory: &str, variant: CpuVariant) {
+    let failures = run_suite(directory, variant);
+    let summary: Vec<String> = failures
+        .iter()
+        .map(|(opcode, (count, first))| {
+            format!("${:02X}: {} failed, first {}", opcode, count, first)
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} opcodes failed on the {}\n{}",
+        failures.len(),
+        variant,
+        summary.join("\n")
+    );
+}
+
+#[test]
+#[ignore = "needs the JSON files in tests/fixtures/single_step"]
+fn nmos_single_step_test() {
+    assert_suite_passes("6502", CpuVariant::Nmos6502);
+}
+
+#[test]
+#[ignore = "needs the JSON files in tests/fixtures/single_step"]
+fn ricoh_single_step_test() {
+    assert_suite_passes("nes6502", CpuVariant::Ricoh2A03);
+}
+
+#[test]
+#[ignore = "needs the JSON files in tests/fixtures/single_step"]
+fn wdc_single_step_test() {
+    assert_suite_passes("wdc65c02", CpuVariant::Wdc65C02);
+}
+
+#[test]
+#[ignore = "needs the JSON files in tests/fixtures/single_step"]
+fn rockwell_single_step_test() {
+    assert_suite_passes("rockwell65c02", CpuVariant::Rockwell65C02);
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use common::{program_setup, run};
+use w65xx_emulator::core::cpu::RunState;
+use w65xx_emulator::core::error::EmulationError;
+use w65xx_emulator::core::instructions::decode::{decode, Mnemonic};
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
+
+#[test]
+fn undocumented_decode_test() {
+    // Verify, every NMOS opcode does something
+    for variant in [CpuVariant::Nmos6502, CpuVariant::Ricoh2A03] {
+        let undocumented = (0..=0xFF)
+            .map(|opcode| decode(opcode, variant).unwrap())
+            .filter(|instruction| instruction.undocumented)
+            .count();
+        assert_eq!(undocumented, 105);
+    }
+    let instruction = decode(0xEB, CpuVariant::Nmos6502).unwrap();
+    assert_eq!(instruction.mnemonic, Mnemonic::SBC);
+    assert!(instruction.undocumented);
+    assert!(!decode(0x07, CpuVariant::Wdc65C02).unwrap().undocumented);
+}
+
+#[test]
+fn load_store_accumulator_index_test() {
+    // Setup
+    let source = "
+        LAX $10
+        LDA #$F0
+        LDX #$3C
+        SAX $20
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+    cpu.memory_rc.borrow_mut()[0x0010] = 0x85;
+
+    // Execute & Verify
+    let cycles = run(&mut cpu, 1);
+    assert_eq!(cycles, 3);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x85);
+    assert_eq!(cpu.x_cell.borrow().value, 0x85);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Negative));
+
+    run(&mut cpu, 3);
+    assert_eq!(cpu.memory_rc.borrow()[0x0020], 0x30);
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Zero));
+    assert_eq!(cpu.program_counter.value, 0x8008);
+}
+
+#[test]
+fn read_modify_write_combined_test() {
+    // Setup
+    let source = "
+        LDA #$01
+        SLO $10
+        DCP $11
+        ISC $12
+        RRA $13
+        RLA $14
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+    cpu.memory_rc
+        .borrow_mut()
+        .load_rom(vec![0x81, 0x04, 0x00, 0x03, 0x40], 0x0010)
+        .unwrap();
+
+    // Execute & Verify, SLO
+    let cycles = run(&mut cpu, 2);
+    assert_eq!(cycles, 2 + 5);
+    assert_eq!(cpu.memory_rc.borrow()[0x0010], 0x02);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x03);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+
+    // DCP
+    run(&mut cpu, 1);
+    assert_eq!(cpu.memory_rc.borrow()[0x0011], 0x03);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Zero));
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+
+    // ISC
+    run(&mut cpu, 1);
+    assert_eq!(cpu.memory_rc.borrow()[0x0012], 0x01);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x02);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+
+    // RRA, the carry rotated out goes into the addition
+    run(&mut cpu, 1);
+    assert_eq!(cpu.memory_rc.borrow()[0x0013], 0x81);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x84);
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+
+    // RLA
+    run(&mut cpu, 1);
+    assert_eq!(cpu.memory_rc.borrow()[0x0014], 0x80);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x80);
+    assert_eq!(cpu.program_counter.value, 0x800C);
+}
+
+#[test]
+fn immediate_combined_test() {
+    // Setup
+    let source = "
+        LDA #$FF
+        ANC #$80
+
+        LDA #$FF
+        ALR #$03
+
+        LDA #$C0
+        CLC
+        ARR #$FF
+
+        LDA #$0F
+        LDX #$03
+        SBX #$01
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute & Verify, ANC
+    run(&mut cpu, 2);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x80);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+
+    // ALR
+    let cycles = run(&mut cpu, 2);
+    assert_eq!(cycles, 2 + 2);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x01);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+    assert_eq!(cpu.program_counter.value, 0x8008);
+
+    // ARR
+    run(&mut cpu, 3);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x60);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+    assert!(!cpu.processor_status_flags.check_flag(StatusFlags::Overflow));
+
+    // SBX
+    run(&mut cpu, 3);
+    assert_eq!(cpu.x_cell.borrow().value, 0x02);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x0F);
+    assert!(cpu.processor_status_flags.check_flag(StatusFlags::Carry));
+}
+
+#[test]
+fn magic_constant_test() {
+    // Setup
+    let source = "
+        LDA #$00
+        LDX #$0F
+        XAA #$3C
+        LDA #$F0
+        LXA #$3C
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute & Verify
+    cpu.magic_constant = 0xFF;
+    run(&mut cpu, 3);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x0C);
+
+    cpu.magic_constant = 0x00;
+    run(&mut cpu, 2);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x30);
+    assert_eq!(cpu.x_cell.borrow().value, 0x30);
+}
+
+#[test]
+fn store_high_byte_test() {
+    // Setup
+    let source = "
+        LDX #$FF
+        SHX $1200,Y
+        LDX #$05
+        LDY #$01
+        SHX $12FF,Y
+        LAS $2000,Y
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+    cpu.memory_rc.borrow_mut()[0x2001] = 0x5A;
+
+    // Execute & Verify, X AND the high byte plus one
+    run(&mut cpu, 2);
+    assert_eq!(cpu.memory_rc.borrow()[0x1200], 0x13);
+
+    // Crossing a page also replaces the high byte of the address
+    run(&mut cpu, 3);
+    assert_eq!(cpu.memory_rc.borrow()[0x0100], 0x01);
+    assert_eq!(cpu.memory_rc.borrow()[0x1300], 0x00);
+
+    // LAS
+    run(&mut cpu, 1);
+    assert_eq!(cpu.accumulator_cell.borrow().value, 0x5A);
+    assert_eq!(cpu.x_cell.borrow().value, 0x5A);
+    assert_eq!(cpu.stack_pointer.get_pointer(), 0x5A);
+}
+
+#[test]
+fn undocumented_nop_test() {
+    // Setup
+    let source = "
+        LDX #$FF
+        NOP $1234,X
+        NOP #$00
+        NOP $10
+    ";
+    let mut cpu = program_setup(source, CpuVariant::Nmos6502);
+
+    // Execute & Verify
+    run(&mut cpu, 1);
+    assert_eq!(run(&mut cpu, 1), 4 + 1);
+    assert_eq!(run(&mut cpu, 1), 2);
+    assert_eq!(run(&mut cpu, 1), 3);
+    assert_eq!(cpu.program_counter.value, 0x8009);
+}
+
+#[test]
+fn jam_test() {
+    // Setup
+    let mut cpu = program_setup("JAM", CpuVariant::Nmos6502);
+
+    // Execute & Verify, the CPU stops on the opcode
+    assert_eq!(cpu.step().unwrap().unwrap().mnemonic, Mnemonic::JAM);
+    assert_eq!(cpu.run_state, RunState::Jammed);
+    assert_eq!(cpu.program_counter.value, 0x8000);
+    cpu.pins.irq = true;
+    cpu.pins.set_nmi(true);
+    assert_eq!(
+        cpu.step(),
+        Err(EmulationError::Halted {
+            state: RunState::Jammed,
+            address: 0x8000
+        })
+    );
+
+    // Only a reset gets it going again
+    cpu.pins.reset = true;
+    assert!(cpu.step().unwrap().is_none());
+    cpu.pins.reset = false;
+    cpu.step().unwrap().unwrap();
+    assert_eq!(cpu.run_state, RunState::Jammed);
+    assert_eq!(cpu.program_counter.value, 0x8000);
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use common::program_setup;
+use std::{cell::RefCell, rc::Rc};
+use w65xx_emulator::core::cpu::CPU;
+use w65xx_emulator::core::instructions::decode::decode;
+use w65xx_emulator::core::register::StatusFlags;
+use w65xx_emulator::core::variant::CpuVariant;
+use w65xx_emulator::peripherals::memory::VirtualMemory;
+
+#[test]
+fn default_variant_test() {
+    let cpu = CPU::new(Rc::new(RefCell::new(VirtualMemory::new())));
+    assert_eq!(cpu.variant, CpuVariant::Nmos6502);
+}
+
+#[test]
+fn indirect_jump_bug_test() {
+    // JMP ($30FF), the pointer's low byte is at 0x30FF, the high byte at 0x3100 or 0x3000 (NMOS)
+    for (variant, expected_pc) in [
+        (CpuVariant::Nmos6502, 0x4050),
+        (CpuVariant::Ricoh2A03, 0x4050),
+        (CpuVariant::Wdc65C02, 0x5050),
+        (CpuVariant::Rockwell65C02, 0x5050),
+    ] {
+        // Setup
+        let mut cpu = program_setup("JMP ($30FF)", variant);
+        {
+            let mut memory = cpu.memory_rc.borrow_mut();
+            memory[0x30FF] = 0x50;
+            memory[0x3000] = 0x40;
+            memory[0x3100] = 0x50;
+        }
+
+        // Execute
+        cpu.step().unwrap().unwrap();
+
+        // Verify
+        assert_eq!(cpu.program_counter.value, expected_pc, "{}", variant);
+    }
+}
+
+#[test]
+fn decimal_disabled_test() {
+    let source = "
+        SED
+        CLC
+        LDA #$09
+        ADC #$01
+    ";
+    for (variant, expected) in [
+        (CpuVariant::Nmos6502, 0x10),
+        (CpuVariant::Wdc65C02, 0x10),
+        (CpuVariant::Ricoh2A03, 0x0A),
+    ] {
+        // Setup
+        let mut cpu = program_setup(source, variant);
+
+        // Execute
+        for _ in 0..4 {
+            cpu.step().unwrap().unwrap();
+        }
+
+        // Verify
+        assert!(cpu.processor_status_flags.check_flag(StatusFlags::Decimal));
+        assert_eq!(cpu.accumulator_cell.borrow().value, expected, "{}", variant);
+    }
+}
+
+#[test]
+fn interrupt_clears_decimal_test() {
+    let source = "
+        SED
+        BRK
+    ";
+    for (variant, decimal_cleared) in [
+        (CpuVariant::Nmos6502, false),
+        (CpuVariant::Ricoh2A03, false),
+        (CpuVariant::Wdc65C02, true),
+        (CpuVariant::Rockwell65C02, true),
+    ] {
+        // Setup
+        let mut cpu = program_setup(source, variant);
+
+        // Execute
+        cpu.step().unwrap().unwrap();
+        cpu.step().unwrap().unwrap();
+
+        // Verify
+        assert_eq!(cpu.program_counter.value, 0x9000);
+        assert_eq!(
+            !cpu.processor_status_flags.check_flag(StatusFlags::Decimal),
+            decimal_cleared,
+            "{}",
+            variant
+        );
+        // The pushed copy keeps the flag either way
+        let pushed_flags = cpu.memory_rc.borrow()[0x01FD];
+        assert_ne!(pushed_flags & StatusFlags::Decimal.get_mask(), 0);
+    }
+}
+
+#[test]
+fn cmos_timing_test() {
+    // JMP ($1234) and ROL $1234,X
+    let nmos_jump = decode(0x6C, CpuVariant::Nmos6502).unwrap();
+    let cmos_jump = decode(0x6C, CpuVariant::Wdc65C02).unwrap();
+    assert_eq!(nmos_jump.cycles, 5);
+    assert_eq!(cmos_jump.cycles, 6);
+
+    let nmos_rotate = decode(0x3E, CpuVariant::Nmos6502).unwrap();
+    let cmos_rotate = decode(0x3E, CpuVariant::Rockwell65C02).unwrap();
+    assert_eq!((nmos_rotate.cycles, nmos_rotate.page_penalty), (7, false));
+    assert_eq!((cmos_rotate.cycles, cmos_rotate.page_penalty), (6, true));
+}